@@ -0,0 +1,210 @@
+use std::{fmt, str::FromStr};
+
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+
+use crate::error::{Result, UnmintError};
+
+/// Decimals of SOL when expressed in lamports
+pub const SOL_DECIMALS: u8 = 9;
+
+/// An exact token amount in the smallest unit of a mint
+///
+/// # Example
+/// ```
+/// use sol_unmint::TokenAmount;
+///
+/// let amount = TokenAmount::parse("0.1", 6).unwrap();
+/// assert_eq!(amount.raw(), 100_000);
+/// assert_eq!(amount.to_string(), "0.1");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TokenAmount {
+    raw: u64,
+    decimals: u8,
+}
+
+impl TokenAmount {
+    pub fn from_raw(raw: u64, decimals: u8) -> Self {
+        Self { raw, decimals }
+    }
+
+    /// Parses a decimal string such as `"12.5"` against `decimals`
+    ///
+    /// Fails when the string has more fractional digits than the mint allows
+    /// (trailing zeros excepted) or when the result does not fit in a `u64`.
+    pub fn parse(amount: &str, decimals: u8) -> Result<Self> {
+        let invalid = |reason: &str| UnmintError::InvalidAmount(format!("{}: {}", amount, reason));
+
+        let trimmed = amount.trim();
+        let (int_part, frac_part) = trimmed.split_once('.').unwrap_or((trimmed, ""));
+
+        if int_part.is_empty() && frac_part.is_empty() {
+            return Err(invalid("empty amount"));
+        }
+        if !int_part
+            .bytes()
+            .chain(frac_part.bytes())
+            .all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid("not a positive decimal number"));
+        }
+
+        let frac_part = frac_part.trim_end_matches('0');
+        if frac_part.len() > decimals as usize {
+            return Err(invalid(&format!(
+                "more than {} fractional digits",
+                decimals
+            )));
+        }
+
+        let padding = decimals as usize - frac_part.len();
+        let digits = int_part
+            .bytes()
+            .chain(frac_part.bytes())
+            .chain(std::iter::repeat_n(b'0', padding));
+
+        let mut raw: u64 = 0;
+        for digit in digits {
+            raw = raw
+                .checked_mul(10)
+                .and_then(|r| r.checked_add((digit - b'0') as u64))
+                .ok_or_else(|| invalid("amount overflows u64"))?;
+        }
+
+        Ok(Self { raw, decimals })
+    }
+
+    /// Amount in the smallest unit of the mint
+    pub fn raw(&self) -> u64 {
+        self.raw
+    }
+
+    pub fn decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    /// Formats the amount the way RPC nodes format `uiAmountString`
+    pub fn to_ui_string(&self) -> String {
+        format_units(self.raw, self.decimals)
+    }
+}
+
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_ui_string())
+    }
+}
+
+/// An exact amount of lamports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Lamports(pub u64);
+
+impl Lamports {
+    /// Parses a SOL amount such as `"0.001"`
+    pub fn from_sol_str(amount_sol: &str) -> Result<Self> {
+        Ok(Lamports(
+            TokenAmount::parse(amount_sol, SOL_DECIMALS)?.raw(),
+        ))
+    }
+
+    /// Formats the amount in SOL
+    pub fn to_sol_string(&self) -> String {
+        format_units(self.0, SOL_DECIMALS)
+    }
+
+    pub fn to_sol(&self) -> f64 {
+        self.0 as f64 / LAMPORTS_PER_SOL as f64
+    }
+}
+
+impl FromStr for Lamports {
+    type Err = UnmintError;
+
+    fn from_str(amount_sol: &str) -> Result<Self> {
+        Lamports::from_sol_str(amount_sol)
+    }
+}
+
+impl fmt::Display for Lamports {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} SOL", self.to_sol_string())
+    }
+}
+
+/// Amount accepted by the send APIs
+///
+/// A float or decimal string is interpreted in UI units and converted exactly
+/// against the mint decimals; `Raw` is already in the smallest unit.
+#[derive(Debug, Clone, Copy)]
+pub enum AmountInput<'a> {
+    Ui(f64),
+    Decimal(&'a str),
+    Raw(u64),
+    Token(TokenAmount),
+}
+
+impl From<f64> for AmountInput<'_> {
+    fn from(amount: f64) -> Self {
+        AmountInput::Ui(amount)
+    }
+}
+
+impl<'a> From<&'a str> for AmountInput<'a> {
+    fn from(amount: &'a str) -> Self {
+        AmountInput::Decimal(amount)
+    }
+}
+
+impl From<u64> for AmountInput<'_> {
+    fn from(amount: u64) -> Self {
+        AmountInput::Raw(amount)
+    }
+}
+
+impl From<TokenAmount> for AmountInput<'_> {
+    fn from(amount: TokenAmount) -> Self {
+        AmountInput::Token(amount)
+    }
+}
+
+impl From<Lamports> for AmountInput<'_> {
+    fn from(amount: Lamports) -> Self {
+        AmountInput::Token(TokenAmount::from_raw(amount.0, SOL_DECIMALS))
+    }
+}
+
+impl AmountInput<'_> {
+    /// Resolves the amount to the smallest unit of a mint with `decimals`
+    pub fn to_token_amount(&self, decimals: u8) -> Result<TokenAmount> {
+        match self {
+            AmountInput::Ui(amount) => {
+                if !amount.is_finite() || *amount < 0.0 {
+                    return Err(UnmintError::InvalidAmount(amount.to_string()));
+                }
+                // `Display` for f64 prints the shortest exact decimal, so 0.1 stays 0.1
+                TokenAmount::parse(&amount.to_string(), decimals)
+            }
+            AmountInput::Decimal(amount) => TokenAmount::parse(amount, decimals),
+            AmountInput::Raw(raw) => Ok(TokenAmount::from_raw(*raw, decimals)),
+            AmountInput::Token(amount) if amount.decimals() == decimals => Ok(*amount),
+            AmountInput::Token(amount) => Err(UnmintError::InvalidAmount(format!(
+                "{} has {} decimals, mint has {}",
+                amount,
+                amount.decimals(),
+                decimals
+            ))),
+        }
+    }
+}
+
+fn format_units(raw: u64, decimals: u8) -> String {
+    let digits = format!("{:0>width$}", raw, width = decimals as usize + 1);
+    let (int_part, frac_part) = digits.split_at(digits.len() - decimals as usize);
+    let frac_part = frac_part.trim_end_matches('0');
+
+    if frac_part.is_empty() {
+        int_part.to_string()
+    } else {
+        format!("{}.{}", int_part, frac_part)
+    }
+}
@@ -0,0 +1,608 @@
+use std::{sync::Arc, time::Duration};
+
+use futures::{StreamExt, stream};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    account::Account, commitment_config::CommitmentConfig, instruction::Instruction,
+    message::Message, pubkey::Pubkey, signature::Signature, signer::Signer,
+    signer::keypair::Keypair, transaction::Transaction,
+};
+use spl_token_2022::state::Account as TokenAccount;
+
+use crate::{
+    amount::{AmountInput, SOL_DECIMALS, TokenAmount},
+    error::{Result, UnmintError, parse_pubkey},
+    instructions::{
+        self, BuiltSend, CONFIRM_POLL_INTERVAL, ConfirmAnswer, ConfirmQuery, Confirmation,
+        DrainMode, PriorityFee, Signers, SolDrain, TokenSend,
+    },
+    mint::{MintCache, MintInfo},
+    policy::Policy,
+    scan::{MAX_MULTIPLE_ACCOUNTS, OwnedTokenAccount},
+    token_program::TokenProgram,
+    transfer_fee::{FeeMode, TokenTransfer},
+    unmint::{PubkeyInput, UiTokenAmount},
+};
+
+/// One `send_and_close` call for `AsyncUnmint::send_and_close_many`
+#[derive(Debug, Clone)]
+pub struct SendAndClose<'a> {
+    pub from_base58_string: &'a str,
+    pub to_address: &'a str,
+    pub token_mint_address: &'a str,
+    pub fee_payer_base58_string: Option<&'a str>,
+    pub memo: Option<&'a str>,
+}
+
+/// Async counterpart of `Unmint` built on the nonblocking `RpcClient`
+///
+/// Every operation builds the same instructions as `Unmint`; only the RPC
+/// calls differ.
+#[derive(Clone)]
+pub struct AsyncUnmint {
+    pub(crate) client: Arc<RpcClient>,
+    pub(crate) token_program: TokenProgram,
+    priority_fee: Option<PriorityFee>,
+    mints: Arc<MintCache>,
+    pub(crate) allow_owner_off_curve: bool,
+    policy: Option<Arc<Policy>>,
+}
+
+impl AsyncUnmint {
+    pub fn new(rpc_url: &str, token_program: TokenProgram) -> Self {
+        let client =
+            RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::confirmed());
+        Self {
+            client: client.into(),
+            token_program,
+            priority_fee: None,
+            mints: Arc::default(),
+            allow_owner_off_curve: false,
+            policy: None,
+        }
+    }
+
+    /// Prepends compute unit limit and price instructions to every transaction
+    pub fn with_priority_fee(mut self, priority_fee: PriorityFee) -> Self {
+        self.priority_fee = Some(priority_fee);
+        self
+    }
+
+    /// Async counterpart of `Unmint::with_mint_cache_ttl`
+    pub fn with_mint_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.mints = Arc::new(MintCache::new(ttl));
+        self
+    }
+
+    /// Async counterpart of `Unmint::with_allow_owner_off_curve`
+    pub fn with_allow_owner_off_curve(mut self, allow: bool) -> Self {
+        self.allow_owner_off_curve = allow;
+        self
+    }
+
+    /// Checks token sends, SOL transfers, `send_and_close` and
+    /// `send_and_close_many` against `policy`, through the plan of what each
+    /// call sends
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Signs `instructions` and sends them, decoding any failure into `UnmintError`
+    async fn send_instructions(
+        &self,
+        instructions: &[Instruction],
+        signers: &Signers,
+    ) -> Result<Signature> {
+        let blockhash = self.client.get_latest_blockhash().await?;
+        let message = instructions::build_message(
+            instructions,
+            &signers.fee_payer.pubkey(),
+            &blockhash,
+            self.priority_fee.as_ref(),
+        );
+
+        self.send_message(message, signers).await
+    }
+
+    async fn send_message(&self, message: Message, signers: &Signers) -> Result<Signature> {
+        self.preflight(&message).await?;
+
+        let transaction = signers.sign(message)?;
+        self.send_transaction(&transaction).await
+    }
+
+    /// Async counterpart of `Unmint::send_transaction`
+    async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+        let message = &transaction.message;
+        let decode = |e| UnmintError::from_client_error(e, Some(message));
+        let commitment = self.client.commitment();
+
+        let signature = self
+            .client
+            .send_transaction(transaction)
+            .await
+            .map_err(decode)?;
+        let mut confirmation = Confirmation::new(signature, message);
+        loop {
+            let answer = match confirmation.query() {
+                ConfirmQuery::Status => ConfirmAnswer::Status(
+                    self.client
+                        .get_signature_status_with_commitment(&signature, commitment)
+                        .await
+                        .map_err(decode)?,
+                ),
+                ConfirmQuery::BlockhashValid => ConfirmAnswer::BlockhashValid(
+                    self.client
+                        .is_blockhash_valid(&message.recent_blockhash, commitment)
+                        .await
+                        .map_err(decode)?,
+                ),
+                ConfirmQuery::History => ConfirmAnswer::Status(
+                    self.client
+                        .get_signature_status_with_commitment_and_history(
+                            &signature, commitment, true,
+                        )
+                        .await
+                        .map_err(decode)?,
+                ),
+                ConfirmQuery::Rebroadcast => {
+                    tokio::time::sleep(CONFIRM_POLL_INTERVAL).await;
+                    let _ = self.client.send_transaction(transaction).await;
+                    ConfirmAnswer::Rebroadcast
+                }
+            };
+            if let Some(result) = confirmation.answer(answer) {
+                return result;
+            }
+        }
+    }
+
+    /// Async counterpart of `Unmint::multiple_accounts`
+    pub(crate) async fn multiple_accounts(
+        &self,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>> {
+        let mut accounts = Vec::with_capacity(addresses.len());
+
+        for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            accounts.extend(self.client.get_multiple_accounts(chunk).await?);
+        }
+
+        Ok(accounts)
+    }
+
+    /// Async counterpart of `Unmint::fetch_account`
+    pub(crate) async fn fetch_account(&self, address: &Pubkey) -> Result<Option<Account>> {
+        Ok(self
+            .client
+            .get_account_with_commitment(address, self.client.commitment())
+            .await?
+            .value)
+    }
+
+    /// Fetches a token account and checks it can be moved by `token_program`
+    async fn token_account(&self, address: &Pubkey) -> Result<TokenAccount> {
+        let account = self
+            .fetch_account(address)
+            .await?
+            .ok_or(UnmintError::AccountNotFound(*address))?;
+
+        instructions::checked_token_account(&self.token_program, address, &account)
+    }
+
+    /// Async counterpart of `Unmint::transfer_ix`
+    #[allow(clippy::too_many_arguments)]
+    async fn transfer_ix(
+        &self,
+        from: &Pubkey,
+        to: &Pubkey,
+        authority: &Keypair,
+        amount: u64,
+        decimals: u8,
+        mint: &Pubkey,
+        fee: Option<u64>,
+    ) -> Result<Instruction> {
+        let fetch = |address: Pubkey| async move {
+            self.client
+                .get_account_with_commitment(&address, self.client.commitment())
+                .await
+                .map(|response| response.value.map(|account| account.data))
+                .map_err(|e| e.into())
+        };
+
+        Ok(self
+            .token_program
+            .transfer_ix_with_extra_metas(from, to, authority, amount, decimals, mint, fee, fetch)
+            .await?)
+    }
+
+    /// Async counterpart of `Unmint::fee_epoch`
+    async fn fee_epoch(&self, mint: &MintInfo) -> Result<Option<u64>> {
+        if mint.transfer_fee_config().is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(self.client.get_epoch_info().await?.epoch))
+    }
+
+    /// Async counterpart of `Unmint::token_send`
+    async fn token_send(
+        &self,
+        from: &Pubkey,
+        to: &Pubkey,
+        token_mint_pubkey: &Pubkey,
+    ) -> Result<TokenSend> {
+        let source = self.token_program.ata(from, token_mint_pubkey);
+        let mint = self.cached_mint_info(token_mint_pubkey).await?;
+        let balance = self.token_account(&source).await?.amount;
+        let destination = self.destination(to, token_mint_pubkey).await?;
+        let epoch = self.fee_epoch(&mint).await?;
+
+        Ok(TokenSend {
+            mint,
+            source,
+            balance,
+            destination,
+            epoch,
+        })
+    }
+
+    /// Async counterpart of `Unmint::send_token_instruction`
+    #[allow(clippy::too_many_arguments)]
+    async fn send_token_instruction(
+        &self,
+        signers: &Signers,
+        ata_payer: &Pubkey,
+        to_pubkey: &Pubkey,
+        token_mint_pubkey: &Pubkey,
+        amount: Option<AmountInput<'_>>,
+        mode: FeeMode,
+        memo: Option<&str>,
+    ) -> Result<BuiltSend> {
+        let send = self
+            .token_send(&signers.from.pubkey(), to_pubkey, token_mint_pubkey)
+            .await?;
+        let quote = send.quote(amount, mode, memo)?;
+
+        let transfer_ix = self
+            .transfer_ix(
+                &send.source,
+                &send.destination.address,
+                &signers.from,
+                quote.sent,
+                send.mint.decimals,
+                token_mint_pubkey,
+                quote.fee,
+            )
+            .await?;
+
+        Ok(send.build(
+            &self.token_program,
+            signers,
+            ata_payer,
+            quote,
+            memo,
+            transfer_ix,
+        ))
+    }
+
+    /// Async counterpart of `Unmint::send_and_close`
+    pub async fn send_and_close(
+        &self,
+        from_base58_string: &str,
+        to_address: &str,
+        token_mint_address: &str,
+        fee_payer_base58_string: Option<&str>,
+        memo: Option<&str>,
+    ) -> Result<Signature> {
+        let to_address_pubkey = parse_pubkey(to_address)?;
+        let token_mint_pubkey = parse_pubkey(token_mint_address)?;
+        let signers = Signers::parse(from_base58_string, fee_payer_base58_string)?;
+
+        let send = self
+            .token_send(
+                &signers.from.pubkey(),
+                &to_address_pubkey,
+                &token_mint_pubkey,
+            )
+            .await?;
+        let quote = send.quote(None, FeeMode::SendExact, memo)?;
+        let transfer_ix = self
+            .transfer_ix(
+                &send.source,
+                &send.destination.address,
+                &signers.from,
+                quote.sent,
+                send.mint.decimals,
+                &token_mint_pubkey,
+                quote.fee,
+            )
+            .await?;
+        let (instructions, plan) =
+            send.send_and_close(&self.token_program, &signers, &quote, memo, transfer_ix)?;
+        if let Some(policy) = &self.policy {
+            policy.check(&plan)?;
+        }
+
+        self.send_instructions(&instructions, &signers).await
+    }
+
+    /// Runs `send_and_close` for every job with at most `concurrency` in flight
+    ///
+    /// Results are returned in the same order as `jobs`.
+    pub async fn send_and_close_many(
+        &self,
+        jobs: &[SendAndClose<'_>],
+        concurrency: usize,
+    ) -> Vec<Result<Signature>> {
+        stream::iter(jobs)
+            .map(|job| {
+                self.send_and_close(
+                    job.from_base58_string,
+                    job.to_address,
+                    job.token_mint_address,
+                    job.fee_payer_base58_string,
+                    job.memo,
+                )
+            })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Async counterpart of `Unmint::send_max_token`
+    pub async fn send_max_token(
+        &self,
+        from_base58_string: &str,
+        to_address: &str,
+        token_mint_address: &str,
+        fee_payer_base58_string: Option<&str>,
+        memo: Option<&str>,
+    ) -> Result<Signature> {
+        let to_pubkey = parse_pubkey(to_address)?;
+        let token_mint_pubkey = parse_pubkey(token_mint_address)?;
+        let signers = Signers::parse(from_base58_string, fee_payer_base58_string)?;
+
+        let send = self
+            .send_token_instruction(
+                &signers,
+                &signers.from.pubkey(),
+                &to_pubkey,
+                &token_mint_pubkey,
+                None,
+                FeeMode::SendExact,
+                memo,
+            )
+            .await?;
+        if let Some(policy) = &self.policy {
+            policy.check(&send.plan)?;
+        }
+
+        self.send_instructions(&send.instructions, &signers).await
+    }
+
+    /// Async counterpart of `Unmint::mint_info`
+    pub async fn mint_info<'a, M>(&self, mint: M) -> Result<MintInfo>
+    where
+        M: Into<PubkeyInput<'a>>,
+    {
+        let mint_pubkey = mint.into().to_pubkey()?;
+
+        let account = self
+            .fetch_account(&mint_pubkey)
+            .await?
+            .ok_or(UnmintError::AccountNotFound(mint_pubkey))?;
+
+        let info = MintInfo::decode(mint_pubkey, &account)?;
+        self.mints.insert(info.clone());
+
+        Ok(info)
+    }
+
+    /// Async counterpart of `Unmint::cached_mint_info`
+    pub(crate) async fn cached_mint_info(&self, mint: &Pubkey) -> Result<MintInfo> {
+        match self.mints.get(mint) {
+            Some(info) => Ok(info),
+            None => self.mint_info(mint).await,
+        }
+    }
+
+    /// Async counterpart of `Unmint::balance`
+    pub async fn balance<'a, A, M>(
+        &self,
+        address: A,
+        token_mint_address: M,
+    ) -> Result<UiTokenAmount>
+    where
+        A: Into<PubkeyInput<'a>>,
+        M: Into<PubkeyInput<'a>>,
+    {
+        let address_pubkey = address.into().to_pubkey()?;
+        let token_mint_pubkey = token_mint_address.into().to_pubkey()?;
+
+        let ata_sender = self.token_program.ata(&address_pubkey, &token_mint_pubkey);
+
+        let account = self
+            .fetch_account(&ata_sender)
+            .await?
+            .ok_or(UnmintError::AccountNotFound(ata_sender))?;
+        let token_account = OwnedTokenAccount::decode(ata_sender, &account)?;
+        let mint = self.cached_mint_info(&token_mint_pubkey).await?;
+
+        Ok(TokenAmount::from_raw(token_account.account.amount, mint.decimals).into())
+    }
+
+    /// Fetches the balance of every mint in `token_mint_addresses` with at most
+    /// `concurrency` requests in flight
+    ///
+    /// Results are returned in the same order as `token_mint_addresses`.
+    pub async fn balances(
+        &self,
+        address: &str,
+        token_mint_addresses: &[&str],
+        concurrency: usize,
+    ) -> Vec<Result<UiTokenAmount>> {
+        stream::iter(token_mint_addresses)
+            .map(|mint| self.balance(address, *mint))
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Async counterpart of `Unmint::balance_sol`
+    pub async fn balance_sol<'a, A>(&self, address: A) -> Result<f64>
+    where
+        A: Into<PubkeyInput<'a>>,
+    {
+        let address_pubkey = address.into().to_pubkey()?;
+        let lamports = self.client.get_balance(&address_pubkey).await?;
+        Ok(lamports as f64 / solana_sdk::native_token::LAMPORTS_PER_SOL as f64)
+    }
+
+    /// Async counterpart of `Unmint::transfer_sol`
+    pub async fn transfer_sol<'a, A>(
+        &self,
+        from_base58_string: &str,
+        to: &str,
+        amount_sol: A,
+        memo: Option<&str>,
+    ) -> Result<Signature>
+    where
+        A: Into<AmountInput<'a>>,
+    {
+        let signers = Signers::parse(from_base58_string, None)?;
+        let to_pubkey = parse_pubkey(to)?;
+        let lamports = amount_sol.into().to_token_amount(SOL_DECIMALS)?.raw();
+        if let Some(policy) = &self.policy {
+            let from = signers.from.pubkey();
+            policy.check(&instructions::sol_transfer_plan(
+                &from,
+                &from,
+                &to_pubkey,
+                [lamports],
+            ))?;
+        }
+
+        let instructions = instructions::transfer_sol_instructions(
+            &signers.from.pubkey(),
+            &to_pubkey,
+            lamports,
+            memo,
+        );
+
+        self.send_instructions(&instructions, &signers).await
+    }
+
+    /// Async counterpart of `Unmint::transfer_max_sol`
+    pub async fn transfer_max_sol(
+        &self,
+        from_base58_string: &str,
+        to: &str,
+        mode: DrainMode,
+    ) -> Result<Signature> {
+        let signers = Signers::parse(from_base58_string, None)?;
+        let from_pubkey = signers.from.pubkey();
+        let to_pubkey = parse_pubkey(to)?;
+
+        let balance = self.client.get_balance(&from_pubkey).await?;
+        let reserve = match mode {
+            DrainMode::KeepRentExempt => {
+                self.client
+                    .get_minimum_balance_for_rent_exemption(0)
+                    .await?
+            }
+            DrainMode::Full => 0,
+        };
+
+        let drain = SolDrain {
+            from: from_pubkey,
+            to: to_pubkey,
+            balance,
+            reserve,
+            blockhash: self.client.get_latest_blockhash().await?,
+            priority_fee: self.priority_fee.as_ref(),
+        };
+        let fee = self
+            .client
+            .get_fee_for_message(&drain.fee_message())
+            .await?;
+        let (lamports, message) = drain.finish(fee)?;
+        if let Some(policy) = &self.policy {
+            policy.check(&instructions::sol_transfer_plan(
+                &from_pubkey,
+                &from_pubkey,
+                &to_pubkey,
+                [lamports],
+            ))?;
+        }
+
+        self.send_message(message, &signers).await
+    }
+
+    /// Async counterpart of `Unmint::send_token`
+    pub async fn send_token<'a, A>(
+        &self,
+        from_base58_string: &str,
+        to_address: &str,
+        token_mint_address: &str,
+        amount: A,
+        fee_payer_base58_string: Option<&str>,
+        memo: Option<&str>,
+    ) -> Result<Signature>
+    where
+        A: Into<AmountInput<'a>>,
+    {
+        self.send_token_with_fee(
+            from_base58_string,
+            to_address,
+            token_mint_address,
+            amount,
+            FeeMode::SendExact,
+            fee_payer_base58_string,
+            memo,
+        )
+        .await
+        .map(|transfer| transfer.signature)
+    }
+
+    /// Async counterpart of `Unmint::send_token_with_fee`
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_token_with_fee<'a, A>(
+        &self,
+        from_base58_string: &str,
+        to_address: &str,
+        token_mint_address: &str,
+        amount: A,
+        mode: FeeMode,
+        fee_payer_base58_string: Option<&str>,
+        memo: Option<&str>,
+    ) -> Result<TokenTransfer>
+    where
+        A: Into<AmountInput<'a>>,
+    {
+        let to_pubkey = parse_pubkey(to_address)?;
+        let token_mint_pubkey = parse_pubkey(token_mint_address)?;
+        let signers = Signers::parse(from_base58_string, fee_payer_base58_string)?;
+
+        let send = self
+            .send_token_instruction(
+                &signers,
+                &signers.fee_payer.pubkey(),
+                &to_pubkey,
+                &token_mint_pubkey,
+                Some(amount.into()),
+                mode,
+                memo,
+            )
+            .await?;
+
+        if let Some(policy) = &self.policy {
+            policy.check(&send.plan)?;
+        }
+
+        let signature = self.send_instructions(&send.instructions, &signers).await?;
+
+        Ok(send.quote.receipt(signature, send.decimals))
+    }
+}
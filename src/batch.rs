@@ -0,0 +1,355 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signer::Signer};
+
+use crate::{
+    amount::TokenAmount,
+    error::{Result, UnmintError, parse_pubkey},
+    instructions::{self, Signers},
+    journal::{ActionStatus, Journal, JournalEntry},
+    plan::{Plan, PlanAction},
+    preflight::{Asset, Shortfall},
+    recipient::{Recipient, validate_recipient},
+    transfer_fee::FeeMode,
+    unmint::Unmint,
+};
+
+/// One row of a payout list: a recipient and a UI amount such as `"1.5"`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Payout {
+    pub address: String,
+    #[serde(deserialize_with = "amount_string")]
+    pub amount: String,
+}
+
+impl Payout {
+    pub fn new(address: &str, amount: &str) -> Self {
+        Self {
+            address: address.to_string(),
+            amount: amount.to_string(),
+        }
+    }
+
+    /// Reads `address,amount` records
+    ///
+    /// Fields may be quoted, so `"1,000.5"` is one amount. Empty lines, lines
+    /// starting with `#` and a first record of `address,amount` are skipped.
+    /// A record with more than two fields or broken quoting fails the whole
+    /// list; rows with a bad address or amount are kept so `batch_send`
+    /// reports them per row.
+    pub fn from_csv(csv: &str) -> Result<Vec<Payout>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .comment(Some(b'#'))
+            .trim(csv::Trim::All)
+            .from_reader(csv.as_bytes());
+        let mut payouts = vec![];
+
+        for (index, record) in reader.records().enumerate() {
+            let record = record.map_err(|e| invalid_payouts(e.to_string()))?;
+            if record.len() > 2 {
+                let line = record.position().map_or(0, |p| p.line());
+                return Err(invalid_payouts(format!(
+                    "line {} has {} fields, expected address,amount",
+                    line,
+                    record.len()
+                )));
+            }
+
+            let address = record.get(0).unwrap_or_default();
+            let amount = record.get(1).unwrap_or_default();
+            if index == 0
+                && address.eq_ignore_ascii_case("address")
+                && amount.eq_ignore_ascii_case("amount")
+            {
+                continue;
+            }
+            payouts.push(Payout::new(address, amount));
+        }
+
+        Ok(payouts)
+    }
+
+    /// Reads a JSON array of `{"address": ..., "amount": ...}` objects; amounts
+    /// may be strings or numbers
+    pub fn from_json(json: &str) -> Result<Vec<Payout>> {
+        serde_json::from_str(json).map_err(|e| invalid_payouts(e.to_string()))
+    }
+}
+
+fn invalid_payouts(reason: String) -> UnmintError {
+    UnmintError::InvalidInput {
+        kind: "payout list",
+        reason,
+    }
+}
+
+fn amount_string<'de, D>(deserializer: D) -> std::result::Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(amount) => Ok(amount),
+        serde_json::Value::Number(amount) => Ok(amount.to_string()),
+        other => Err(serde::de::Error::custom(format!(
+            "expected an amount, found {}",
+            other
+        ))),
+    }
+}
+
+/// One entry of the journal written by `batch_send`; `id` is the row index
+pub type PayoutResult = JournalEntry<Payout>;
+
+/// A validated row ready to be sent
+struct PlannedPayout {
+    row: usize,
+    instructions: Vec<Instruction>,
+    amount: u64,
+}
+
+impl Unmint {
+    /// Pays every row of `recipients` from the `token_mint_address` ATA of the sender
+    ///
+    /// Every row is validated first; rows with a bad address, amount or
+    /// recipient are journaled as `Skipped`. Missing recipient ATAs are created
+    /// and the transfers are packed into as few transactions as fit. Nothing is
+    /// sent when a payout breaks the `Policy`, be it its recipient or its amount.
+    ///
+    /// The status of every row is kept in the `Journal` at `journal_path`, one
+    /// JSON line per change, and each transaction is journaled as pending before
+    /// it is sent. Calling `batch_send` again with the same list and journal
+    /// resumes the run: rows already confirmed are skipped, and pending ones are
+    /// settled with `reconcile` before anything is resent.
+    ///
+    /// # Returns
+    /// * The final `PayoutResult` of every row, in list order
+    pub fn batch_send<P: AsRef<Path>>(
+        &self,
+        token_mint_address: &str,
+        from_base58_string: &str,
+        recipients: &[Payout],
+        journal_path: P,
+    ) -> Result<Vec<PayoutResult>> {
+        let mint_pubkey = parse_pubkey(token_mint_address)?;
+        let signers = Signers::parse(from_base58_string, None)?;
+        let mut journal = Journal::open(journal_path)?;
+
+        if let Some(entry) = journal.entries().find(|entry| {
+            entry.status.is_confirmed()
+                && entry
+                    .id
+                    .parse::<usize>()
+                    .map_or(true, |row| row >= recipients.len())
+        }) {
+            return Err(UnmintError::JournalMismatch(format!(
+                "entry {} was confirmed for a row not in the payout list",
+                entry.id
+            )));
+        }
+
+        self.reconcile(&mut journal)?;
+
+        let mut outstanding = vec![];
+        for (row, payout) in recipients.iter().enumerate() {
+            if journal.plan(&row.to_string(), payout.clone())? {
+                outstanding.push(row);
+            }
+        }
+
+        let planned = self.plan_payouts(
+            &signers,
+            &mint_pubkey,
+            recipients,
+            &outstanding,
+            &mut journal,
+        )?;
+
+        let groups: Vec<Vec<Instruction>> =
+            planned.iter().map(|p| p.instructions.clone()).collect();
+        let batches = instructions::pack_groups(
+            &groups,
+            &signers.fee_payer.pubkey(),
+            self.priority_fee.as_ref(),
+        )?;
+
+        for batch in batches {
+            let batch_instructions: Vec<Instruction> =
+                batch.iter().flat_map(|&i| groups[i].clone()).collect();
+            let ids: Vec<String> = batch.iter().map(|&i| planned[i].row.to_string()).collect();
+
+            // a failed batch is journaled as failed and retried on the next run
+            if let Err(e) = self.send_journaled(&mut journal, &ids, &batch_instructions, &signers) {
+                // the transaction may still land; the next run settles it
+                let pending = ids.iter().any(|id| {
+                    journal
+                        .get(id)
+                        .is_some_and(|entry| matches!(entry.status, ActionStatus::Pending { .. }))
+                });
+                if pending {
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok((0..recipients.len())
+            .filter_map(|row| journal.get(&row.to_string()).cloned())
+            .collect())
+    }
+
+    /// Validates `rows` of `recipients` and builds their instructions
+    ///
+    /// Invalid rows are recorded and left out. Fails before anything is sent
+    /// when the sender cannot cover the valid rows.
+    fn plan_payouts(
+        &self,
+        signers: &Signers,
+        mint_pubkey: &Pubkey,
+        recipients: &[Payout],
+        rows: &[usize],
+        journal: &mut Journal<Payout>,
+    ) -> Result<Vec<PlannedPayout>> {
+        let mint = self.cached_mint_info(mint_pubkey)?;
+        let ata_sender = self.token_program.ata(&signers.from.pubkey(), mint_pubkey);
+        let sender = self.token_account(&ata_sender)?;
+
+        let epoch = match mint.transfer_fee_config() {
+            Some(_) => Some(self.client.get_epoch_info()?.epoch),
+            None => None,
+        };
+
+        // parse every row, then fetch the recipients and their ATAs in batches
+        let mut parsed = vec![];
+        for &row in rows {
+            let payout = &recipients[row];
+            let parsed_row = parse_pubkey(&payout.address).and_then(|address| {
+                let amount = TokenAmount::parse(&payout.amount, mint.decimals)?.raw();
+                if amount == 0 {
+                    return Err(UnmintError::InvalidAmount(format!(
+                        "{}: must be positive",
+                        payout.amount
+                    )));
+                }
+                Ok((address, amount))
+            });
+            match parsed_row {
+                Ok((address, amount)) => parsed.push((row, address, amount)),
+                Err(e) => journal.record(
+                    &row.to_string(),
+                    ActionStatus::Skipped {
+                        reason: e.to_string(),
+                    },
+                )?,
+            }
+        }
+
+        let addresses: Vec<Pubkey> = parsed.iter().map(|(_, address, _)| *address).collect();
+        let accounts = self.multiple_accounts(&addresses)?;
+
+        let mut destinations = vec![];
+        for ((row, address, amount), account) in parsed.into_iter().zip(accounts) {
+            match validate_recipient(
+                &address,
+                account.as_ref(),
+                mint_pubkey,
+                self.allow_owner_off_curve,
+            ) {
+                Ok(Recipient::TokenAccount(token_account)) => {
+                    destinations.push((row, address, amount, token_account, false))
+                }
+                Ok(Recipient::Wallet(owner)) => {
+                    let ata = self.token_program.ata(&owner, mint_pubkey);
+                    destinations.push((row, owner, amount, ata, true))
+                }
+                Err(e) => journal.record(
+                    &row.to_string(),
+                    ActionStatus::Skipped {
+                        reason: e.to_string(),
+                    },
+                )?,
+            }
+        }
+
+        let token_accounts: Vec<Pubkey> = destinations.iter().map(|d| d.3).collect();
+        let existing = self.multiple_accounts(&token_accounts)?;
+
+        let mut planned = vec![];
+        let mut actions = vec![];
+        for ((row, owner, amount, token_account, is_ata), account) in
+            destinations.into_iter().zip(existing)
+        {
+            if let Err(e) = instructions::check_memo(&token_account, account.as_ref(), None) {
+                journal.record(
+                    &row.to_string(),
+                    ActionStatus::Skipped {
+                        reason: e.to_string(),
+                    },
+                )?;
+                continue;
+            }
+
+            let quote = instructions::fee_quote(&mint, epoch, amount, FeeMode::SendExact)?;
+
+            let transfer_ix = self.transfer_ix(
+                &self.token_program,
+                &ata_sender,
+                &token_account,
+                &signers.from,
+                quote.sent,
+                mint.decimals,
+                mint_pubkey,
+                quote.fee,
+            )?;
+
+            actions.push(PlanAction::Transfer {
+                program: self.token_program.program_id(),
+                source: ata_sender,
+                destination: token_account,
+                authority: signers.from.pubkey(),
+                mint: *mint_pubkey,
+                amount: quote.sent,
+                decimals: mint.decimals,
+                fee: quote.fee,
+            });
+            planned.push(PlannedPayout {
+                row,
+                instructions: instructions::transfer_instructions(
+                    &self.token_program,
+                    &signers.fee_payer.pubkey(),
+                    &owner,
+                    mint_pubkey,
+                    is_ata && account.is_none(),
+                    None,
+                    transfer_ix,
+                ),
+                amount,
+            });
+        }
+
+        if let Some(policy) = &self.policy {
+            policy.check(&Plan::from_actions(signers.fee_payer.pubkey(), actions))?;
+        }
+
+        let total = planned
+            .iter()
+            .try_fold(0u64, |total, p| total.checked_add(p.amount))
+            .ok_or_else(|| UnmintError::Overflow("payout total".to_string()))?;
+        if total > sender.amount {
+            return Err(UnmintError::Shortfall(vec![Shortfall {
+                account: ata_sender,
+                asset: Asset::Token {
+                    mint: *mint_pubkey,
+                    decimals: mint.decimals,
+                },
+                amount: total,
+                fee: 0,
+                rent: 0,
+                available: sender.amount,
+            }]));
+        }
+
+        Ok(planned)
+    }
+}
@@ -0,0 +1,157 @@
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signature, signer::Signer};
+use spl_token_2022::extension::AccountType;
+
+use crate::{
+    amount::TokenAmount,
+    error::{Result, UnmintError, parse_pubkey},
+    instructions::Signers,
+    mint::{MintExtension, MintInfo},
+    token_program::TokenProgram,
+    unmint::{PubkeyInput, Unmint},
+};
+
+/// Offset of the supply field in a mint
+const MINT_SUPPLY_OFFSET: usize = 36;
+
+/// Offset of the account type byte of a Token-2022 account with extensions
+const ACCOUNT_TYPE_OFFSET: usize = 165;
+
+/// A mint `Unmint::closable_mints` found
+#[derive(Debug, Clone)]
+pub struct ClosableMint {
+    pub mint: MintInfo,
+    /// Rent returned by closing the mint
+    pub lamports: u64,
+}
+
+impl MintInfo {
+    /// Authority of the MintCloseAuthority extension
+    pub fn close_authority(&self) -> Option<Pubkey> {
+        self.extensions
+            .iter()
+            .find_map(|extension| match extension {
+                MintExtension::MintCloseAuthority { close_authority } => *close_authority,
+                _ => None,
+            })
+    }
+
+    /// Fails with `UnmintError::MintNotClosable` unless `authority` can close the mint now
+    ///
+    /// Only Token-2022 mints with a MintCloseAuthority naming `authority` and a
+    /// zero supply can be closed.
+    pub fn check_closable(&self, authority: &Pubkey) -> Result<()> {
+        let refuse = |reason: String| {
+            Err(UnmintError::MintNotClosable {
+                mint: self.address,
+                reason,
+            })
+        };
+
+        if self.token_program != TokenProgram::Token2022 {
+            return refuse("legacy mints cannot be closed".to_string());
+        }
+        match self.close_authority() {
+            None => return refuse("it has no close authority".to_string()),
+            Some(close_authority) if close_authority != *authority => {
+                return refuse(format!("its close authority is {}", close_authority));
+            }
+            Some(_) => {}
+        }
+        if self.supply > 0 {
+            return refuse(format!(
+                "{} tokens are still in circulation",
+                TokenAmount::from_raw(self.supply, self.decimals)
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Unmint {
+    /// Closes a Token-2022 mint with zero supply, reclaiming its rent
+    ///
+    /// # Arguments
+    /// * `token_mint_address` - Mint to close
+    /// * `authority_base58_string` - Base58 private key of the mint close authority, pays the fee
+    /// * `destination` - Receives the rent of the mint
+    ///
+    /// # Returns
+    /// * `Signature` of the transaction
+    pub fn close_mint(
+        &self,
+        token_mint_address: &str,
+        authority_base58_string: &str,
+        destination: &str,
+    ) -> Result<Signature> {
+        let mint_pubkey = parse_pubkey(token_mint_address)?;
+        let signers = Signers::parse(authority_base58_string, None)?;
+        let destination = parse_pubkey(destination)?;
+
+        let mint = self.mint_info(&mint_pubkey)?;
+        mint.check_closable(&signers.from.pubkey())?;
+
+        if let Some(policy) = &self.policy
+            && let Some(violation) = policy.close_violation(&mint_pubkey, &mint_pubkey)
+        {
+            return Err(UnmintError::PolicyViolation(vec![violation]));
+        }
+
+        let close_ix =
+            TokenProgram::Token2022.close_ix(&mint_pubkey, &destination, &signers.from)?;
+
+        self.send_instructions(&[close_ix], &signers)
+    }
+
+    /// Lists the Token-2022 mints with zero supply that `authority` can close
+    ///
+    /// Scans every zero-supply Token-2022 mint with `getProgramAccounts`, so it
+    /// needs an RPC node that serves unrestricted program scans.
+    pub fn closable_mints<'a, A>(&self, authority: A) -> Result<Vec<ClosableMint>>
+    where
+        A: Into<PubkeyInput<'a>>,
+    {
+        let authority_pubkey = authority.into().to_pubkey()?;
+
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                    MINT_SUPPLY_OFFSET,
+                    0u64.to_le_bytes().to_vec(),
+                )),
+                // a close authority is an extension, so the mint has an account type
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                    ACCOUNT_TYPE_OFFSET,
+                    vec![AccountType::Mint as u8],
+                )),
+            ]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(self.client.commitment()),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+
+        let found = self
+            .client
+            .get_program_accounts_with_config(&TokenProgram::Token2022.program_id(), config)?;
+
+        Ok(found
+            .into_iter()
+            .filter_map(|(address, account)| {
+                let mint = MintInfo::decode(address, &account).ok()?;
+                mint.check_closable(&authority_pubkey).ok()?;
+                Some(ClosableMint {
+                    mint,
+                    lamports: account.lamports,
+                })
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,105 @@
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+};
+
+use crate::{
+    error::Result,
+    instructions::{self, Signers},
+    scan::OwnedTokenAccount,
+    token_program::TokenProgram,
+    unmint::{PubkeyInput, Unmint},
+};
+
+/// An active approval found by `Unmint::delegations`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delegation {
+    /// Token account the delegate may move tokens out of
+    pub account: Pubkey,
+    pub token_program: TokenProgram,
+    pub mint: Pubkey,
+    pub delegate: Pubkey,
+    /// Raw amount the delegate may still move
+    pub delegated_amount: u64,
+    /// Frozen accounts cannot be revoked until the freeze authority thaws them
+    pub frozen: bool,
+}
+
+impl OwnedTokenAccount {
+    /// Active delegation of the account, if any
+    pub fn delegation(&self) -> Option<Delegation> {
+        let delegate: Option<Pubkey> = self.account.delegate.into();
+        Some(Delegation {
+            account: self.address,
+            token_program: self.token_program,
+            mint: self.account.mint,
+            delegate: delegate?,
+            delegated_amount: self.account.delegated_amount,
+            frozen: self.account.is_frozen(),
+        })
+    }
+}
+
+impl Unmint {
+    /// Lists every token account of `owner` with an active delegate, under
+    /// both token programs
+    pub fn delegations<'a, A>(&self, owner: A) -> Result<Vec<Delegation>>
+    where
+        A: Into<PubkeyInput<'a>>,
+    {
+        Ok(self
+            .token_accounts(owner)?
+            .iter()
+            .filter_map(OwnedTokenAccount::delegation)
+            .collect())
+    }
+
+    /// Revokes every delegation of the owner, packing the revokes into as few
+    /// transactions as fit
+    ///
+    /// Frozen accounts are skipped, the token program rejects any revoke on
+    /// them; `delegations` still lists them.
+    ///
+    /// # Arguments
+    /// * `owner_base58_string` - Base58 private key of the token account owner
+    /// * `fee_payer_base58_string` - Optional, who pays the fees
+    ///
+    /// # Returns
+    /// * `Signature` of every transaction sent, empty when nothing was delegated
+    pub fn revoke_all(
+        &self,
+        owner_base58_string: &str,
+        fee_payer_base58_string: Option<&str>,
+    ) -> Result<Vec<Signature>> {
+        let signers = Signers::parse(owner_base58_string, fee_payer_base58_string)?;
+        let owner = signers.from.pubkey();
+
+        let groups = self
+            .delegations(&owner)?
+            .iter()
+            .filter(|delegation| !delegation.frozen)
+            .map(|delegation| {
+                Ok(vec![
+                    delegation
+                        .token_program
+                        .revoke_ix(&delegation.account, &signers.from)?,
+                ])
+            })
+            .collect::<Result<Vec<Vec<Instruction>>>>()?;
+        let batches = instructions::pack_groups(
+            &groups,
+            &signers.fee_payer.pubkey(),
+            self.priority_fee.as_ref(),
+        )?;
+
+        let mut signatures = vec![];
+        for batch in batches {
+            let batch_instructions: Vec<Instruction> =
+                batch.iter().flat_map(|&i| groups[i].clone()).collect();
+            signatures.push(self.send_instructions(&batch_instructions, &signers)?);
+        }
+
+        Ok(signatures)
+    }
+}
@@ -0,0 +1,312 @@
+use std::{io, path::PathBuf, str::FromStr};
+
+use num_traits::FromPrimitive;
+use solana_client::client_error::ClientError;
+use solana_sdk::{
+    instruction::InstructionError, message::Message, program_error::ProgramError, pubkey::Pubkey,
+    signature::Keypair, transaction::TransactionError,
+};
+use solana_stake_interface::state::Lockup;
+use spl_token_2022::error::TokenError;
+use thiserror::Error;
+
+use crate::{
+    plan::Drift, policy::Violation, preflight::Shortfall, recipient::RecipientKind,
+    stake::StakeActivation, token_program::TokenProgram,
+};
+
+pub type Result<T, E = UnmintError> = std::result::Result<T, E>;
+
+/// Errors returned by `Unmint`
+#[derive(Debug, Error)]
+pub enum UnmintError {
+    /// The account does not exist on chain
+    #[error("account not found: {0}")]
+    AccountNotFound(Pubkey),
+
+    /// An account cannot cover the lamports or tokens the operation needs
+    #[error("insufficient funds: {0}")]
+    InsufficientFunds(String),
+
+    /// Preflight found accounts that cannot cover the transaction; nothing was signed
+    #[error(
+        "insufficient funds: {}",
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+    )]
+    Shortfall(Vec<Shortfall>),
+
+    /// A string could not be parsed as a `Pubkey`
+    #[error("invalid pubkey: {0}")]
+    InvalidPubkey(String),
+
+    /// An amount could not be represented exactly in the smallest unit
+    #[error("invalid amount: {0}")]
+    InvalidAmount(String),
+
+    /// A string could not be parsed as a base58 private key
+    #[error("invalid private key")]
+    InvalidKey,
+
+    /// The account is not owned by the token program this `Unmint` uses
+    #[error("account {account} is owned by {owner}, expected {expected}")]
+    WrongTokenProgram {
+        account: Pubkey,
+        owner: Pubkey,
+        expected: Pubkey,
+    },
+
+    /// The account is owned by neither token program
+    #[error("account {account} is owned by {owner}, not by a token program")]
+    NotTokenProgram { account: Pubkey, owner: Pubkey },
+
+    /// A message cannot be encoded as an off-chain message or parsed as a sign-in message
+    #[error("invalid message: {0}")]
+    InvalidMessage(String),
+
+    /// A sign-in message does not verify or does not match what the server expects
+    #[error("sign-in rejected: {0}")]
+    SignInRejected(String),
+
+    /// The token account is frozen
+    #[error("token account {0} is frozen")]
+    AccountFrozen(Pubkey),
+
+    /// The token account still holds tokens, so it cannot be closed
+    #[error("token account {0} still holds tokens and cannot be closed")]
+    NonZeroBalance(Pubkey),
+
+    /// The account exists but is not the kind the operation works on
+    #[error("{address} is not a {expected}")]
+    WrongAccountType {
+        address: Pubkey,
+        expected: &'static str,
+    },
+
+    /// The account data cannot be decoded
+    #[error("invalid account {address}: {reason}")]
+    InvalidAccountData { address: Pubkey, reason: String },
+
+    /// A program id is neither token program
+    #[error("{0} is not a token program")]
+    UnsupportedProgram(Pubkey),
+
+    /// The operation needs Token-2022, or a mint extension the mint lacks
+    #[error("not supported: {0}")]
+    Unsupported(String),
+
+    /// An instruction could not be built from the given arguments
+    #[error("invalid instruction: {0}")]
+    InvalidInstruction(ProgramError),
+
+    /// The fee payer is not the one the plan was built for
+    #[error("plan is paid by {expected}, not {found}")]
+    FeePayerMismatch { expected: Pubkey, found: Pubkey },
+
+    /// A required signer was not given
+    #[error("missing a signature from {0}")]
+    MissingSigner(Pubkey),
+
+    /// An amount or total does not fit in a u64
+    #[error("amount overflows u64: {0}")]
+    Overflow(String),
+
+    /// A group of instructions that must land together does not fit in one transaction
+    #[error("instruction group {0} does not fit in a single transaction")]
+    TransactionTooLarge(usize),
+
+    /// A `Journal` does not match the operation it is used for
+    #[error("journal does not match this run: {0}")]
+    JournalMismatch(String),
+
+    /// A `Policy`, `Plan` or payout list could not be parsed
+    #[error("invalid {kind}: {reason}")]
+    InvalidInput { kind: &'static str, reason: String },
+
+    /// A file could not be read or written
+    #[error("cannot access {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+
+    /// The recipient address cannot receive tokens of the mint
+    #[error("cannot send to {address}: it is {kind}")]
+    InvalidRecipient {
+        address: Pubkey,
+        kind: RecipientKind,
+    },
+
+    /// The signer is not the authority the mint or account expects
+    #[error(
+        "{found} is not the authority of {address}, {}",
+        .expected.map_or("which has none".to_string(), |e| format!("expected {}", e))
+    )]
+    WrongAuthority {
+        address: Pubkey,
+        /// `None` when the authority was renounced
+        expected: Option<Pubkey>,
+        found: Pubkey,
+    },
+
+    /// The mint cannot be closed by the given authority
+    #[error("cannot close mint {mint}: {reason}")]
+    MintNotClosable { mint: Pubkey, reason: String },
+
+    /// The stake account is still delegated or cooling down
+    #[error("stake account {address} is {activation}, only inactive stake can be withdrawn")]
+    StakeNotInactive {
+        address: Pubkey,
+        activation: StakeActivation,
+    },
+
+    /// The stake account has a lockup in force, set by its custodian
+    #[error(
+        "stake account {address} is locked until epoch {} and unix time {}",
+        lockup.epoch,
+        lockup.unix_timestamp
+    )]
+    StakeLocked { address: Pubkey, lockup: Lockup },
+
+    /// The recipient token account only accepts transfers with a memo
+    #[error("token account {0} requires a memo on incoming transfers")]
+    MemoRequired(Pubkey),
+
+    /// `retire_wallet` found accounts it cannot empty; nothing was sent
+    #[error("cannot retire wallet: {}", .0.join("; "))]
+    RetireRefused(Vec<String>),
+
+    /// Balances a `Plan` assumed changed before it was executed; nothing was sent
+    #[error(
+        "plan is out of date: {}",
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+    )]
+    PlanDrift(Vec<Drift>),
+
+    /// The operation breaks the `Policy` set with `with_policy`; nothing was sent
+    #[error(
+        "policy violation: {}",
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+    )]
+    PolicyViolation(Vec<Violation>),
+
+    /// The blockhash expired before the transaction landed
+    #[error("transaction expired before it was confirmed")]
+    TransactionExpired,
+
+    /// A token program instruction failed on chain
+    #[error("instruction {index} failed: {error}")]
+    Token { index: u8, error: TokenError },
+
+    /// Any other instruction failure
+    #[error("instruction {index} failed: {error}")]
+    Instruction { index: u8, error: InstructionError },
+
+    /// The transaction was rejected for a reason not covered above
+    #[error("transaction failed: {0}")]
+    Transaction(TransactionError),
+
+    /// Transport or RPC level failure
+    #[error("rpc error: {0}")]
+    Rpc(Box<ClientError>),
+
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl UnmintError {
+    /// Decodes a `TransactionError`
+    ///
+    /// When the `message` that produced the error is given, custom errors are
+    /// decoded against the program that ran the failing instruction.
+    pub fn from_transaction_error(error: TransactionError, message: Option<&Message>) -> Self {
+        match error {
+            TransactionError::BlockhashNotFound => UnmintError::TransactionExpired,
+            TransactionError::InsufficientFundsForFee => {
+                UnmintError::InsufficientFunds("fee payer cannot pay the fee".to_string())
+            }
+            TransactionError::InsufficientFundsForRent { account_index } => {
+                let account = message
+                    .and_then(|m| m.account_keys.get(account_index as usize))
+                    .map(|k| k.to_string())
+                    .unwrap_or_else(|| format!("#{}", account_index));
+                UnmintError::InsufficientFunds(format!("{} would not be rent exempt", account))
+            }
+            TransactionError::InstructionError(index, InstructionError::Custom(code)) => {
+                let program = message.and_then(|m| m.program_id(index as usize));
+                match program {
+                    Some(program) if TokenProgram::is_token_program(program) => {
+                        match TokenError::from_u32(code) {
+                            // closing an account that still holds tokens; the
+                            // account is the first of the close instruction
+                            Some(TokenError::NonNativeHasBalance) => {
+                                match message.and_then(|m| instruction_account(m, index, 0)) {
+                                    Some(account) => UnmintError::NonZeroBalance(account),
+                                    None => UnmintError::Token {
+                                        index,
+                                        error: TokenError::NonNativeHasBalance,
+                                    },
+                                }
+                            }
+                            Some(error) => UnmintError::Token { index, error },
+                            None => UnmintError::Instruction {
+                                index,
+                                error: InstructionError::Custom(code),
+                            },
+                        }
+                    }
+                    // SystemError::ResultWithNegativeLamports
+                    Some(program)
+                        if *program == solana_system_interface::program::ID && code == 1 =>
+                    {
+                        UnmintError::InsufficientFunds(format!(
+                            "instruction {} transfers more lamports than available",
+                            index
+                        ))
+                    }
+                    _ => UnmintError::Instruction {
+                        index,
+                        error: InstructionError::Custom(code),
+                    },
+                }
+            }
+            TransactionError::InstructionError(index, error) => {
+                UnmintError::Instruction { index, error }
+            }
+            error => UnmintError::Transaction(error),
+        }
+    }
+
+    /// Decodes a `ClientError`
+    ///
+    /// Transaction errors, including those of a failed preflight simulation,
+    /// are decoded against `message` when it is given.
+    pub fn from_client_error(error: ClientError, message: Option<&Message>) -> Self {
+        match error.get_transaction_error() {
+            Some(tx_error) => UnmintError::from_transaction_error(tx_error, message),
+            None => UnmintError::Rpc(Box::new(error)),
+        }
+    }
+}
+
+impl From<ClientError> for UnmintError {
+    fn from(error: ClientError) -> Self {
+        UnmintError::from_client_error(error, None)
+    }
+}
+
+/// Key of the `position`th account of instruction `index` in `message`
+fn instruction_account(message: &Message, index: u8, position: usize) -> Option<Pubkey> {
+    let instruction = message.instructions.get(index as usize)?;
+    let key = *instruction.accounts.get(position)?;
+    message.account_keys.get(key as usize).copied()
+}
+
+/// Parses a base58 public key
+pub(crate) fn parse_pubkey(address: &str) -> Result<Pubkey> {
+    Pubkey::from_str(address).map_err(|_| UnmintError::InvalidPubkey(address.to_string()))
+}
+
+/// Parses a base58 private key without panicking on malformed input
+pub(crate) fn parse_keypair(base58_string: &str) -> Result<Keypair> {
+    let bytes = bs58::decode(base58_string)
+        .into_vec()
+        .map_err(|_| UnmintError::InvalidKey)?;
+    Keypair::try_from(bytes.as_slice()).map_err(|_| UnmintError::InvalidKey)
+}
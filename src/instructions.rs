@@ -0,0 +1,634 @@
+use std::time::Duration;
+
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_sdk::{
+    account::Account,
+    hash::Hash,
+    instruction::Instruction,
+    message::Message,
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    transaction::{Transaction, TransactionError},
+};
+use solana_system_interface::instruction as system_instruction;
+use spl_token_2022::{
+    extension::{StateWithExtensions, memo_transfer::memo_required},
+    state::{Account as TokenAccount, AccountState},
+};
+
+use crate::{
+    amount::AmountInput,
+    error::{Result, UnmintError, parse_keypair},
+    mint::MintInfo,
+    plan::{Plan, PlanAction},
+    recipient::Destination,
+    token_program::TokenProgram,
+    transfer_fee::{FeeMode, FeeQuote},
+};
+
+/// Compute budget settings prepended to every transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriorityFee {
+    /// Price of one compute unit in micro-lamports
+    pub compute_unit_price: u64,
+    /// Compute units requested for the transaction
+    pub compute_unit_limit: u32,
+}
+
+/// How much SOL `transfer_max_sol` leaves in the sender account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrainMode {
+    /// Keep the rent-exempt minimum of an empty account
+    KeepRentExempt,
+    /// Send everything; the emptied account is garbage-collected
+    Full,
+}
+
+/// Keypairs taking part in an operation
+pub(crate) struct Signers {
+    pub from: Keypair,
+    pub fee_payer: Keypair,
+    /// Keypairs of accounts the transaction creates, such as a new mint
+    pub extra: Vec<Keypair>,
+}
+
+impl Signers {
+    /// Parses the sender and optional fee payer; the sender pays when no fee payer is given
+    pub fn parse(from_base58_string: &str, fee_payer_base58_string: Option<&str>) -> Result<Self> {
+        Ok(Self {
+            from: parse_keypair(from_base58_string)?,
+            fee_payer: parse_keypair(fee_payer_base58_string.unwrap_or(from_base58_string))?,
+            extra: vec![],
+        })
+    }
+
+    /// Returns the distinct keypairs taking part in the operation
+    pub fn keypairs(&self) -> Vec<&Keypair> {
+        let mut signers: Vec<&Keypair> = vec![&self.from];
+        if self.fee_payer.pubkey() != self.from.pubkey() {
+            signers.push(&self.fee_payer);
+        }
+        signers.extend(&self.extra);
+        signers
+    }
+
+    /// Signs `message` with the keypairs among `keypairs` it requires
+    ///
+    /// A message does not always need every keypair, e.g. a plan whose steps
+    /// are all authorized by the fee payer; `Transaction::sign` would panic on
+    /// the extra ones.
+    pub fn sign(&self, message: Message) -> Result<Transaction> {
+        let required = &message.account_keys[..message.header.num_required_signatures as usize];
+        let keypairs: Vec<&Keypair> = self
+            .keypairs()
+            .into_iter()
+            .filter(|keypair| required.contains(&keypair.pubkey()))
+            .collect();
+        if let Some(missing) = required
+            .iter()
+            .find(|key| !keypairs.iter().any(|keypair| keypair.pubkey() == **key))
+        {
+            return Err(UnmintError::MissingSigner(*missing));
+        }
+
+        let blockhash = message.recent_blockhash;
+        let mut transaction = Transaction::new_unsigned(message);
+        transaction
+            .try_sign(&keypairs, blockhash)
+            .map_err(|e| anyhow::anyhow!(e))?;
+
+        Ok(transaction)
+    }
+}
+
+/// Decodes a fetched token account and checks it can be moved by `token_program`
+pub(crate) fn checked_token_account(
+    token_program: &TokenProgram,
+    address: &Pubkey,
+    account: &Account,
+) -> Result<TokenAccount> {
+    let expected = token_program.program_id();
+    if account.owner != expected {
+        return Err(UnmintError::WrongTokenProgram {
+            account: *address,
+            owner: account.owner,
+            expected,
+        });
+    }
+
+    let state = StateWithExtensions::<TokenAccount>::unpack(&account.data).map_err(|e| {
+        UnmintError::InvalidAccountData {
+            address: *address,
+            reason: e.to_string(),
+        }
+    })?;
+
+    if state.base.state == AccountState::Frozen {
+        return Err(UnmintError::AccountFrozen(*address));
+    }
+
+    Ok(state.base)
+}
+
+/// Fails with `MemoRequired` when `destination` is a Token-2022 account with
+/// required incoming memos and no `memo` is given
+///
+/// `account` is `None` when the destination does not exist yet; a freshly
+/// created ATA never requires memos.
+pub fn check_memo(
+    destination: &Pubkey,
+    account: Option<&Account>,
+    memo: Option<&str>,
+) -> Result<()> {
+    let Some(account) = account else {
+        return Ok(());
+    };
+    if memo.is_some() || account.owner != TokenProgram::Token2022.program_id() {
+        return Ok(());
+    }
+
+    let state = StateWithExtensions::<TokenAccount>::unpack(&account.data).map_err(|e| {
+        UnmintError::InvalidAccountData {
+            address: *destination,
+            reason: e.to_string(),
+        }
+    })?;
+
+    if memo_required(&state) {
+        return Err(UnmintError::MemoRequired(*destination));
+    }
+
+    Ok(())
+}
+
+/// Prepends the idempotent creation of the recipient ATA to `transfer_ix` when
+/// `create_destination` is set, and `memo` right before the transfer
+pub(crate) fn transfer_instructions(
+    token_program: &TokenProgram,
+    ata_payer: &Pubkey,
+    to: &Pubkey,
+    mint: &Pubkey,
+    create_destination: bool,
+    memo: Option<&str>,
+    transfer_ix: Instruction,
+) -> Vec<Instruction> {
+    let mut instructions = vec![];
+
+    if create_destination {
+        instructions.push(token_program.create_ata_idempotent_ix(ata_payer, to, mint));
+    }
+
+    // MemoTransfer only accepts a memo in the instruction right before the transfer
+    if let Some(memo) = memo {
+        instructions.push(memo_instruction(memo));
+    }
+
+    instructions.push(transfer_ix);
+
+    instructions
+}
+
+/// Chain state a token send is built from
+///
+/// `Unmint` and `AsyncUnmint` only fetch it; the quote and the instructions
+/// are worked out here.
+pub(crate) struct TokenSend {
+    pub mint: MintInfo,
+    /// Sender's associated token account
+    pub source: Pubkey,
+    /// Raw balance of `source`
+    pub balance: u64,
+    pub destination: Destination,
+    /// Current epoch, only fetched for mints with a transfer fee
+    pub epoch: Option<u64>,
+}
+
+/// A send built from a `TokenSend`, ready to be checked and signed
+pub(crate) struct BuiltSend {
+    pub instructions: Vec<Instruction>,
+    /// The steps of `instructions`, for `Policy::check`
+    pub plan: Plan,
+    pub quote: FeeQuote,
+    pub decimals: u8,
+}
+
+impl TokenSend {
+    /// Checks the memo rules of the destination and quotes a transfer of
+    /// `amount`, or of the whole balance when `None`
+    pub(crate) fn quote(
+        &self,
+        amount: Option<AmountInput<'_>>,
+        mode: FeeMode,
+        memo: Option<&str>,
+    ) -> Result<FeeQuote> {
+        check_memo(
+            &self.destination.address,
+            self.destination.account.as_ref(),
+            memo,
+        )?;
+
+        let amount = match amount {
+            Some(amount) => amount.to_token_amount(self.mint.decimals)?.raw(),
+            None => self.balance,
+        };
+
+        fee_quote(&self.mint, self.epoch, amount, mode)
+    }
+
+    /// Wraps `transfer_ix` with the destination ATA creation and `memo`
+    pub(crate) fn instructions(
+        &self,
+        token_program: &TokenProgram,
+        ata_payer: &Pubkey,
+        memo: Option<&str>,
+        transfer_ix: Instruction,
+    ) -> Vec<Instruction> {
+        transfer_instructions(
+            token_program,
+            ata_payer,
+            &self.destination.owner,
+            &self.mint.address,
+            self.destination.create,
+            memo,
+            transfer_ix,
+        )
+    }
+
+    /// The actions `instructions` takes for `quote`, for `Policy::check`
+    pub(crate) fn actions(
+        &self,
+        token_program: &TokenProgram,
+        owner: &Pubkey,
+        ata_payer: &Pubkey,
+        quote: &FeeQuote,
+    ) -> Vec<PlanAction> {
+        let program = token_program.program_id();
+        let mut actions = vec![];
+        if self.destination.create {
+            actions.push(PlanAction::CreateAta {
+                program,
+                payer: *ata_payer,
+                owner: self.destination.owner,
+                mint: self.mint.address,
+                address: self.destination.address,
+            });
+        }
+        actions.push(PlanAction::Transfer {
+            program,
+            source: self.source,
+            destination: self.destination.address,
+            authority: *owner,
+            mint: self.mint.address,
+            amount: quote.sent,
+            decimals: self.mint.decimals,
+            fee: quote.fee,
+        });
+        actions
+    }
+
+    /// Builds the send of `quote`, with `transfer_ix` moving `quote.sent`
+    pub(crate) fn build(
+        &self,
+        token_program: &TokenProgram,
+        signers: &Signers,
+        ata_payer: &Pubkey,
+        quote: FeeQuote,
+        memo: Option<&str>,
+        transfer_ix: Instruction,
+    ) -> BuiltSend {
+        let actions = self.actions(token_program, &signers.from.pubkey(), ata_payer, &quote);
+
+        BuiltSend {
+            instructions: self.instructions(token_program, ata_payer, memo, transfer_ix),
+            plan: Plan::from_actions(signers.fee_payer.pubkey(), actions),
+            quote,
+            decimals: self.mint.decimals,
+        }
+    }
+
+    /// `send_and_close`: `transfer_ix` of the whole balance, then closing
+    /// `source` with its rent going to the fee payer
+    ///
+    /// Returns the instructions with the plan of them for `Policy::check`.
+    pub(crate) fn send_and_close(
+        &self,
+        token_program: &TokenProgram,
+        signers: &Signers,
+        quote: &FeeQuote,
+        memo: Option<&str>,
+        transfer_ix: Instruction,
+    ) -> Result<(Vec<Instruction>, Plan)> {
+        let owner = signers.from.pubkey();
+        let fee_payer = signers.fee_payer.pubkey();
+
+        let mut instructions = self.instructions(token_program, &fee_payer, memo, transfer_ix);
+        instructions.push(close_instruction(
+            token_program,
+            &signers.from,
+            &self.mint.address,
+            &fee_payer,
+        )?);
+
+        let mut actions = self.actions(token_program, &owner, &fee_payer, quote);
+        actions.push(PlanAction::Close {
+            program: token_program.program_id(),
+            account: self.source,
+            mint: self.mint.address,
+            destination: fee_payer,
+            authority: owner,
+        });
+
+        Ok((instructions, Plan::from_actions(fee_payer, actions)))
+    }
+}
+
+/// Splits `amount` according to the transfer fee of `mint` during `epoch`
+///
+/// `epoch` is only read for mints with a transfer fee.
+pub(crate) fn fee_quote(
+    mint: &MintInfo,
+    epoch: Option<u64>,
+    amount: u64,
+    mode: FeeMode,
+) -> Result<FeeQuote> {
+    match (mint.transfer_fee_config(), epoch) {
+        (Some(config), Some(epoch)) => FeeQuote::with_config(config, epoch, amount, mode),
+        (Some(_), None) => Err(UnmintError::Unsupported(format!(
+            "quoting the transfer fee of mint {} without the current epoch",
+            mint.address
+        ))),
+        (None, _) => Ok(FeeQuote::without_fee(amount)),
+    }
+}
+
+/// Builds the instruction closing the sender's ATA for `mint`
+pub(crate) fn close_instruction(
+    token_program: &TokenProgram,
+    from: &Keypair,
+    mint: &Pubkey,
+    destination: &Pubkey,
+) -> Result<Instruction> {
+    let ata_sender = token_program.ata(&from.pubkey(), mint);
+    Ok(token_program.close_ix(&ata_sender, destination, from)?)
+}
+
+/// Builds an SPL Memo instruction
+pub(crate) fn memo_instruction(memo: &str) -> Instruction {
+    spl_memo::build_memo(memo.as_bytes(), &[])
+}
+
+/// Builds a SOL transfer instruction
+pub(crate) fn transfer_sol_instruction(from: &Pubkey, to: &Pubkey, lamports: u64) -> Instruction {
+    system_instruction::transfer(from, to, lamports)
+}
+
+/// Builds a SOL transfer, preceded by `memo` when given
+pub(crate) fn transfer_sol_instructions(
+    from: &Pubkey,
+    to: &Pubkey,
+    lamports: u64,
+    memo: Option<&str>,
+) -> Vec<Instruction> {
+    let mut instructions = vec![];
+    if let Some(memo) = memo {
+        instructions.push(memo_instruction(memo));
+    }
+    instructions.push(transfer_sol_instruction(from, to, lamports));
+    instructions
+}
+
+/// The plan of SOL transfers of `lamports` each from `from` to `to`, for
+/// `Policy::check`
+///
+/// Withdrawals from stake, nonce and other accounts of `from` are planned as
+/// transfers from `from`.
+pub(crate) fn sol_transfer_plan(
+    fee_payer: &Pubkey,
+    from: &Pubkey,
+    to: &Pubkey,
+    lamports: impl IntoIterator<Item = u64>,
+) -> Plan {
+    let actions = lamports
+        .into_iter()
+        .map(|lamports| PlanAction::TransferSol {
+            from: *from,
+            to: *to,
+            lamports,
+        })
+        .collect();
+    Plan::from_actions(*fee_payer, actions)
+}
+
+/// Builds the message for `instructions`, prefixed with the compute budget
+/// instructions of `priority_fee`
+pub(crate) fn build_message(
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    blockhash: &Hash,
+    priority_fee: Option<&PriorityFee>,
+) -> Message {
+    let mut all = vec![];
+
+    if let Some(priority_fee) = priority_fee {
+        all.push(ComputeBudgetInstruction::set_compute_unit_limit(
+            priority_fee.compute_unit_limit,
+        ));
+        all.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee.compute_unit_price,
+        ));
+    }
+
+    all.extend_from_slice(instructions);
+
+    Message::new_with_blockhash(&all, Some(payer), blockhash)
+}
+
+/// A SOL drain of everything but the fee and `reserve` from `from` to `to`
+///
+/// Each flavour of `transfer_max_sol` fetches the fields and the fee of
+/// `fee_message`; the message `finish` returns has the same size and fee.
+pub(crate) struct SolDrain<'a> {
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub balance: u64,
+    pub reserve: u64,
+    pub blockhash: Hash,
+    pub priority_fee: Option<&'a PriorityFee>,
+}
+
+impl SolDrain<'_> {
+    /// The message sending `lamports`
+    fn message(&self, lamports: u64) -> Message {
+        build_message(
+            &[transfer_sol_instruction(&self.from, &self.to, lamports)],
+            &self.from,
+            &self.blockhash,
+            self.priority_fee,
+        )
+    }
+
+    /// The message the fee is quoted on: a drain of the whole balance
+    pub fn fee_message(&self) -> Message {
+        self.message(self.balance)
+    }
+
+    /// Returns the lamports sent with the message sending them, given the `fee`
+    /// of `fee_message`
+    pub fn finish(&self, fee: u64) -> Result<(u64, Message)> {
+        let lamports = max_transferable(self.balance, fee, self.reserve)?;
+        Ok((lamports, self.message(lamports)))
+    }
+}
+
+/// Returns the lamports left to send from `balance` after `fee` and `reserve`
+///
+/// `transfer_max_sol` passes the exact fee of the drain message and, with
+/// `DrainMode::KeepRentExempt`, the rent-exempt minimum as `reserve`.
+pub fn max_transferable(balance: u64, fee: u64, reserve: u64) -> Result<u64> {
+    balance
+        .checked_sub(fee)
+        .and_then(|rest| rest.checked_sub(reserve))
+        .filter(|lamports| *lamports > 0)
+        .ok_or_else(|| {
+            UnmintError::InsufficientFunds(format!(
+                "balance of {} lamports does not cover the {} lamport fee and {} lamport reserve",
+                balance, fee, reserve
+            ))
+        })
+}
+
+/// How often `send_transaction` polls the status of a sent transaction
+pub(crate) const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// What `Confirmation` needs from the RPC next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConfirmQuery {
+    /// The status of the signature at the client commitment
+    Status,
+    /// Whether the blockhash of the message is still valid
+    BlockhashValid,
+    /// The status of the signature, searching the transaction history
+    History,
+    /// Wait `CONFIRM_POLL_INTERVAL`, then resend the transaction
+    Rebroadcast,
+}
+
+/// The RPC answer to a `ConfirmQuery`
+pub(crate) enum ConfirmAnswer {
+    /// Answers `Status` and `History`
+    Status(Option<std::result::Result<(), TransactionError>>),
+    BlockhashValid(bool),
+    Rebroadcast,
+}
+
+/// Waits for a sent transaction to be confirmed or its blockhash to expire
+///
+/// Each flavour of `send_transaction` runs `query` against its RPC client and
+/// passes the result to `answer` until it returns the outcome. The transaction
+/// is rebroadcast while it is pending; resending the same signed transaction
+/// cannot make it land twice.
+pub(crate) struct Confirmation<'a> {
+    signature: Signature,
+    message: &'a Message,
+    query: ConfirmQuery,
+}
+
+impl<'a> Confirmation<'a> {
+    pub fn new(signature: Signature, message: &'a Message) -> Self {
+        Self {
+            signature,
+            message,
+            query: ConfirmQuery::Status,
+        }
+    }
+
+    pub fn query(&self) -> ConfirmQuery {
+        self.query
+    }
+
+    /// Takes the answer to `query`; returns the outcome once there is one
+    pub fn answer(&mut self, answer: ConfirmAnswer) -> Option<Result<Signature>> {
+        match (self.query, answer) {
+            (_, ConfirmAnswer::Status(Some(result))) => Some(
+                result
+                    .map(|()| self.signature)
+                    .map_err(|e| UnmintError::from_transaction_error(e, Some(self.message))),
+            ),
+            (ConfirmQuery::History, ConfirmAnswer::Status(None)) => {
+                Some(Err(UnmintError::TransactionExpired))
+            }
+            (_, ConfirmAnswer::Status(None)) => {
+                self.query = ConfirmQuery::BlockhashValid;
+                None
+            }
+            // it may have landed just before the blockhash expired
+            (_, ConfirmAnswer::BlockhashValid(false)) => {
+                self.query = ConfirmQuery::History;
+                None
+            }
+            (_, ConfirmAnswer::BlockhashValid(true)) => {
+                self.query = ConfirmQuery::Rebroadcast;
+                None
+            }
+            (_, ConfirmAnswer::Rebroadcast) => {
+                self.query = ConfirmQuery::Status;
+                None
+            }
+        }
+    }
+}
+
+/// Packs groups of instructions into as few transactions as fit in a packet
+///
+/// Groups are never split across transactions. Returns, per transaction, the
+/// indexes of the groups it carries.
+pub(crate) fn pack_groups(
+    groups: &[Vec<Instruction>],
+    payer: &Pubkey,
+    priority_fee: Option<&PriorityFee>,
+) -> Result<Vec<Vec<usize>>> {
+    let mut batches: Vec<Vec<usize>> = vec![];
+    let mut current: Vec<usize> = vec![];
+    let mut current_instructions: Vec<Instruction> = vec![];
+
+    for (index, group) in groups.iter().enumerate() {
+        let mut candidate = current_instructions.clone();
+        candidate.extend_from_slice(group);
+
+        if fits_in_packet(&candidate, payer, priority_fee)? {
+            current.push(index);
+            current_instructions = candidate;
+            continue;
+        }
+
+        if current.is_empty() {
+            return Err(UnmintError::TransactionTooLarge(index));
+        }
+
+        batches.push(std::mem::take(&mut current));
+        current_instructions = group.clone();
+        if !fits_in_packet(&current_instructions, payer, priority_fee)? {
+            return Err(UnmintError::TransactionTooLarge(index));
+        }
+        current.push(index);
+    }
+
+    if !current.is_empty() {
+        batches.push(current);
+    }
+
+    Ok(batches)
+}
+
+fn fits_in_packet(
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    priority_fee: Option<&PriorityFee>,
+) -> Result<bool> {
+    let message = build_message(instructions, payer, &Hash::default(), priority_fee);
+    let transaction = Transaction::new_unsigned(message);
+    let size = bincode::serialized_size(&transaction).map_err(|e| anyhow::anyhow!(e))?;
+    Ok(size as usize <= PACKET_DATA_SIZE)
+}
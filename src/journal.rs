@@ -0,0 +1,449 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use solana_sdk::{
+    instruction::Instruction, signature::Signature, signer::Signer, transaction::Transaction,
+};
+
+use crate::{
+    error::{Result, UnmintError},
+    instructions::{self, Signers},
+    unmint::Unmint,
+};
+
+/// How often pending transactions of a previous run are checked while their blockhash is valid
+const PENDING_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Where a journaled action stands
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ActionStatus {
+    /// Recorded, no transaction built yet
+    Planned,
+    /// Signed and possibly sent; settled against the chain before anything is resent
+    Pending {
+        #[serde(with = "display_fromstr")]
+        signature: Signature,
+        last_valid_block_height: u64,
+        /// Base64 of the signed transaction, rebroadcast while its blockhash is valid
+        transaction: String,
+    },
+    Confirmed {
+        #[serde(with = "display_fromstr")]
+        signature: Signature,
+    },
+    /// Did not land; retried on the next run
+    Failed { error: String },
+    /// Not attempted, e.g. an invalid payout row; planned again on the next run
+    Skipped { reason: String },
+}
+
+impl ActionStatus {
+    pub fn is_confirmed(&self) -> bool {
+        matches!(self, ActionStatus::Confirmed { .. })
+    }
+}
+
+/// One action of a bulk operation and its latest status
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry<A> {
+    /// Stable identifier of the action within the operation
+    pub id: String,
+    #[serde(flatten)]
+    pub action: A,
+    #[serde(flatten)]
+    pub status: ActionStatus,
+}
+
+/// Append-only JSON lines record of a bulk operation
+///
+/// Every status change is appended and synced to disk before the operation
+/// moves on, so a crashed run can be reloaded with `Journal::open`, settled
+/// with `Unmint::reconcile` and continued with only the outstanding actions.
+pub struct Journal<A> {
+    path: PathBuf,
+    file: File,
+    order: Vec<String>,
+    entries: HashMap<String, JournalEntry<A>>,
+}
+
+impl<A> Journal<A>
+where
+    A: Serialize + DeserializeOwned + Clone + PartialEq,
+{
+    /// Opens the journal at `path`, loading the entries of a previous run
+    ///
+    /// A final line cut short by a crash is ignored; the lines before it still
+    /// hold the previous status of its action. Any other line that cannot be
+    /// parsed is an error.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let io_error = |source| UnmintError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        let mut journal = Self {
+            path: path.to_path_buf(),
+            file: OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(io_error)?,
+            order: vec![],
+            entries: HashMap::new(),
+        };
+
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(io_error(e)),
+        };
+        // only the final line can be cut short by a crash; it is cut off, or
+        // ended, so the next entry starts on a line of its own
+        let mut offset = 0;
+        let mut lines = contents.split_inclusive('\n').enumerate().peekable();
+        while let Some((index, line)) = lines.next() {
+            let last = lines.peek().is_none();
+            match serde_json::from_str::<JournalEntry<A>>(line.trim_end()) {
+                Ok(entry) => {
+                    journal.insert(entry);
+                    if last && !line.ends_with('\n') {
+                        journal.file.write_all(b"\n").map_err(io_error)?;
+                    }
+                }
+                Err(_) if last => journal.file.set_len(offset as u64).map_err(io_error)?,
+                Err(e) => {
+                    return Err(UnmintError::JournalMismatch(format!(
+                        "line {} cannot be parsed: {}",
+                        index + 1,
+                        e
+                    )));
+                }
+            }
+            offset += line.len();
+        }
+
+        Ok(journal)
+    }
+
+    /// Entries in the order they were first planned
+    pub fn entries(&self) -> impl Iterator<Item = &JournalEntry<A>> {
+        self.order.iter().map(|id| &self.entries[id])
+    }
+
+    pub fn get(&self, id: &str) -> Option<&JournalEntry<A>> {
+        self.entries.get(id)
+    }
+
+    /// Entries that are not confirmed yet
+    pub fn outstanding(&self) -> impl Iterator<Item = &JournalEntry<A>> {
+        self.entries().filter(|entry| !entry.status.is_confirmed())
+    }
+
+    /// Journals `action` under `id` and returns whether it still has to run
+    ///
+    /// An action that is not confirmed is replaced by `action`. Fails when a
+    /// different action was already confirmed under `id`.
+    pub fn plan(&mut self, id: &str, action: A) -> Result<bool> {
+        match self.entries.get(id) {
+            Some(entry) if entry.status.is_confirmed() => {
+                if entry.action != action {
+                    return Err(UnmintError::JournalMismatch(format!(
+                        "entry {} was confirmed for a different action",
+                        id
+                    )));
+                }
+                Ok(false)
+            }
+            Some(entry) if entry.action == action => Ok(true),
+            _ => {
+                self.append(JournalEntry {
+                    id: id.to_string(),
+                    action,
+                    status: ActionStatus::Planned,
+                })?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Journals `action` under `id` as planned, whatever its previous status
+    ///
+    /// For actions that can legitimately repeat, such as emptying a token
+    /// account that was opened again after an earlier run closed it.
+    pub fn replan(&mut self, id: &str, action: A) -> Result<()> {
+        self.append(JournalEntry {
+            id: id.to_string(),
+            action,
+            status: ActionStatus::Planned,
+        })
+    }
+
+    /// Appends the new status of a planned action
+    pub fn record(&mut self, id: &str, status: ActionStatus) -> Result<()> {
+        let action = self
+            .entries
+            .get(id)
+            .ok_or_else(|| UnmintError::JournalMismatch(format!("no entry {}", id)))?
+            .action
+            .clone();
+
+        self.append(JournalEntry {
+            id: id.to_string(),
+            action,
+            status,
+        })
+    }
+
+    fn append(&mut self, entry: JournalEntry<A>) -> Result<()> {
+        let mut line = serde_json::to_string(&entry).map_err(|e| anyhow::anyhow!(e))?;
+        line.push('\n');
+        self.file
+            .write_all(line.as_bytes())
+            .and_then(|_| self.file.sync_data())
+            .map_err(|source| UnmintError::Io {
+                path: self.path.clone(),
+                source,
+            })?;
+
+        self.insert(entry);
+        Ok(())
+    }
+
+    fn insert(&mut self, entry: JournalEntry<A>) {
+        if !self.entries.contains_key(&entry.id) {
+            self.order.push(entry.id.clone());
+        }
+        self.entries.insert(entry.id.clone(), entry);
+    }
+}
+
+/// Serializes a value through its `Display` and `FromStr` impls, so keys and
+/// signatures are written in base58
+pub(crate) mod display_fromstr {
+    use std::{fmt::Display, str::FromStr};
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: Display, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Unmint {
+    /// Settles the pending transactions of a journal against the chain
+    ///
+    /// Transactions that landed are confirmed or failed. One whose blockhash is
+    /// still valid is rebroadcast until it lands or expires, so an action is
+    /// never sent a second time while its first attempt can still land.
+    pub fn reconcile<A>(&self, journal: &mut Journal<A>) -> Result<()>
+    where
+        A: Serialize + DeserializeOwned + Clone + PartialEq,
+    {
+        loop {
+            let pending: Vec<(String, Signature, u64, String)> = journal
+                .entries()
+                .filter_map(|entry| match &entry.status {
+                    ActionStatus::Pending {
+                        signature,
+                        last_valid_block_height,
+                        transaction,
+                    } => Some((
+                        entry.id.clone(),
+                        *signature,
+                        *last_valid_block_height,
+                        transaction.clone(),
+                    )),
+                    _ => None,
+                })
+                .collect();
+            if pending.is_empty() {
+                return Ok(());
+            }
+
+            let settled = self.settle(
+                &pending
+                    .iter()
+                    .map(|(_, signature, last_valid_block_height, _)| {
+                        (*signature, *last_valid_block_height)
+                    })
+                    .collect::<Vec<_>>(),
+            )?;
+
+            let mut waiting = false;
+            for ((id, _, _, transaction), settled) in pending.into_iter().zip(settled) {
+                match settled {
+                    Some(status) => journal.record(&id, status)?,
+                    None => {
+                        // resending the same signed transaction cannot pay twice
+                        if let Ok(transaction) = decode_transaction(&transaction) {
+                            let _ = self.client.send_transaction(&transaction);
+                        }
+                        waiting = true;
+                    }
+                }
+            }
+
+            if waiting {
+                thread::sleep(PENDING_POLL_INTERVAL);
+            }
+        }
+    }
+
+    /// Sends `instructions` for the journaled actions `ids`
+    ///
+    /// The signed transaction is journaled as pending before it is sent. A
+    /// transaction rejected by preflight or failed on chain journals the actions
+    /// as failed. After a transport error or timeout the signature is checked on
+    /// chain: the actions are journaled as confirmed or failed once that is
+    /// settled, and left pending for `reconcile` while the transaction can
+    /// still land.
+    pub(crate) fn send_journaled<A>(
+        &self,
+        journal: &mut Journal<A>,
+        ids: &[String],
+        instructions: &[Instruction],
+        signers: &Signers,
+    ) -> Result<Signature>
+    where
+        A: Serialize + DeserializeOwned + Clone + PartialEq,
+    {
+        let (blockhash, last_valid_block_height) = self
+            .client
+            .get_latest_blockhash_with_commitment(self.client.commitment())?;
+        let message = instructions::build_message(
+            instructions,
+            &signers.fee_payer.pubkey(),
+            &blockhash,
+            self.priority_fee.as_ref(),
+        );
+
+        let transaction = match self.sign_message(message, signers) {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                for id in ids {
+                    journal.record(
+                        id,
+                        ActionStatus::Failed {
+                            error: e.to_string(),
+                        },
+                    )?;
+                }
+                return Err(e);
+            }
+        };
+
+        let signature = transaction.signatures[0];
+        let encoded = encode_transaction(&transaction)?;
+        for id in ids {
+            journal.record(
+                id,
+                ActionStatus::Pending {
+                    signature,
+                    last_valid_block_height,
+                    transaction: encoded.clone(),
+                },
+            )?;
+        }
+
+        match self.send_transaction(&transaction) {
+            Ok(signature) => {
+                for id in ids {
+                    journal.record(id, ActionStatus::Confirmed { signature })?;
+                }
+                Ok(signature)
+            }
+            // the transaction was rejected or failed, so it can no longer land
+            Err(e) if !matches!(e, UnmintError::Rpc(_) | UnmintError::TransactionExpired) => {
+                for id in ids {
+                    journal.record(
+                        id,
+                        ActionStatus::Failed {
+                            error: e.to_string(),
+                        },
+                    )?;
+                }
+                Err(e)
+            }
+            Err(e) => {
+                // a rebroadcast may have landed before the error; the chain decides
+                if let Ok(settled) = self.settle(&[(signature, last_valid_block_height)])
+                    && let Some(status) = settled.into_iter().flatten().next()
+                {
+                    let status = match status {
+                        ActionStatus::Failed { .. } => ActionStatus::Failed {
+                            error: e.to_string(),
+                        },
+                        status => status,
+                    };
+                    for id in ids {
+                        journal.record(id, status.clone())?;
+                    }
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Journal status of each `(signature, last_valid_block_height)` on chain,
+    /// `None` while the transaction can still land
+    fn settle(&self, pending: &[(Signature, u64)]) -> Result<Vec<Option<ActionStatus>>> {
+        let signatures: Vec<Signature> = pending.iter().map(|(signature, _)| *signature).collect();
+        let statuses = self
+            .client
+            .get_signature_statuses_with_history(&signatures)?
+            .value;
+        let block_height = self.client.get_block_height()?;
+
+        Ok(pending
+            .iter()
+            .zip(statuses)
+            .map(
+                |(&(signature, last_valid_block_height), status)| match status {
+                    Some(status) if status.err.is_some() => Some(ActionStatus::Failed {
+                        error: format!("transaction {} failed", signature),
+                    }),
+                    Some(status) if status.satisfies_commitment(self.client.commitment()) => {
+                        Some(ActionStatus::Confirmed { signature })
+                    }
+                    None if block_height > last_valid_block_height => Some(ActionStatus::Failed {
+                        error: format!("transaction {} expired", signature),
+                    }),
+                    _ => None,
+                },
+            )
+            .collect())
+    }
+}
+
+fn encode_transaction(transaction: &Transaction) -> Result<String> {
+    let bytes = bincode::serialize(transaction).map_err(|e| anyhow::anyhow!(e))?;
+    Ok(BASE64.encode(bytes))
+}
+
+fn decode_transaction(encoded: &str) -> Result<Transaction> {
+    let bytes = BASE64.decode(encoded).map_err(|e| anyhow::anyhow!(e))?;
+    Ok(bincode::deserialize(&bytes).map_err(|e| anyhow::anyhow!(e))?)
+}
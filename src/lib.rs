@@ -1,7 +1,60 @@
+mod amount;
+mod async_unmint;
+mod batch;
+mod close_mint;
+mod delegation;
+mod error;
+mod instructions;
+mod journal;
+mod metadata;
+mod mint;
+mod mint_admin;
+mod offchain;
+mod plan;
+mod policy;
+mod preflight;
+mod recipient;
+mod reclaim;
+mod retire;
+mod risk;
+mod scan;
+mod simulate;
+mod stake;
 mod token_program;
+mod transfer_fee;
 mod unmint;
 mod wallet;
 
-pub use token_program::TokenProgram;
-pub use unmint::Unmint;
+pub use amount::{AmountInput, Lamports, SOL_DECIMALS, TokenAmount};
+pub use async_unmint::{AsyncUnmint, SendAndClose};
+pub use batch::{Payout, PayoutResult};
+pub use close_mint::ClosableMint;
+pub use delegation::Delegation;
+pub use error::{Result, UnmintError};
+pub use instructions::{DrainMode, PriorityFee, check_memo, max_transferable};
+pub use journal::{ActionStatus, Journal, JournalEntry};
+pub use metadata::{
+    METAPLEX_METADATA_PROGRAM_ID, TokenMetadata, metaplex_metadata_address, token_label,
+};
+pub use mint::{DEFAULT_MINT_CACHE_TTL, MintExtension, MintInfo};
+pub use mint_admin::{NewMint, NewMintExtension};
+pub use offchain::*;
+pub use plan::{Balance, BalanceDelta, Drift, Plan, PlanAction, PlanStep};
+pub use policy::{MintLimits, Policy, Violation};
+pub use preflight::{Asset, Requirements, Shortfall};
+pub use recipient::{Recipient, RecipientKind, validate_recipient};
+pub use reclaim::{
+    AccountReclaim, EmptyTokenAccount, LookupTableState, ReclaimKind, ReclaimReport,
+    ReclaimableAccount,
+};
+pub use retire::{
+    RetirePolicy, RetireReceipt, RetireStep, TokenAction, TokenDisposition, TokenReceipt,
+};
+pub use risk::{RiskConfig, RiskFlag, RiskLevel, RiskReport, assess_token};
+pub use scan::OwnedTokenAccount;
+pub use simulate::{BalanceChange, Simulation, TokenBalanceChange, balance_changes};
+pub use stake::{StakeAccount, StakeActivation, StakeReclaim};
+pub use token_program::{AuthorityKind, TokenProgram};
+pub use transfer_fee::{FeeMode, FeeQuote, TokenTransfer};
+pub use unmint::{PubkeyInput, UiTokenAmount, Unmint};
 pub use wallet::*;
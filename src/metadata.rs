@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{error::Result, mint::MintInfo, token_program::TokenProgram, unmint::Unmint};
+
+/// Metaplex Token Metadata program
+pub const METAPLEX_METADATA_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+/// Offset of the mint in a Metaplex metadata account, after the key and update authority
+const METAPLEX_MINT_OFFSET: usize = 1 + 32;
+
+/// Offset of the name in a Metaplex metadata account
+const METAPLEX_NAME_OFFSET: usize = METAPLEX_MINT_OFFSET + 32;
+
+/// Human-readable description of a mint
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TokenMetadata {
+    pub name: String,
+    pub symbol: String,
+    /// Off-chain JSON describing the token
+    pub uri: String,
+}
+
+impl TokenMetadata {
+    /// Builds metadata from on-chain strings, dropping the NUL padding Metaplex uses
+    pub fn new(name: &str, symbol: &str, uri: &str) -> Self {
+        let clean = |s: &str| s.trim_end_matches('\0').trim().to_string();
+        Self {
+            name: clean(name),
+            symbol: clean(symbol),
+            uri: clean(uri),
+        }
+    }
+
+    /// Decodes a Metaplex metadata account of `mint`
+    ///
+    /// Returns `None` when the data is not a metadata account of that mint.
+    pub fn from_metaplex(mint: &Pubkey, data: &[u8]) -> Option<Self> {
+        if data.get(METAPLEX_MINT_OFFSET..METAPLEX_NAME_OFFSET)? != mint.as_ref() {
+            return None;
+        }
+
+        let mut rest = &data[METAPLEX_NAME_OFFSET..];
+        let name = read_borsh_string(&mut rest)?;
+        let symbol = read_borsh_string(&mut rest)?;
+        let uri = read_borsh_string(&mut rest)?;
+
+        Some(Self::new(&name, &symbol, &uri))
+    }
+}
+
+/// Address of the Metaplex metadata account of `mint`
+pub fn metaplex_metadata_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"metadata",
+            METAPLEX_METADATA_PROGRAM_ID.as_ref(),
+            mint.as_ref(),
+        ],
+        &METAPLEX_METADATA_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Short name for `mint` in reports: the symbol when known, otherwise the
+/// shortened address, e.g. `EPjF…Dt1v`
+pub fn token_label(metadata: Option<&TokenMetadata>, mint: &Pubkey) -> String {
+    match metadata {
+        Some(metadata) if !metadata.symbol.is_empty() => metadata.symbol.clone(),
+        Some(metadata) if !metadata.name.is_empty() => metadata.name.clone(),
+        _ => {
+            let address = mint.to_string();
+            format!("{}…{}", &address[..4], &address[address.len() - 4..])
+        }
+    }
+}
+
+fn read_borsh_string(data: &mut &[u8]) -> Option<String> {
+    let len = u32::from_le_bytes(data.get(..4)?.try_into().ok()?) as usize;
+    let bytes = data.get(4..4 + len)?;
+    *data = &data[4 + len..];
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+impl Unmint {
+    /// Resolves name, symbol and URI of every mint in `mints`
+    ///
+    /// Token-2022 metadata stored in the mint is read directly. Otherwise the
+    /// MetadataPointer target or the Metaplex metadata PDA is fetched, all in
+    /// batched `getMultipleAccounts` requests. Mints without metadata are left out.
+    pub fn token_metadata(&self, mints: &[Pubkey]) -> Result<HashMap<Pubkey, TokenMetadata>> {
+        let infos = self.mint_infos(mints)?;
+        let mut metadata = HashMap::new();
+        let mut lookups = vec![];
+
+        for (mint, info) in &infos {
+            match info.metadata() {
+                Some(embedded) => {
+                    metadata.insert(*mint, embedded.clone());
+                }
+                None => {
+                    let address = info
+                        .metadata_address()
+                        .filter(|address| address != mint)
+                        .unwrap_or_else(|| metaplex_metadata_address(mint));
+                    lookups.push((*mint, address));
+                }
+            }
+        }
+
+        let addresses: Vec<Pubkey> = lookups.iter().map(|(_, address)| *address).collect();
+        for ((mint, address), account) in lookups.iter().zip(self.multiple_accounts(&addresses)?) {
+            let Some(account) = account else { continue };
+
+            let found = if account.owner == METAPLEX_METADATA_PROGRAM_ID {
+                TokenMetadata::from_metaplex(mint, &account.data)
+            } else if TokenProgram::is_token_program(&account.owner) {
+                // a pointer to another mint that stores the metadata
+                MintInfo::decode(*address, &account)
+                    .ok()
+                    .and_then(|info| info.metadata().cloned())
+            } else {
+                None
+            };
+
+            if let Some(found) = found {
+                metadata.insert(*mint, found);
+            }
+        }
+
+        Ok(metadata)
+    }
+}
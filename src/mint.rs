@@ -0,0 +1,419 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use solana_sdk::{
+    account::Account, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey,
+};
+use spl_token_2022::{
+    extension::{
+        BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+        default_account_state::DefaultAccountState, group_member_pointer::GroupMemberPointer,
+        group_pointer::GroupPointer, interest_bearing_mint::InterestBearingConfig,
+        metadata_pointer::MetadataPointer, mint_close_authority::MintCloseAuthority,
+        pausable::PausableConfig, permanent_delegate::PermanentDelegate,
+        scaled_ui_amount::ScaledUiAmountConfig, transfer_fee::TransferFeeConfig,
+        transfer_hook::TransferHook,
+    },
+    state::{Account as TokenAccount, AccountState, Mint},
+};
+use spl_token_metadata_interface::state::TokenMetadata as SplTokenMetadata;
+
+use crate::{
+    error::{Result, UnmintError},
+    metadata::TokenMetadata,
+    token_program::TokenProgram,
+    unmint::{PubkeyInput, Unmint},
+};
+
+/// How long `mint_info` reuses a fetched mint by default
+pub const DEFAULT_MINT_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// A decoded Token-2022 mint extension
+#[derive(Debug, Clone, PartialEq)]
+pub enum MintExtension {
+    /// Fee withheld on every transfer
+    TransferFee(TransferFeeConfig),
+    /// Program invoked on every transfer
+    TransferHook {
+        authority: Option<Pubkey>,
+        program_id: Option<Pubkey>,
+    },
+    /// Delegate that can move or burn tokens from any account of the mint
+    PermanentDelegate { delegate: Option<Pubkey> },
+    /// Tokens cannot be transferred, only burned
+    NonTransferable,
+    /// Balances accrue interest for display
+    InterestBearing {
+        rate_authority: Option<Pubkey>,
+        /// Current rate in basis points
+        current_rate: i16,
+    },
+    /// State new token accounts start in
+    DefaultAccountState(AccountState),
+    /// Account holding the token metadata
+    MetadataPointer {
+        authority: Option<Pubkey>,
+        metadata_address: Option<Pubkey>,
+    },
+    /// Name, symbol and URI stored in the mint itself
+    TokenMetadata(TokenMetadata),
+    /// Authority allowed to close the mint once supply is zero
+    MintCloseAuthority { close_authority: Option<Pubkey> },
+    GroupPointer {
+        authority: Option<Pubkey>,
+        group_address: Option<Pubkey>,
+    },
+    GroupMemberPointer {
+        authority: Option<Pubkey>,
+        member_address: Option<Pubkey>,
+    },
+    /// Transfers, mints and burns can be paused
+    Pausable {
+        authority: Option<Pubkey>,
+        paused: bool,
+    },
+    /// Balances are displayed multiplied by `multiplier`
+    ScaledUiAmount {
+        authority: Option<Pubkey>,
+        multiplier: f64,
+    },
+    /// Any other extension, identified by its type only
+    Other(ExtensionType),
+}
+
+/// A mint returned by `Unmint::mint_info`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MintInfo {
+    pub address: Pubkey,
+    pub token_program: TokenProgram,
+    pub supply: u64,
+    pub decimals: u8,
+    /// `None` once minting is renounced
+    pub mint_authority: Option<Pubkey>,
+    pub freeze_authority: Option<Pubkey>,
+    /// Token-2022 extensions, empty for legacy mints
+    pub extensions: Vec<MintExtension>,
+}
+
+impl MintInfo {
+    /// Decodes a fetched mint account
+    pub fn decode(address: Pubkey, account: &Account) -> Result<Self> {
+        let token_program =
+            TokenProgram::from_program_id(&account.owner).ok_or(UnmintError::NotTokenProgram {
+                account: address,
+                owner: account.owner,
+            })?;
+
+        let invalid = |e: ProgramError| UnmintError::InvalidAccountData {
+            address,
+            reason: e.to_string(),
+        };
+        let state = StateWithExtensions::<Mint>::unpack(&account.data).map_err(invalid)?;
+        let extension_types = state.get_extension_types().map_err(invalid)?;
+
+        let extensions = extension_types
+            .into_iter()
+            .map(|extension_type| decode_extension(&state, extension_type))
+            .collect();
+
+        Ok(Self {
+            address,
+            token_program,
+            supply: state.base.supply,
+            decimals: state.base.decimals,
+            mint_authority: state.base.mint_authority.into(),
+            freeze_authority: state.base.freeze_authority.into(),
+            extensions,
+        })
+    }
+
+    pub fn transfer_fee_config(&self) -> Option<&TransferFeeConfig> {
+        self.extensions
+            .iter()
+            .find_map(|extension| match extension {
+                MintExtension::TransferFee(config) => Some(config),
+                _ => None,
+            })
+    }
+
+    /// Metadata stored in the mint by the TokenMetadata extension
+    pub fn metadata(&self) -> Option<&TokenMetadata> {
+        self.extensions
+            .iter()
+            .find_map(|extension| match extension {
+                MintExtension::TokenMetadata(metadata) => Some(metadata),
+                _ => None,
+            })
+    }
+
+    /// Account the MetadataPointer extension points to
+    pub fn metadata_address(&self) -> Option<Pubkey> {
+        self.extensions
+            .iter()
+            .find_map(|extension| match extension {
+                MintExtension::MetadataPointer {
+                    metadata_address, ..
+                } => *metadata_address,
+                _ => None,
+            })
+    }
+
+    pub fn is_non_transferable(&self) -> bool {
+        self.extensions.contains(&MintExtension::NonTransferable)
+    }
+
+    /// Size of an associated token account of this mint
+    ///
+    /// Token-2022 accounts carry the extensions the mint requires plus
+    /// ImmutableOwner, which the associated token account program always adds.
+    pub fn token_account_len(&self) -> Result<usize> {
+        if self.token_program == TokenProgram::Legacy {
+            return Ok(TokenAccount::LEN);
+        }
+
+        let mint_extensions: Vec<ExtensionType> = self
+            .extensions
+            .iter()
+            .map(MintExtension::extension_type)
+            .collect();
+
+        let mut account_extensions = vec![ExtensionType::ImmutableOwner];
+        for extension in ExtensionType::get_required_init_account_extensions(&mint_extensions) {
+            if !account_extensions.contains(&extension) {
+                account_extensions.push(extension);
+            }
+        }
+
+        ExtensionType::try_calculate_account_len::<TokenAccount>(&account_extensions).map_err(|e| {
+            UnmintError::InvalidAccountData {
+                address: self.address,
+                reason: e.to_string(),
+            }
+        })
+    }
+}
+
+impl MintExtension {
+    pub fn extension_type(&self) -> ExtensionType {
+        match self {
+            MintExtension::TransferFee(_) => ExtensionType::TransferFeeConfig,
+            MintExtension::TransferHook { .. } => ExtensionType::TransferHook,
+            MintExtension::PermanentDelegate { .. } => ExtensionType::PermanentDelegate,
+            MintExtension::NonTransferable => ExtensionType::NonTransferable,
+            MintExtension::InterestBearing { .. } => ExtensionType::InterestBearingConfig,
+            MintExtension::DefaultAccountState(_) => ExtensionType::DefaultAccountState,
+            MintExtension::MetadataPointer { .. } => ExtensionType::MetadataPointer,
+            MintExtension::TokenMetadata(_) => ExtensionType::TokenMetadata,
+            MintExtension::MintCloseAuthority { .. } => ExtensionType::MintCloseAuthority,
+            MintExtension::GroupPointer { .. } => ExtensionType::GroupPointer,
+            MintExtension::GroupMemberPointer { .. } => ExtensionType::GroupMemberPointer,
+            MintExtension::Pausable { .. } => ExtensionType::Pausable,
+            MintExtension::ScaledUiAmount { .. } => ExtensionType::ScaledUiAmount,
+            MintExtension::Other(extension_type) => *extension_type,
+        }
+    }
+}
+
+fn decode_extension<S: BaseStateWithExtensions<Mint>>(
+    state: &S,
+    extension_type: ExtensionType,
+) -> MintExtension {
+    let decoded =
+        match extension_type {
+            ExtensionType::TransferFeeConfig => state
+                .get_extension::<TransferFeeConfig>()
+                .map(|config| MintExtension::TransferFee(*config)),
+            ExtensionType::TransferHook => {
+                state
+                    .get_extension::<TransferHook>()
+                    .map(|hook| MintExtension::TransferHook {
+                        authority: hook.authority.into(),
+                        program_id: hook.program_id.into(),
+                    })
+            }
+            ExtensionType::PermanentDelegate => {
+                state.get_extension::<PermanentDelegate>().map(|delegate| {
+                    MintExtension::PermanentDelegate {
+                        delegate: delegate.delegate.into(),
+                    }
+                })
+            }
+            ExtensionType::NonTransferable => Ok(MintExtension::NonTransferable),
+            ExtensionType::InterestBearingConfig => state
+                .get_extension::<InterestBearingConfig>()
+                .map(|config| MintExtension::InterestBearing {
+                    rate_authority: config.rate_authority.into(),
+                    current_rate: config.current_rate.into(),
+                }),
+            ExtensionType::DefaultAccountState => state
+                .get_extension::<DefaultAccountState>()
+                .and_then(|default| {
+                    AccountState::try_from(default.state)
+                        .map_err(|_| ProgramError::InvalidAccountData)
+                })
+                .map(MintExtension::DefaultAccountState),
+            ExtensionType::MetadataPointer => {
+                state.get_extension::<MetadataPointer>().map(|pointer| {
+                    MintExtension::MetadataPointer {
+                        authority: pointer.authority.into(),
+                        metadata_address: pointer.metadata_address.into(),
+                    }
+                })
+            }
+            ExtensionType::TokenMetadata => state
+                .get_variable_len_extension::<SplTokenMetadata>()
+                .map(|metadata| {
+                    MintExtension::TokenMetadata(TokenMetadata::new(
+                        &metadata.name,
+                        &metadata.symbol,
+                        &metadata.uri,
+                    ))
+                }),
+            ExtensionType::MintCloseAuthority => {
+                state.get_extension::<MintCloseAuthority>().map(|close| {
+                    MintExtension::MintCloseAuthority {
+                        close_authority: close.close_authority.into(),
+                    }
+                })
+            }
+            ExtensionType::GroupPointer => {
+                state
+                    .get_extension::<GroupPointer>()
+                    .map(|pointer| MintExtension::GroupPointer {
+                        authority: pointer.authority.into(),
+                        group_address: pointer.group_address.into(),
+                    })
+            }
+            ExtensionType::GroupMemberPointer => {
+                state.get_extension::<GroupMemberPointer>().map(|pointer| {
+                    MintExtension::GroupMemberPointer {
+                        authority: pointer.authority.into(),
+                        member_address: pointer.member_address.into(),
+                    }
+                })
+            }
+            ExtensionType::Pausable => {
+                state
+                    .get_extension::<PausableConfig>()
+                    .map(|config| MintExtension::Pausable {
+                        authority: config.authority.into(),
+                        paused: config.paused.into(),
+                    })
+            }
+            ExtensionType::ScaledUiAmount => {
+                state.get_extension::<ScaledUiAmountConfig>().map(|config| {
+                    MintExtension::ScaledUiAmount {
+                        authority: config.authority.into(),
+                        multiplier: config.multiplier.into(),
+                    }
+                })
+            }
+            other => Ok(MintExtension::Other(other)),
+        };
+
+    decoded.unwrap_or(MintExtension::Other(extension_type))
+}
+
+/// Mints fetched by `mint_info`, reused by sends for `ttl`
+///
+/// Only decimals, program and extensions are read from cached mints; supply
+/// and authorities come from `mint_info`, which always fetches.
+pub(crate) struct MintCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<Pubkey, (Instant, MintInfo)>>,
+}
+
+impl MintCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, mint: &Pubkey) -> Option<MintInfo> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(mint)
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < self.ttl)
+            .map(|(_, info)| info.clone())
+    }
+
+    pub fn insert(&self, info: MintInfo) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (fetched_at, _)| fetched_at.elapsed() < self.ttl);
+        entries.insert(info.address, (Instant::now(), info));
+    }
+}
+
+impl Default for MintCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MINT_CACHE_TTL)
+    }
+}
+
+impl Unmint {
+    /// Fetches and decodes a mint, including its Token-2022 extensions
+    ///
+    /// Always fetched, so supply and authorities are current. The result
+    /// refreshes the cache sends use, see `with_mint_cache_ttl`.
+    pub fn mint_info<'a, M>(&self, mint: M) -> Result<MintInfo>
+    where
+        M: Into<PubkeyInput<'a>>,
+    {
+        let mint_pubkey = mint.into().to_pubkey()?;
+
+        let account = self
+            .fetch_account(&mint_pubkey)?
+            .ok_or(UnmintError::AccountNotFound(mint_pubkey))?;
+
+        let info = MintInfo::decode(mint_pubkey, &account)?;
+        self.mints.insert(info.clone());
+
+        Ok(info)
+    }
+
+    /// `mint_info`, reusing a mint cached within `DEFAULT_MINT_CACHE_TTL`
+    ///
+    /// For decimals, program and extensions only; supply may be stale.
+    pub(crate) fn cached_mint_info(&self, mint: &Pubkey) -> Result<MintInfo> {
+        match self.mints.get(mint) {
+            Some(info) => Ok(info),
+            None => self.mint_info(mint),
+        }
+    }
+
+    /// Fetches every mint in `mints` that is not cached with one batched lookup
+    ///
+    /// Mints that do not exist are left out of the result.
+    pub(crate) fn mint_infos(&self, mints: &[Pubkey]) -> Result<HashMap<Pubkey, MintInfo>> {
+        let mut infos = HashMap::new();
+        let mut missing = vec![];
+
+        for mint in mints {
+            match self.mints.get(mint) {
+                Some(info) => {
+                    infos.insert(*mint, info);
+                }
+                None => missing.push(*mint),
+            }
+        }
+        missing.sort();
+        missing.dedup();
+
+        for (mint, account) in missing.iter().zip(self.multiple_accounts(&missing)?) {
+            let Some(account) = account else { continue };
+            let info = MintInfo::decode(*mint, &account)?;
+            self.mints.insert(info.clone());
+            infos.insert(*mint, info);
+        }
+
+        Ok(infos)
+    }
+}
@@ -0,0 +1,344 @@
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+};
+use solana_system_interface::instruction as system_instruction;
+use spl_token_2022::{
+    extension::{
+        ExtensionType, metadata_pointer, transfer_fee::instruction::initialize_transfer_fee_config,
+    },
+    state::Mint,
+};
+use spl_token_metadata_interface::state::TokenMetadata as MetadataState;
+
+use crate::{
+    amount::AmountInput,
+    error::{Result, UnmintError, parse_pubkey},
+    instructions::Signers,
+    metadata::TokenMetadata,
+    token_program::{AuthorityKind, TokenProgram},
+    unmint::Unmint,
+};
+
+/// Token-2022 extension initialized by `create_mint`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NewMintExtension {
+    /// Metadata stored in the mint itself, updatable by the mint authority
+    Metadata(TokenMetadata),
+    /// Fee withheld on every transfer; `authority` may change the fee and
+    /// withdraw withheld fees
+    TransferFee {
+        basis_points: u16,
+        maximum_fee: u64,
+        authority: Option<Pubkey>,
+    },
+    /// Authority allowed to close the mint once its supply is zero
+    CloseAuthority(Pubkey),
+}
+
+/// A mint created by `create_mint`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NewMint {
+    pub address: Pubkey,
+    pub signature: Signature,
+}
+
+impl Unmint {
+    /// Creates a mint under the token program of this `Unmint`
+    ///
+    /// The mint address is a fresh keypair. Extensions are only available for
+    /// Token-2022; `NewMintExtension::Metadata` needs the payer to be the mint
+    /// authority, since the mint authority signs the metadata.
+    ///
+    /// # Arguments
+    /// * `payer_base58_string` - Base58 private key paying rent and the fee
+    /// * `decimals` - Decimals of the mint
+    /// * `mint_authority` - Optional, defaults to the payer
+    /// * `freeze_authority` - Optional, `None` creates a mint that cannot freeze accounts
+    /// * `extensions` - Token-2022 extensions to initialize
+    pub fn create_mint(
+        &self,
+        payer_base58_string: &str,
+        decimals: u8,
+        mint_authority: Option<&str>,
+        freeze_authority: Option<&str>,
+        extensions: &[NewMintExtension],
+    ) -> Result<NewMint> {
+        let mut signers = Signers::parse(payer_base58_string, None)?;
+        let payer = signers.fee_payer.pubkey();
+        let mint_authority = mint_authority
+            .map(parse_pubkey)
+            .transpose()?
+            .unwrap_or(payer);
+        let freeze_authority = freeze_authority.map(parse_pubkey).transpose()?;
+
+        if self.token_program == TokenProgram::Legacy && !extensions.is_empty() {
+            return Err(UnmintError::Unsupported(
+                "mint extensions on a legacy mint".to_string(),
+            ));
+        }
+
+        let mint = Keypair::new();
+        let mint_pubkey = mint.pubkey();
+        let program_id = self.token_program.program_id();
+
+        let mut extension_types = vec![];
+        let mut extension_ixs = vec![];
+        let mut metadata = None;
+        for extension in extensions {
+            match extension {
+                NewMintExtension::Metadata(token_metadata) => {
+                    if mint_authority != payer {
+                        // the metadata is initialized in the same transaction
+                        return Err(UnmintError::MissingSigner(mint_authority));
+                    }
+                    extension_types.push(ExtensionType::MetadataPointer);
+                    extension_ixs.push(
+                        metadata_pointer::instruction::initialize(
+                            &program_id,
+                            &mint_pubkey,
+                            Some(mint_authority),
+                            Some(mint_pubkey),
+                        )
+                        .map_err(UnmintError::InvalidInstruction)?,
+                    );
+                    metadata = Some(token_metadata);
+                }
+                NewMintExtension::TransferFee {
+                    basis_points,
+                    maximum_fee,
+                    authority,
+                } => {
+                    extension_types.push(ExtensionType::TransferFeeConfig);
+                    extension_ixs.push(
+                        initialize_transfer_fee_config(
+                            &program_id,
+                            &mint_pubkey,
+                            authority.as_ref(),
+                            authority.as_ref(),
+                            *basis_points,
+                            *maximum_fee,
+                        )
+                        .map_err(UnmintError::InvalidInstruction)?,
+                    );
+                }
+                NewMintExtension::CloseAuthority(close_authority) => {
+                    extension_types.push(ExtensionType::MintCloseAuthority);
+                    extension_ixs.push(
+                        spl_token_2022::instruction::initialize_mint_close_authority(
+                            &program_id,
+                            &mint_pubkey,
+                            Some(close_authority),
+                        )
+                        .map_err(UnmintError::InvalidInstruction)?,
+                    );
+                }
+            }
+        }
+
+        let space = ExtensionType::try_calculate_account_len::<Mint>(&extension_types)
+            .map_err(UnmintError::InvalidInstruction)?;
+        // the metadata is written after the mint is initialized, growing the
+        // account; its rent is paid up front
+        let metadata_len = match metadata {
+            Some(metadata) => MetadataState {
+                update_authority: Some(mint_authority)
+                    .try_into()
+                    .map_err(UnmintError::InvalidInstruction)?,
+                mint: mint_pubkey,
+                name: metadata.name.clone(),
+                symbol: metadata.symbol.clone(),
+                uri: metadata.uri.clone(),
+                additional_metadata: vec![],
+            }
+            .tlv_size_of()
+            .map_err(UnmintError::InvalidInstruction)?,
+            None => 0,
+        };
+        let lamports = self
+            .client
+            .get_minimum_balance_for_rent_exemption(space + metadata_len)?;
+
+        let mut instructions: Vec<Instruction> = vec![system_instruction::create_account(
+            &payer,
+            &mint_pubkey,
+            lamports,
+            space as u64,
+            &program_id,
+        )];
+        instructions.extend(extension_ixs);
+        instructions.push(self.token_program.initialize_mint_ix(
+            &mint_pubkey,
+            &mint_authority,
+            freeze_authority.as_ref(),
+            decimals,
+        )?);
+        if let Some(metadata) = metadata {
+            instructions.push(spl_token_metadata_interface::instruction::initialize(
+                &program_id,
+                &mint_pubkey,
+                &mint_authority,
+                &mint_pubkey,
+                &mint_authority,
+                metadata.name.clone(),
+                metadata.symbol.clone(),
+                metadata.uri.clone(),
+            ));
+        }
+
+        signers.extra.push(mint);
+        let signature = self.send_instructions(&instructions, &signers)?;
+
+        Ok(NewMint {
+            address: mint_pubkey,
+            signature,
+        })
+    }
+
+    /// Mints `amount` to the wallet `to_address`, creating its associated
+    /// token account when needed
+    ///
+    /// # Arguments
+    /// * `token_mint_address` - Mint to issue
+    /// * `mint_authority_base58_string` - Base58 private key of the mint authority
+    /// * `to_address` - Wallet or token account of the mint receiving the tokens
+    /// * `amount` - UI amount, or `AmountInput::Raw`
+    /// * `fee_payer_base58_string` - Optional, who pays the fee and the account rent
+    pub fn mint_to<'a, A>(
+        &self,
+        token_mint_address: &str,
+        mint_authority_base58_string: &str,
+        to_address: &str,
+        amount: A,
+        fee_payer_base58_string: Option<&str>,
+    ) -> Result<Signature>
+    where
+        A: Into<AmountInput<'a>>,
+    {
+        let mint_pubkey = parse_pubkey(token_mint_address)?;
+        let to_pubkey = parse_pubkey(to_address)?;
+        let signers = Signers::parse(mint_authority_base58_string, fee_payer_base58_string)?;
+
+        let mint = self.mint_info(&mint_pubkey)?;
+        if mint.mint_authority != Some(signers.from.pubkey()) {
+            return Err(UnmintError::WrongAuthority {
+                address: mint_pubkey,
+                expected: mint.mint_authority,
+                found: signers.from.pubkey(),
+            });
+        }
+        let amount = amount.into().to_token_amount(mint.decimals)?.raw();
+
+        let destination = self.destination(&to_pubkey, &mint_pubkey)?;
+        let mut instructions = vec![];
+        if destination.create {
+            instructions.push(self.token_program.create_ata_idempotent_ix(
+                &signers.fee_payer.pubkey(),
+                &destination.owner,
+                &mint_pubkey,
+            ));
+        }
+        instructions.push(self.token_program.mint_to_ix(
+            &mint_pubkey,
+            &destination.address,
+            &signers.from,
+            amount,
+            mint.decimals,
+        )?);
+
+        self.send_instructions(&instructions, &signers)
+    }
+
+    /// Hands `kind` of the mint or token account `address` to `new_authority`
+    ///
+    /// `None` renounces the authority for good, e.g. renouncing
+    /// `AuthorityKind::MintTokens` fixes the supply and renouncing
+    /// `AuthorityKind::FreezeAccount` means no account can be frozen again.
+    ///
+    /// # Arguments
+    /// * `address` - Mint or token account
+    /// * `kind` - Which authority to change
+    /// * `authority_base58_string` - Base58 private key of the current authority, pays the fee
+    /// * `new_authority` - New authority, `None` to renounce
+    pub fn set_authority(
+        &self,
+        address: &str,
+        kind: AuthorityKind,
+        authority_base58_string: &str,
+        new_authority: Option<&str>,
+    ) -> Result<Signature> {
+        let owned = parse_pubkey(address)?;
+        let signers = Signers::parse(authority_base58_string, None)?;
+        let new_authority = new_authority.map(parse_pubkey).transpose()?;
+
+        let set_authority_ix = self.token_program.set_authority_ix(
+            &owned,
+            new_authority.as_ref(),
+            kind,
+            &signers.from,
+        )?;
+
+        self.send_instructions(&[set_authority_ix], &signers)
+    }
+
+    /// Freezes `token_account_address`, signed by the freeze authority of its mint
+    pub fn freeze(
+        &self,
+        token_account_address: &str,
+        token_mint_address: &str,
+        freeze_authority_base58_string: &str,
+    ) -> Result<Signature> {
+        let (account, mint, signers) = self.freeze_signers(
+            token_account_address,
+            token_mint_address,
+            freeze_authority_base58_string,
+        )?;
+
+        let freeze_ix = self
+            .token_program
+            .freeze_ix(&account, &mint, &signers.from)?;
+        self.send_instructions(&[freeze_ix], &signers)
+    }
+
+    /// Thaws a frozen `token_account_address`, signed by the freeze authority of its mint
+    pub fn thaw(
+        &self,
+        token_account_address: &str,
+        token_mint_address: &str,
+        freeze_authority_base58_string: &str,
+    ) -> Result<Signature> {
+        let (account, mint, signers) = self.freeze_signers(
+            token_account_address,
+            token_mint_address,
+            freeze_authority_base58_string,
+        )?;
+
+        let thaw_ix = self.token_program.thaw_ix(&account, &mint, &signers.from)?;
+        self.send_instructions(&[thaw_ix], &signers)
+    }
+
+    /// Parses the arguments of `freeze` and `thaw` and checks the freeze authority
+    fn freeze_signers(
+        &self,
+        token_account_address: &str,
+        token_mint_address: &str,
+        freeze_authority_base58_string: &str,
+    ) -> Result<(Pubkey, Pubkey, Signers)> {
+        let account = parse_pubkey(token_account_address)?;
+        let mint_pubkey = parse_pubkey(token_mint_address)?;
+        let signers = Signers::parse(freeze_authority_base58_string, None)?;
+
+        let mint = self.mint_info(&mint_pubkey)?;
+        if mint.freeze_authority != Some(signers.from.pubkey()) {
+            return Err(UnmintError::WrongAuthority {
+                address: mint_pubkey,
+                expected: mint.freeze_authority,
+                found: signers.from.pubkey(),
+            });
+        }
+
+        Ok((account, mint_pubkey, signers))
+    }
+}
@@ -0,0 +1,334 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use solana_offchain_message::OffchainMessage;
+use solana_sdk::{pubkey::Pubkey, signature::Signature, signer::Signer};
+
+pub use solana_offchain_message::MessageFormat;
+
+use crate::error::{Result, UnmintError};
+
+/// Off-chain message version produced by `sign_message`
+pub const OFFCHAIN_MESSAGE_VERSION: u8 = 0;
+
+/// Builds the off-chain message envelope for `message`
+///
+/// The envelope carries the `\xffsolana offchain` signing domain, the header
+/// version and the message format (restricted ASCII, limited UTF-8 or extended UTF-8),
+/// picked from the content and length of `message`.
+pub fn offchain_message(message: &[u8]) -> Result<OffchainMessage> {
+    OffchainMessage::new(OFFCHAIN_MESSAGE_VERSION, message)
+        .map_err(|e| UnmintError::InvalidMessage(e.to_string()))
+}
+
+/// Signs `message` using the Solana off-chain message signing format
+///
+/// # Example
+/// ```no_run
+/// use solana_sdk::signature::Keypair;
+///
+/// let keypair = Keypair::new();
+/// let signature = sol_unmint::sign_message(&keypair, b"hello").unwrap();
+/// ```
+pub fn sign_message(signer: &dyn Signer, message: &[u8]) -> Result<Signature> {
+    offchain_message(message)?
+        .sign(signer)
+        .map_err(|e| anyhow::anyhow!("failed to sign off-chain message: {}", e).into())
+}
+
+/// Verifies an off-chain message signature produced by `sign_message`
+///
+/// Returns `false` when the signature does not match, and an error when
+/// `message` cannot be encoded as an off-chain message at all.
+pub fn verify_message(address: &Pubkey, message: &[u8], signature: &Signature) -> Result<bool> {
+    offchain_message(message)?
+        .verify(address, signature)
+        .map_err(|e| UnmintError::InvalidMessage(e.to_string()))
+}
+
+/// A Sign-In-With-Solana (SIWS) message
+///
+/// The text representation follows the SIWS/EIP-4361 layout. SIWS signatures
+/// are made over the raw UTF-8 text, not over an off-chain message envelope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignInMessage {
+    pub domain: String,
+    pub address: Pubkey,
+    pub statement: Option<String>,
+    pub uri: Option<String>,
+    pub version: Option<String>,
+    pub chain_id: Option<String>,
+    pub nonce: Option<String>,
+    pub issued_at: Option<String>,
+    pub expiration_time: Option<String>,
+    pub not_before: Option<String>,
+    pub request_id: Option<String>,
+    pub resources: Vec<String>,
+}
+
+/// Fields a server expects in a `SignInMessage`
+///
+/// The domain and the current time are always checked; any other field left
+/// as `None` is not.
+#[derive(Debug, Clone)]
+pub struct SignInExpectation<'a> {
+    domain: &'a str,
+    /// Time used to check `issued_at`, `expiration_time` and `not_before`
+    now: DateTime<Utc>,
+    pub address: Option<&'a Pubkey>,
+    pub nonce: Option<&'a str>,
+    pub uri: Option<&'a str>,
+    pub chain_id: Option<&'a str>,
+}
+
+impl<'a> SignInExpectation<'a> {
+    /// Expects a message for `domain` that is valid at `now`
+    pub fn new(domain: &'a str, now: DateTime<Utc>) -> Self {
+        Self {
+            domain,
+            now,
+            address: None,
+            nonce: None,
+            uri: None,
+            chain_id: None,
+        }
+    }
+}
+
+const SIWS_HEADER: &str = " wants you to sign in with your Solana account:";
+
+impl SignInMessage {
+    pub fn new(domain: &str, address: Pubkey) -> Self {
+        Self {
+            domain: domain.to_string(),
+            address,
+            statement: None,
+            uri: None,
+            version: Some("1".to_string()),
+            chain_id: None,
+            nonce: None,
+            issued_at: None,
+            expiration_time: None,
+            not_before: None,
+            request_id: None,
+            resources: vec![],
+        }
+    }
+
+    /// Returns the text that gets signed
+    pub fn to_message(&self) -> String {
+        self.to_string()
+    }
+
+    /// Signs the message text with `signer`
+    pub fn sign(&self, signer: &dyn Signer) -> Result<Signature> {
+        if signer.pubkey() != self.address {
+            return Err(UnmintError::SignInRejected(format!(
+                "signer {} does not match sign-in address {}",
+                signer.pubkey(),
+                self.address
+            )));
+        }
+        Ok(signer.sign_message(self.to_message().as_bytes()))
+    }
+
+    /// Parses `message`, checks `signature` against the address it names and
+    /// validates it against `expected`
+    pub fn verify(
+        message: &str,
+        signature: &Signature,
+        expected: &SignInExpectation,
+    ) -> Result<SignInMessage> {
+        let parsed = SignInMessage::from_str(message)?;
+
+        if !signature.verify(parsed.address.as_ref(), message.as_bytes()) {
+            return Err(UnmintError::SignInRejected(format!(
+                "invalid signature for {}",
+                parsed.address
+            )));
+        }
+
+        check_field(
+            "domain",
+            Some(parsed.domain.as_str()),
+            Some(expected.domain),
+        )?;
+        if let Some(address) = expected.address
+            && *address != parsed.address
+        {
+            return Err(UnmintError::SignInRejected(format!(
+                "address mismatch: expected {}, got {}",
+                address, parsed.address
+            )));
+        }
+        check_field("nonce", parsed.nonce.as_deref(), expected.nonce)?;
+        check_field("uri", parsed.uri.as_deref(), expected.uri)?;
+        check_field("chain id", parsed.chain_id.as_deref(), expected.chain_id)?;
+
+        let rejected = |reason: String| Err(UnmintError::SignInRejected(reason));
+        if let Some(issued_at) = &parsed.issued_at
+            && parse_time(issued_at)? > expected.now
+        {
+            return rejected(format!("issued in the future: {}", issued_at));
+        }
+        if let Some(expiration_time) = &parsed.expiration_time
+            && parse_time(expiration_time)? <= expected.now
+        {
+            return rejected(format!("expired at {}", expiration_time));
+        }
+        if let Some(not_before) = &parsed.not_before
+            && parse_time(not_before)? > expected.now
+        {
+            return rejected(format!("not valid before {}", not_before));
+        }
+
+        Ok(parsed)
+    }
+}
+
+fn check_field(name: &str, actual: Option<&str>, expected: Option<&str>) -> Result<()> {
+    match (actual, expected) {
+        (_, None) => Ok(()),
+        (Some(actual), Some(expected)) if actual == expected => Ok(()),
+        (actual, Some(expected)) => Err(UnmintError::SignInRejected(format!(
+            "{} mismatch: expected {}, got {}",
+            name,
+            expected,
+            actual.unwrap_or("<missing>")
+        ))),
+    }
+}
+
+fn parse_time(value: &str) -> Result<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(value)
+        .map_err(|e| UnmintError::InvalidMessage(format!("invalid timestamp {}: {}", value, e)))
+}
+
+impl fmt::Display for SignInMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}\n{}", self.domain, SIWS_HEADER, self.address)?;
+
+        if let Some(statement) = &self.statement {
+            write!(f, "\n\n{}", statement)?;
+        }
+
+        let fields = [
+            ("URI", &self.uri),
+            ("Version", &self.version),
+            ("Chain ID", &self.chain_id),
+            ("Nonce", &self.nonce),
+            ("Issued At", &self.issued_at),
+            ("Expiration Time", &self.expiration_time),
+            ("Not Before", &self.not_before),
+            ("Request ID", &self.request_id),
+        ];
+
+        let mut first = true;
+        for (label, value) in fields {
+            if let Some(value) = value {
+                let sep = if first { "\n\n" } else { "\n" };
+                write!(f, "{}{}: {}", sep, label, value)?;
+                first = false;
+            }
+        }
+
+        if !self.resources.is_empty() {
+            write!(f, "{}Resources:", if first { "\n\n" } else { "\n" })?;
+            for resource in &self.resources {
+                write!(f, "\n- {}", resource)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for SignInMessage {
+    type Err = UnmintError;
+
+    fn from_str(message: &str) -> Result<Self> {
+        let mut lines = message.split('\n');
+
+        let domain = lines
+            .next()
+            .and_then(|line| line.strip_suffix(SIWS_HEADER))
+            .ok_or_else(|| UnmintError::InvalidMessage("missing sign-in header".to_string()))?;
+        let address = lines
+            .next()
+            .and_then(|line| Pubkey::from_str(line).ok())
+            .ok_or_else(|| {
+                UnmintError::InvalidMessage("missing or invalid sign-in address".to_string())
+            })?;
+
+        let mut parsed = SignInMessage::new(domain, address);
+        parsed.version = None;
+
+        let rest: Vec<&str> = lines.collect();
+        let mut index = 0;
+
+        // blank line, optional statement, blank line
+        if rest.get(index) == Some(&"") {
+            index += 1;
+            if let Some(line) = rest.get(index)
+                && !line.is_empty()
+                && !is_field(line)
+            {
+                parsed.statement = Some(line.to_string());
+                index += 1;
+                if rest.get(index) == Some(&"") {
+                    index += 1;
+                }
+            }
+        }
+
+        while let Some(line) = rest.get(index) {
+            index += 1;
+            if *line == "Resources:" {
+                while let Some(resource) = rest.get(index).and_then(|l| l.strip_prefix("- ")) {
+                    parsed.resources.push(resource.to_string());
+                    index += 1;
+                }
+                continue;
+            }
+
+            let (label, value) = line.split_once(": ").ok_or_else(|| {
+                UnmintError::InvalidMessage(format!("malformed sign-in line: {}", line))
+            })?;
+            let value = Some(value.to_string());
+            match label {
+                "URI" => parsed.uri = value,
+                "Version" => parsed.version = value,
+                "Chain ID" => parsed.chain_id = value,
+                "Nonce" => parsed.nonce = value,
+                "Issued At" => parsed.issued_at = value,
+                "Expiration Time" => parsed.expiration_time = value,
+                "Not Before" => parsed.not_before = value,
+                "Request ID" => parsed.request_id = value,
+                _ => {
+                    return Err(UnmintError::InvalidMessage(format!(
+                        "unknown sign-in field: {}",
+                        label
+                    )));
+                }
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+fn is_field(line: &str) -> bool {
+    line == "Resources:"
+        || [
+            "URI: ",
+            "Version: ",
+            "Chain ID: ",
+            "Nonce: ",
+            "Issued At: ",
+            "Expiration Time: ",
+            "Not Before: ",
+            "Request ID: ",
+        ]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+}
@@ -0,0 +1,892 @@
+use std::{collections::HashMap, fmt};
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    account::Account,
+    hash::{Hash, hashv},
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signature::Signature,
+    signer::Signer,
+};
+use spl_token_2022::extension::transfer_fee::instruction::harvest_withheld_tokens_to_mint;
+
+use crate::{
+    amount::{AmountInput, SOL_DECIMALS},
+    error::{Result, UnmintError, parse_pubkey},
+    instructions::{self, Signers},
+    journal::display_fromstr,
+    scan::OwnedTokenAccount,
+    simulate::Simulation,
+    token_program::TokenProgram,
+    transfer_fee::FeeMode,
+    unmint::Unmint,
+};
+
+/// One step of a `Plan`
+///
+/// Amounts are raw, in the smallest unit of the mint or in lamports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PlanAction {
+    /// Creates the associated token account `address` of `owner`
+    CreateAta {
+        #[serde(with = "display_fromstr")]
+        program: Pubkey,
+        #[serde(with = "display_fromstr")]
+        payer: Pubkey,
+        #[serde(with = "display_fromstr")]
+        owner: Pubkey,
+        #[serde(with = "display_fromstr")]
+        mint: Pubkey,
+        #[serde(with = "display_fromstr")]
+        address: Pubkey,
+    },
+    Transfer {
+        #[serde(with = "display_fromstr")]
+        program: Pubkey,
+        #[serde(with = "display_fromstr")]
+        source: Pubkey,
+        #[serde(with = "display_fromstr")]
+        destination: Pubkey,
+        #[serde(with = "display_fromstr")]
+        authority: Pubkey,
+        #[serde(with = "display_fromstr")]
+        mint: Pubkey,
+        amount: u64,
+        decimals: u8,
+        /// Transfer fee withheld in the destination, Token-2022 only
+        fee: Option<u64>,
+    },
+    Burn {
+        #[serde(with = "display_fromstr")]
+        program: Pubkey,
+        #[serde(with = "display_fromstr")]
+        account: Pubkey,
+        #[serde(with = "display_fromstr")]
+        authority: Pubkey,
+        #[serde(with = "display_fromstr")]
+        mint: Pubkey,
+        amount: u64,
+        decimals: u8,
+    },
+    /// Closes an empty token account, returning its rent to `destination`
+    Close {
+        #[serde(with = "display_fromstr")]
+        program: Pubkey,
+        #[serde(with = "display_fromstr")]
+        account: Pubkey,
+        #[serde(with = "display_fromstr")]
+        mint: Pubkey,
+        #[serde(with = "display_fromstr")]
+        destination: Pubkey,
+        #[serde(with = "display_fromstr")]
+        authority: Pubkey,
+    },
+    /// Closes a wrapped SOL account, sending its SOL and rent to `destination`
+    Unwrap {
+        #[serde(with = "display_fromstr")]
+        program: Pubkey,
+        #[serde(with = "display_fromstr")]
+        account: Pubkey,
+        #[serde(with = "display_fromstr")]
+        mint: Pubkey,
+        #[serde(with = "display_fromstr")]
+        destination: Pubkey,
+        #[serde(with = "display_fromstr")]
+        authority: Pubkey,
+    },
+    /// Moves the transfer fees withheld in `sources` to the mint; needs no signature
+    HarvestFees {
+        #[serde(with = "display_fromstr")]
+        program: Pubkey,
+        #[serde(with = "display_fromstr")]
+        mint: Pubkey,
+        #[serde(with = "pubkeys")]
+        sources: Vec<Pubkey>,
+    },
+    TransferSol {
+        #[serde(with = "display_fromstr")]
+        from: Pubkey,
+        #[serde(with = "display_fromstr")]
+        to: Pubkey,
+        lamports: u64,
+    },
+}
+
+/// Which balance of an account a `BalanceDelta` is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "balance", rename_all = "snake_case")]
+pub enum Balance {
+    Lamports,
+    Tokens {
+        #[serde(with = "display_fromstr")]
+        mint: Pubkey,
+    },
+    /// Transfer fees withheld in a Token-2022 account
+    WithheldFees {
+        #[serde(with = "display_fromstr")]
+        mint: Pubkey,
+    },
+}
+
+impl fmt::Display for Balance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Balance::Lamports => write!(f, "lamports"),
+            Balance::Tokens { mint } => write!(f, "tokens of {}", mint),
+            Balance::WithheldFees { mint } => write!(f, "withheld fees of {}", mint),
+        }
+    }
+}
+
+/// A balance a step changes, in raw units
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceDelta {
+    #[serde(with = "display_fromstr")]
+    pub address: Pubkey,
+    #[serde(flatten)]
+    pub balance: Balance,
+    pub before: u64,
+    pub after: u64,
+}
+
+/// A `PlanAction` and the balances it is expected to change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanStep {
+    #[serde(flatten)]
+    pub action: PlanAction,
+    pub deltas: Vec<BalanceDelta>,
+}
+
+/// Actions an operation will take, built from the chain state at planning time
+///
+/// A plan can be written out with `to_json` for review and run later with
+/// `Unmint::execute`. Expected deltas leave out transaction fees, so the fee
+/// payer's lamports are only tracked when a step moves them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Plan {
+    #[serde(with = "display_fromstr")]
+    pub fee_payer: Pubkey,
+    pub steps: Vec<PlanStep>,
+    /// Hash of the plan `Unmint::dry_run` simulated successfully; never
+    /// serialized, so a plan read back with `from_json` must be dry run again
+    #[serde(skip)]
+    confirmation: Option<Hash>,
+}
+
+impl Plan {
+    pub fn new(fee_payer: Pubkey, steps: Vec<PlanStep>) -> Self {
+        Self {
+            fee_payer,
+            steps,
+            confirmation: None,
+        }
+    }
+
+    /// A plan of `actions` without balance deltas, for `Policy::check` of
+    /// operations that do not run through `execute`
+    pub(crate) fn from_actions(fee_payer: Pubkey, actions: Vec<PlanAction>) -> Self {
+        let steps = actions
+            .into_iter()
+            .map(|action| PlanStep {
+                action,
+                deltas: vec![],
+            })
+            .collect();
+        Self::new(fee_payer, steps)
+    }
+
+    /// Whether `Unmint::dry_run` succeeded for the plan as it is now; a `Policy`
+    /// can require it before large plans run
+    ///
+    /// Changing the fee payer or any step after the dry run clears it.
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmation
+            .is_some_and(|confirmation| self.hash().is_ok_and(|hash| hash == confirmation))
+    }
+
+    fn hash(&self) -> Result<Hash> {
+        let steps = serde_json::to_vec(&self.steps).map_err(|e| anyhow::anyhow!(e))?;
+        Ok(hashv(&[self.fee_payer.as_ref(), &steps]))
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| anyhow::anyhow!(e).into())
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| UnmintError::InvalidInput {
+            kind: "plan",
+            reason: e.to_string(),
+        })
+    }
+
+    /// Every balance the plan reads, with the value it assumed before its first step
+    pub fn assumed_balances(&self) -> Vec<(Pubkey, Balance, u64)> {
+        let mut assumed: Vec<(Pubkey, Balance, u64)> = vec![];
+        for delta in self.steps.iter().flat_map(|step| &step.deltas) {
+            if !assumed
+                .iter()
+                .any(|(address, balance, _)| *address == delta.address && *balance == delta.balance)
+            {
+                assumed.push((delta.address, delta.balance, delta.before));
+            }
+        }
+        assumed
+    }
+}
+
+/// A balance that changed since the plan was built
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Drift {
+    pub address: Pubkey,
+    pub balance: Balance,
+    pub planned: u64,
+    pub found: u64,
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} holds {} {} but the plan assumed {}",
+            self.address, self.found, self.balance, self.planned
+        )
+    }
+}
+
+/// Reads `balance` of `address` from a fetched account; a missing account holds nothing
+fn current_balance(address: &Pubkey, account: Option<&Account>, balance: &Balance) -> u64 {
+    let token = || {
+        account
+            .and_then(|account| OwnedTokenAccount::decode(*address, account).ok())
+            .filter(|token| match balance {
+                Balance::Tokens { mint } | Balance::WithheldFees { mint } => {
+                    token.account.mint == *mint
+                }
+                Balance::Lamports => false,
+            })
+    };
+
+    match balance {
+        Balance::Lamports => account.map(|a| a.lamports).unwrap_or_default(),
+        Balance::Tokens { .. } => token().map(|t| t.account.amount).unwrap_or_default(),
+        Balance::WithheldFees { .. } => token().map(|t| t.withheld_amount).unwrap_or_default(),
+    }
+}
+
+/// Builds the steps of a plan, tracking every balance from its observed value
+struct PlanBuilder {
+    plan: Plan,
+    balances: HashMap<(Pubkey, Balance), u64>,
+}
+
+impl PlanBuilder {
+    fn new(fee_payer: Pubkey) -> Self {
+        Self {
+            plan: Plan::new(fee_payer, vec![]),
+            balances: HashMap::new(),
+        }
+    }
+
+    /// Records the fetched state of `address`; `mint` names the token balances to track
+    fn observe(&mut self, address: Pubkey, account: Option<&Account>, mint: Option<&Pubkey>) {
+        let mut balances = vec![Balance::Lamports];
+        if let Some(mint) = mint {
+            balances.push(Balance::Tokens { mint: *mint });
+            balances.push(Balance::WithheldFees { mint: *mint });
+        }
+
+        for balance in balances {
+            self.balances
+                .entry((address, balance))
+                .or_insert_with(|| current_balance(&address, account, &balance));
+        }
+    }
+
+    /// Appends `action`, applying `changes` to the tracked balances
+    fn push(&mut self, action: PlanAction, changes: &[(Pubkey, Balance, i128)]) -> Result<()> {
+        let mut deltas: Vec<BalanceDelta> = vec![];
+
+        for (address, balance, change) in changes {
+            let before = *self.balances.get(&(*address, *balance)).ok_or_else(|| {
+                anyhow::anyhow!("plan does not track the {} of {}", balance, address)
+            })?;
+            let after = u64::try_from(before as i128 + change).map_err(|_| {
+                UnmintError::InsufficientFunds(format!(
+                    "{} holds {} {}, the plan needs {}",
+                    address,
+                    before,
+                    balance,
+                    change.unsigned_abs()
+                ))
+            })?;
+            self.balances.insert((*address, *balance), after);
+
+            match deltas
+                .iter_mut()
+                .find(|d| d.address == *address && d.balance == *balance)
+            {
+                Some(delta) => delta.after = after,
+                None => deltas.push(BalanceDelta {
+                    address: *address,
+                    balance: *balance,
+                    before,
+                    after,
+                }),
+            }
+        }
+
+        self.plan.steps.push(PlanStep { action, deltas });
+        Ok(())
+    }
+
+    fn balance(&self, address: &Pubkey, balance: Balance) -> u64 {
+        self.balances
+            .get(&(*address, balance))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+mod pubkeys {
+    use serde::{Deserialize, Deserializer, Serializer, ser::SerializeSeq};
+    use solana_sdk::pubkey::Pubkey;
+
+    pub fn serialize<S: Serializer>(keys: &[Pubkey], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(keys.len()))?;
+        for key in keys {
+            seq.serialize_element(&key.to_string())?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Pubkey>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|key| key.parse().map_err(serde::de::Error::custom))
+            .collect()
+    }
+}
+
+impl Unmint {
+    /// Plans `send_token` from the wallet `from_address` without signing anything
+    ///
+    /// The recipient ATA is created when it does not exist yet.
+    pub fn plan_send_token<'a, A>(
+        &self,
+        from_address: &str,
+        to_address: &str,
+        token_mint_address: &str,
+        amount: A,
+        fee_payer_address: Option<&str>,
+    ) -> Result<Plan>
+    where
+        A: Into<AmountInput<'a>>,
+    {
+        let from = parse_pubkey(from_address)?;
+        let to = parse_pubkey(to_address)?;
+        let mint_pubkey = parse_pubkey(token_mint_address)?;
+        let fee_payer = fee_payer_address
+            .map(parse_pubkey)
+            .transpose()?
+            .unwrap_or(from);
+
+        let mint = self.cached_mint_info(&mint_pubkey)?;
+        let amount = amount.into().to_token_amount(mint.decimals)?.raw();
+
+        let mut builder = PlanBuilder::new(fee_payer);
+        self.plan_transfer(&mut builder, &from, &to, &mint_pubkey, Some(amount))?;
+
+        Ok(builder.plan)
+    }
+
+    /// Plans `send_and_close`: the whole balance is sent to `to_address` and the
+    /// sender's ATA is closed, its rent going to the fee payer
+    ///
+    /// Transfer fees withheld in the ATA are harvested to the mint first so it
+    /// can be closed.
+    pub fn plan_send_and_close(
+        &self,
+        from_address: &str,
+        to_address: &str,
+        token_mint_address: &str,
+        fee_payer_address: Option<&str>,
+    ) -> Result<Plan> {
+        let from = parse_pubkey(from_address)?;
+        let to = parse_pubkey(to_address)?;
+        let mint_pubkey = parse_pubkey(token_mint_address)?;
+        let fee_payer = fee_payer_address
+            .map(parse_pubkey)
+            .transpose()?
+            .unwrap_or(from);
+
+        let mut builder = PlanBuilder::new(fee_payer);
+        let program = self.token_program.program_id();
+        let ata_sender = self.token_program.ata(&from, &mint_pubkey);
+        let account = self
+            .fetch_account(&ata_sender)?
+            .ok_or(UnmintError::AccountNotFound(ata_sender))?;
+        let token = OwnedTokenAccount::decode(ata_sender, &account)?;
+
+        if token.account.amount > 0 {
+            self.plan_transfer(&mut builder, &from, &to, &mint_pubkey, None)?;
+        } else {
+            builder.observe(ata_sender, Some(&account), Some(&mint_pubkey));
+        }
+
+        let withheld = builder.balance(&ata_sender, Balance::WithheldFees { mint: mint_pubkey });
+        if withheld > 0 {
+            builder.push(
+                PlanAction::HarvestFees {
+                    program,
+                    mint: mint_pubkey,
+                    sources: vec![ata_sender],
+                },
+                &[(
+                    ata_sender,
+                    Balance::WithheldFees { mint: mint_pubkey },
+                    -(withheld as i128),
+                )],
+            )?;
+        }
+
+        let receiver = self.fetch_account(&fee_payer)?;
+        builder.observe(fee_payer, receiver.as_ref(), None);
+        let lamports = builder.balance(&ata_sender, Balance::Lamports);
+        builder.push(
+            PlanAction::Close {
+                program,
+                account: ata_sender,
+                mint: mint_pubkey,
+                destination: fee_payer,
+                authority: from,
+            },
+            &[
+                (ata_sender, Balance::Lamports, -(lamports as i128)),
+                (fee_payer, Balance::Lamports, lamports as i128),
+            ],
+        )?;
+
+        Ok(builder.plan)
+    }
+
+    /// Plans burning `amount` from the ATA of `owner_address`
+    pub fn plan_burn<'a, A>(
+        &self,
+        owner_address: &str,
+        token_mint_address: &str,
+        amount: A,
+        fee_payer_address: Option<&str>,
+    ) -> Result<Plan>
+    where
+        A: Into<AmountInput<'a>>,
+    {
+        let owner = parse_pubkey(owner_address)?;
+        let mint_pubkey = parse_pubkey(token_mint_address)?;
+        let fee_payer = fee_payer_address
+            .map(parse_pubkey)
+            .transpose()?
+            .unwrap_or(owner);
+
+        let mint = self.cached_mint_info(&mint_pubkey)?;
+        let amount = amount.into().to_token_amount(mint.decimals)?.raw();
+        let ata = self.token_program.ata(&owner, &mint_pubkey);
+        let account = self
+            .fetch_account(&ata)?
+            .ok_or(UnmintError::AccountNotFound(ata))?;
+
+        let mut builder = PlanBuilder::new(fee_payer);
+        builder.observe(ata, Some(&account), Some(&mint_pubkey));
+        builder.push(
+            PlanAction::Burn {
+                program: self.token_program.program_id(),
+                account: ata,
+                authority: owner,
+                mint: mint_pubkey,
+                amount,
+                decimals: mint.decimals,
+            },
+            &[(
+                ata,
+                Balance::Tokens { mint: mint_pubkey },
+                -(amount as i128),
+            )],
+        )?;
+
+        Ok(builder.plan)
+    }
+
+    /// Plans harvesting the transfer fees withheld in `token_accounts` to their mint
+    pub fn plan_harvest_fees(
+        &self,
+        token_mint_address: &str,
+        token_accounts: &[&str],
+        fee_payer_address: &str,
+    ) -> Result<Plan> {
+        let mint_pubkey = parse_pubkey(token_mint_address)?;
+        let fee_payer = parse_pubkey(fee_payer_address)?;
+        let sources = token_accounts
+            .iter()
+            .map(|address| parse_pubkey(address))
+            .collect::<Result<Vec<Pubkey>>>()?;
+
+        let mut builder = PlanBuilder::new(fee_payer);
+        let mut changes = vec![];
+        for (source, account) in sources.iter().zip(self.multiple_accounts(&sources)?) {
+            let account = account.ok_or(UnmintError::AccountNotFound(*source))?;
+            builder.observe(*source, Some(&account), Some(&mint_pubkey));
+            let withheld = builder.balance(source, Balance::WithheldFees { mint: mint_pubkey });
+            changes.push((
+                *source,
+                Balance::WithheldFees { mint: mint_pubkey },
+                -(withheld as i128),
+            ));
+        }
+
+        builder.push(
+            PlanAction::HarvestFees {
+                program: self.token_program.program_id(),
+                mint: mint_pubkey,
+                sources,
+            },
+            &changes,
+        )?;
+
+        Ok(builder.plan)
+    }
+
+    /// Plans `transfer_sol`; the sender pays the fee
+    pub fn plan_transfer_sol<'a, A>(
+        &self,
+        from_address: &str,
+        to_address: &str,
+        amount_sol: A,
+    ) -> Result<Plan>
+    where
+        A: Into<AmountInput<'a>>,
+    {
+        let from = parse_pubkey(from_address)?;
+        let to = parse_pubkey(to_address)?;
+        let lamports = amount_sol.into().to_token_amount(SOL_DECIMALS)?.raw();
+
+        let accounts = self.multiple_accounts(&[from, to])?;
+
+        let mut builder = PlanBuilder::new(from);
+        builder.observe(from, accounts[0].as_ref(), None);
+        builder.observe(to, accounts[1].as_ref(), None);
+        builder.push(
+            PlanAction::TransferSol { from, to, lamports },
+            &[
+                (from, Balance::Lamports, -(lamports as i128)),
+                (to, Balance::Lamports, lamports as i128),
+            ],
+        )?;
+
+        Ok(builder.plan)
+    }
+
+    /// Adds the transfer of `amount` from the ATA of `from` to `to`, or of the
+    /// whole balance when `amount` is `None`, creating the recipient ATA if needed
+    fn plan_transfer(
+        &self,
+        builder: &mut PlanBuilder,
+        from: &Pubkey,
+        to: &Pubkey,
+        mint_pubkey: &Pubkey,
+        amount: Option<u64>,
+    ) -> Result<()> {
+        let program = self.token_program.program_id();
+        let mint = self.cached_mint_info(mint_pubkey)?;
+        let ata_sender = self.token_program.ata(from, mint_pubkey);
+        let sender = self
+            .fetch_account(&ata_sender)?
+            .ok_or(UnmintError::AccountNotFound(ata_sender))?;
+        let is_native =
+            instructions::checked_token_account(&self.token_program, &ata_sender, &sender)?
+                .is_native();
+        builder.observe(ata_sender, Some(&sender), Some(mint_pubkey));
+
+        let destination = self.destination(to, mint_pubkey)?;
+        instructions::check_memo(&destination.address, destination.account.as_ref(), None)?;
+        builder.observe(
+            destination.address,
+            destination.account.as_ref(),
+            Some(mint_pubkey),
+        );
+
+        if destination.create && destination.account.is_none() {
+            let payer = builder.plan.fee_payer;
+            let payer_account = self.fetch_account(&payer)?;
+            builder.observe(payer, payer_account.as_ref(), None);
+
+            let rent = self
+                .client
+                .get_minimum_balance_for_rent_exemption(mint.token_account_len()?)?;
+            builder.push(
+                PlanAction::CreateAta {
+                    program,
+                    payer,
+                    owner: destination.owner,
+                    mint: *mint_pubkey,
+                    address: destination.address,
+                },
+                &[
+                    (payer, Balance::Lamports, -(rent as i128)),
+                    (destination.address, Balance::Lamports, rent as i128),
+                ],
+            )?;
+        }
+
+        let amount = amount.unwrap_or_else(|| {
+            builder.balance(&ata_sender, Balance::Tokens { mint: *mint_pubkey })
+        });
+        let quote =
+            instructions::fee_quote(&mint, self.fee_epoch(&mint)?, amount, FeeMode::SendExact)?;
+        let fee = quote.fee.unwrap_or_default();
+
+        let mut changes = vec![
+            (
+                ata_sender,
+                Balance::Tokens { mint: *mint_pubkey },
+                -(quote.sent as i128),
+            ),
+            (
+                destination.address,
+                Balance::Tokens { mint: *mint_pubkey },
+                (quote.sent - fee) as i128,
+            ),
+            (
+                destination.address,
+                Balance::WithheldFees { mint: *mint_pubkey },
+                fee as i128,
+            ),
+        ];
+        // wrapped SOL moves its lamports along with the amount
+        if is_native {
+            changes.push((ata_sender, Balance::Lamports, -(quote.sent as i128)));
+            changes.push((destination.address, Balance::Lamports, quote.sent as i128));
+        }
+        builder.push(
+            PlanAction::Transfer {
+                program,
+                source: ata_sender,
+                destination: destination.address,
+                authority: *from,
+                mint: *mint_pubkey,
+                amount: quote.sent,
+                decimals: mint.decimals,
+                fee: quote.fee,
+            },
+            &changes,
+        )
+    }
+
+    /// Compares the balances `plan` assumed with the chain
+    ///
+    /// # Returns
+    /// * Every balance that changed since the plan was built, empty when none did
+    pub fn plan_drift(&self, plan: &Plan) -> Result<Vec<Drift>> {
+        let assumed = plan.assumed_balances();
+        let mut addresses: Vec<Pubkey> = assumed.iter().map(|(address, _, _)| *address).collect();
+        addresses.sort();
+        addresses.dedup();
+
+        let accounts: HashMap<Pubkey, Option<Account>> = addresses
+            .iter()
+            .copied()
+            .zip(self.multiple_accounts(&addresses)?)
+            .collect();
+
+        Ok(assumed
+            .into_iter()
+            .filter_map(|(address, balance, planned)| {
+                let account = accounts.get(&address).and_then(Option::as_ref);
+                let found = current_balance(&address, account, &balance);
+                (found != planned).then_some(Drift {
+                    address,
+                    balance,
+                    planned,
+                    found,
+                })
+            })
+            .collect())
+    }
+
+    /// Runs `plan`, refusing with `UnmintError::PlanDrift` when any balance it
+    /// assumed has changed since it was built, and with
+    /// `UnmintError::PolicyViolation` when it breaks the policy of `with_policy`
+    ///
+    /// Steps are packed into as few transactions as fit, in order.
+    ///
+    /// # Arguments
+    /// * `from_base58_string` - Base58 private key of the authority of the planned steps
+    /// * `fee_payer_base58_string` - Optional, must match the fee payer of the plan
+    ///
+    /// # Returns
+    /// * `Signature` of every transaction sent
+    pub fn execute(
+        &self,
+        plan: &Plan,
+        from_base58_string: &str,
+        fee_payer_base58_string: Option<&str>,
+    ) -> Result<Vec<Signature>> {
+        let signers = Signers::parse(from_base58_string, fee_payer_base58_string)?;
+        if signers.fee_payer.pubkey() != plan.fee_payer {
+            return Err(UnmintError::FeePayerMismatch {
+                expected: plan.fee_payer,
+                found: signers.fee_payer.pubkey(),
+            });
+        }
+
+        // the policy sees whether the dry run still matches the plan's steps
+        if let Some(policy) = &self.policy {
+            policy.check(plan)?;
+        }
+
+        let drift = self.plan_drift(plan)?;
+        if !drift.is_empty() {
+            return Err(UnmintError::PlanDrift(drift));
+        }
+
+        let groups = plan
+            .steps
+            .iter()
+            .map(|step| Ok(vec![self.step_instruction(&step.action, &signers)?]))
+            .collect::<Result<Vec<Vec<Instruction>>>>()?;
+        let batches = instructions::pack_groups(
+            &groups,
+            &signers.fee_payer.pubkey(),
+            self.priority_fee.as_ref(),
+        )?;
+
+        let mut signatures = vec![];
+        for batch in batches {
+            let batch_instructions: Vec<Instruction> =
+                batch.iter().flat_map(|&i| groups[i].clone()).collect();
+            signatures.push(self.send_instructions(&batch_instructions, &signers)?);
+        }
+
+        Ok(signatures)
+    }
+
+    /// Simulates every step of `plan` in a single transaction and marks the
+    /// plan confirmed when the simulation succeeds, see `Plan::is_confirmed`
+    ///
+    /// Plans too large for one transaction cannot be dry run.
+    pub fn dry_run(
+        &self,
+        plan: &mut Plan,
+        from_base58_string: &str,
+        fee_payer_base58_string: Option<&str>,
+    ) -> Result<Simulation> {
+        let signers = Signers::parse(from_base58_string, fee_payer_base58_string)?;
+        if signers.fee_payer.pubkey() != plan.fee_payer {
+            return Err(UnmintError::FeePayerMismatch {
+                expected: plan.fee_payer,
+                found: signers.fee_payer.pubkey(),
+            });
+        }
+
+        let instructions = plan
+            .steps
+            .iter()
+            .map(|step| self.step_instruction(&step.action, &signers))
+            .collect::<Result<Vec<Instruction>>>()?;
+        let simulation = self.simulate_instructions(&instructions, &signers)?;
+
+        plan.confirmation = if simulation.succeeded() {
+            Some(plan.hash()?)
+        } else {
+            None
+        };
+        Ok(simulation)
+    }
+
+    fn step_instruction(&self, action: &PlanAction, signers: &Signers) -> Result<Instruction> {
+        let signer = |authority: &Pubkey| -> Result<&Keypair> {
+            signers
+                .keypairs()
+                .into_iter()
+                .find(|keypair| keypair.pubkey() == *authority)
+                .ok_or(UnmintError::MissingSigner(*authority))
+        };
+        let token_program = |program: &Pubkey| -> Result<TokenProgram> {
+            TokenProgram::from_program_id(program).ok_or(UnmintError::UnsupportedProgram(*program))
+        };
+
+        match action {
+            PlanAction::CreateAta {
+                program,
+                payer,
+                owner,
+                mint,
+                ..
+            } => Ok(token_program(program)?.create_ata_idempotent_ix(payer, owner, mint)),
+            PlanAction::Transfer {
+                program,
+                source,
+                destination,
+                authority,
+                mint,
+                amount,
+                decimals,
+                fee,
+            } => self.transfer_ix(
+                &token_program(program)?,
+                source,
+                destination,
+                signer(authority)?,
+                *amount,
+                *decimals,
+                mint,
+                *fee,
+            ),
+            PlanAction::Burn {
+                program,
+                account,
+                authority,
+                mint,
+                amount,
+                decimals,
+            } => Ok(token_program(program)?.burn_ix(
+                account,
+                mint,
+                signer(authority)?,
+                *amount,
+                *decimals,
+            )?),
+            PlanAction::Close {
+                program,
+                account,
+                destination,
+                authority,
+                ..
+            }
+            | PlanAction::Unwrap {
+                program,
+                account,
+                destination,
+                authority,
+                ..
+            } => Ok(token_program(program)?.close_ix(account, destination, signer(authority)?)?),
+            PlanAction::HarvestFees {
+                program,
+                mint,
+                sources,
+            } => {
+                let sources: Vec<&Pubkey> = sources.iter().collect();
+                harvest_withheld_tokens_to_mint(program, mint, &sources)
+                    .map_err(UnmintError::InvalidInstruction)
+            }
+            PlanAction::TransferSol { from, to, lamports } => {
+                signer(from)?;
+                Ok(instructions::transfer_sol_instruction(from, to, *lamports))
+            }
+        }
+    }
+}
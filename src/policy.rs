@@ -0,0 +1,389 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    path::Path,
+};
+
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    amount::{Lamports, TokenAmount},
+    error::{Result, UnmintError, parse_pubkey},
+    plan::{Plan, PlanAction},
+    token_program::TokenProgram,
+};
+
+/// Limits on transfers of one mint, as UI amounts such as `"1000"`
+///
+/// The amounts are parsed with the mint's decimals when a plan is evaluated,
+/// so a malformed limit fails `Policy::evaluate` rather than `Policy::from_toml`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MintLimits {
+    /// Largest amount a single transfer or burn may move
+    pub max_transfer: Option<String>,
+    /// Plans moving more than this in a single transfer or burn need a dry run
+    pub confirm_above: Option<String>,
+}
+
+/// Safeguards evaluated against every plan `Unmint::execute` runs
+///
+/// Loaded from TOML with `Policy::load`:
+///
+/// ```toml
+/// protected_mints = ["EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"]
+/// allowed_recipients = ["57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj"]
+/// max_sol_transfer = "1.5"
+/// confirm_closes_above = 10
+///
+/// [mints.Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB]
+/// max_transfer = "1000"
+/// confirm_above = "100"
+/// ```
+///
+/// Amounts are strings so they are read exactly.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// Mints whose accounts may be closed or burned; empty allows every mint
+    /// not in `protected_mints`
+    pub closable_mints: HashSet<Pubkey>,
+    /// Mints whose accounts are never closed or burned
+    pub protected_mints: HashSet<Pubkey>,
+    /// Wallets and token accounts that may receive tokens or SOL; empty allows
+    /// any. The owners a plan takes funds from may always receive them back;
+    /// a separate fee payer must be listed like any other recipient.
+    pub allowed_recipients: HashSet<Pubkey>,
+    /// Largest SOL transfer, in lamports
+    pub max_sol_transfer: Option<u64>,
+    /// Plans sending more lamports than this in a single transfer need a dry run
+    pub confirm_sol_above: Option<u64>,
+    /// Plans closing more token accounts than this need a dry run
+    pub confirm_closes_above: Option<usize>,
+    pub mint_limits: HashMap<Pubkey, MintLimits>,
+}
+
+/// `Policy` as written in TOML
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct PolicyFile {
+    closable_mints: Vec<String>,
+    protected_mints: Vec<String>,
+    allowed_recipients: Vec<String>,
+    max_sol_transfer: Option<String>,
+    confirm_sol_above: Option<String>,
+    confirm_closes_above: Option<usize>,
+    mints: HashMap<String, MintLimits>,
+}
+
+/// Why a plan may not run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The plan closes or burns an account of a protected mint
+    ProtectedMint { mint: Pubkey, account: Pubkey },
+    /// The plan closes or burns an account of a mint missing from `closable_mints`
+    MintNotClosable { mint: Pubkey, account: Pubkey },
+    /// A transfer exceeds the limit of its mint or of SOL
+    TransferLimit {
+        mint: Option<Pubkey>,
+        amount: String,
+        limit: String,
+    },
+    /// Tokens or SOL go to an address missing from `allowed_recipients`
+    RecipientNotAllowed(Pubkey),
+    /// The plan is above a confirmation threshold and has no successful dry run
+    ConfirmationRequired(String),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::ProtectedMint { mint, account } => write!(
+                f,
+                "{} holds protected mint {} and may not be closed or burned",
+                account, mint
+            ),
+            Violation::MintNotClosable { mint, account } => write!(
+                f,
+                "{} holds mint {}, which is not in closable_mints",
+                account, mint
+            ),
+            Violation::TransferLimit {
+                mint: Some(mint),
+                amount,
+                limit,
+            } => write!(
+                f,
+                "transfer of {} {} exceeds the limit of {}",
+                amount, mint, limit
+            ),
+            Violation::TransferLimit {
+                mint: None,
+                amount,
+                limit,
+            } => write!(
+                f,
+                "transfer of {} SOL exceeds the limit of {} SOL",
+                amount, limit
+            ),
+            Violation::RecipientNotAllowed(address) => {
+                write!(f, "{} is not an allowed recipient", address)
+            }
+            Violation::ConfirmationRequired(reason) => {
+                write!(f, "{} needs a successful dry run first", reason)
+            }
+        }
+    }
+}
+
+fn parse_pubkeys(keys: &[String]) -> Result<HashSet<Pubkey>> {
+    keys.iter().map(|key| parse_pubkey(key)).collect()
+}
+
+impl Policy {
+    /// Reads a policy from TOML, see `Policy`
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        let file: PolicyFile = toml::from_str(toml).map_err(|e| UnmintError::InvalidInput {
+            kind: "policy",
+            reason: e.to_string(),
+        })?;
+
+        // mint amounts need the mint's decimals, so they are parsed when a plan is evaluated
+        let mint_limits = file
+            .mints
+            .into_iter()
+            .map(|(mint, limits)| Ok((parse_pubkey(&mint)?, limits)))
+            .collect::<Result<HashMap<Pubkey, MintLimits>>>()?;
+
+        let lamports = |amount: Option<String>| -> Result<Option<u64>> {
+            amount
+                .map(|amount| Ok(Lamports::from_sol_str(&amount)?.0))
+                .transpose()
+        };
+
+        Ok(Self {
+            closable_mints: parse_pubkeys(&file.closable_mints)?,
+            protected_mints: parse_pubkeys(&file.protected_mints)?,
+            allowed_recipients: parse_pubkeys(&file.allowed_recipients)?,
+            max_sol_transfer: lamports(file.max_sol_transfer)?,
+            confirm_sol_above: lamports(file.confirm_sol_above)?,
+            confirm_closes_above: file.confirm_closes_above,
+            mint_limits,
+        })
+    }
+
+    /// Reads the TOML policy at `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let toml = fs::read_to_string(path).map_err(|source| UnmintError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml(&toml)
+    }
+
+    /// Lists every rule `plan` breaks, empty when it may run
+    pub fn evaluate(&self, plan: &Plan) -> Result<Vec<Violation>> {
+        let mut violations = vec![];
+        let mut closes = 0;
+
+        // owners may always receive their own funds back; the fee payer only
+        // counts when it is one of them
+        let mut owners: HashSet<Pubkey> = HashSet::new();
+        for step in &plan.steps {
+            match &step.action {
+                PlanAction::Transfer { authority, .. }
+                | PlanAction::Burn { authority, .. }
+                | PlanAction::Close { authority, .. }
+                | PlanAction::Unwrap { authority, .. } => owners.insert(*authority),
+                PlanAction::TransferSol { from, .. } => owners.insert(*from),
+                PlanAction::CreateAta { .. } | PlanAction::HarvestFees { .. } => false,
+            };
+        }
+
+        for step in &plan.steps {
+            match &step.action {
+                PlanAction::Burn {
+                    account,
+                    mint,
+                    amount,
+                    decimals,
+                    ..
+                } => {
+                    self.check_closable(mint, account, &mut violations);
+                    // a burn counts against the transfer limits of its mint
+                    self.check_token_amount(plan, mint, *amount, *decimals, &mut violations)?;
+                }
+                PlanAction::Close {
+                    account,
+                    mint,
+                    destination,
+                    ..
+                } => {
+                    closes += 1;
+                    self.check_closable(mint, account, &mut violations);
+                    self.check_recipient(destination, None, &owners, &mut violations);
+                }
+                PlanAction::Unwrap {
+                    account,
+                    mint,
+                    destination,
+                    ..
+                } => {
+                    closes += 1;
+                    self.check_closable(mint, account, &mut violations);
+                    self.check_recipient(destination, None, &owners, &mut violations);
+                }
+                PlanAction::Transfer {
+                    program,
+                    destination,
+                    mint,
+                    amount,
+                    decimals,
+                    ..
+                } => {
+                    let program = TokenProgram::from_program_id(program);
+                    self.check_recipient(
+                        destination,
+                        program.map(|program| (program, *mint)),
+                        &owners,
+                        &mut violations,
+                    );
+                    self.check_token_amount(plan, mint, *amount, *decimals, &mut violations)?;
+                }
+                PlanAction::TransferSol { to, lamports, .. } => {
+                    self.check_recipient(to, None, &owners, &mut violations);
+                    if let Some(limit) = self.max_sol_transfer
+                        && *lamports > limit
+                    {
+                        violations.push(Violation::TransferLimit {
+                            mint: None,
+                            amount: Lamports(*lamports).to_sol_string(),
+                            limit: Lamports(limit).to_sol_string(),
+                        });
+                    }
+                    if let Some(threshold) = self.confirm_sol_above
+                        && *lamports > threshold
+                        && !plan.is_confirmed()
+                    {
+                        violations.push(Violation::ConfirmationRequired(format!(
+                            "transfer of {}",
+                            Lamports(*lamports)
+                        )));
+                    }
+                }
+                PlanAction::CreateAta { .. } | PlanAction::HarvestFees { .. } => {}
+            }
+        }
+
+        if let Some(threshold) = self.confirm_closes_above
+            && closes > threshold
+            && !plan.is_confirmed()
+        {
+            violations.push(Violation::ConfirmationRequired(format!(
+                "closing {} accounts",
+                closes
+            )));
+        }
+
+        Ok(violations)
+    }
+
+    /// Fails with `UnmintError::PolicyViolation` when `plan` breaks any rule
+    pub fn check(&self, plan: &Plan) -> Result<()> {
+        let violations = self.evaluate(plan)?;
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(UnmintError::PolicyViolation(violations))
+        }
+    }
+
+    fn check_closable(&self, mint: &Pubkey, account: &Pubkey, violations: &mut Vec<Violation>) {
+        violations.extend(self.close_violation(mint, account));
+    }
+
+    /// Why `account` of `mint` may not be closed or burned, if it may not
+    pub(crate) fn close_violation(&self, mint: &Pubkey, account: &Pubkey) -> Option<Violation> {
+        if self.protected_mints.contains(mint) {
+            Some(Violation::ProtectedMint {
+                mint: *mint,
+                account: *account,
+            })
+        } else if !self.closable_mints.is_empty() && !self.closable_mints.contains(mint) {
+            Some(Violation::MintNotClosable {
+                mint: *mint,
+                account: *account,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn check_recipient(
+        &self,
+        address: &Pubkey,
+        token: Option<(TokenProgram, Pubkey)>,
+        owners: &HashSet<Pubkey>,
+        violations: &mut Vec<Violation>,
+    ) {
+        if !self.recipient_allowed(address, token, owners) {
+            violations.push(Violation::RecipientNotAllowed(*address));
+        }
+    }
+
+    /// Whether `address` may receive funds taken from `owners`; a token account
+    /// passes when it is the associated token account of an allowed wallet for `token`
+    fn recipient_allowed(
+        &self,
+        address: &Pubkey,
+        token: Option<(TokenProgram, Pubkey)>,
+        owners: &HashSet<Pubkey>,
+    ) -> bool {
+        if self.allowed_recipients.is_empty() {
+            return true;
+        }
+
+        let allowed = |wallet: &Pubkey| {
+            *wallet == *address
+                || token.is_some_and(|(program, mint)| program.ata(wallet, &mint) == *address)
+        };
+        self.allowed_recipients.iter().chain(owners).any(allowed)
+    }
+
+    fn check_token_amount(
+        &self,
+        plan: &Plan,
+        mint: &Pubkey,
+        amount: u64,
+        decimals: u8,
+        violations: &mut Vec<Violation>,
+    ) -> Result<()> {
+        let Some(limits) = self.mint_limits.get(mint) else {
+            return Ok(());
+        };
+        let amount = TokenAmount::from_raw(amount, decimals);
+
+        if let Some(limit) = &limits.max_transfer {
+            let limit = TokenAmount::parse(limit, decimals)?;
+            if amount > limit {
+                violations.push(Violation::TransferLimit {
+                    mint: Some(*mint),
+                    amount: amount.to_string(),
+                    limit: limit.to_string(),
+                });
+            }
+        }
+        if let Some(threshold) = &limits.confirm_above
+            && amount > TokenAmount::parse(threshold, decimals)?
+            && !plan.is_confirmed()
+        {
+            violations.push(Violation::ConfirmationRequired(format!(
+                "moving {} of {}",
+                amount, mint
+            )));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,355 @@
+use std::{collections::HashMap, fmt};
+
+use solana_sdk::{account::Account, message::Message, pubkey::Pubkey};
+use solana_system_interface::instruction::SystemInstruction;
+use spl_token_2022::{
+    extension::transfer_fee::instruction::TransferFeeInstruction, instruction::TokenInstruction,
+};
+
+use crate::{
+    amount::{Lamports, TokenAmount},
+    async_unmint::AsyncUnmint,
+    error::{Result, UnmintError},
+    mint::MintInfo,
+    scan::OwnedTokenAccount,
+    token_program::TokenProgram,
+    unmint::Unmint,
+};
+
+/// What an account is short of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Asset {
+    Sol,
+    Token { mint: Pubkey, decimals: u8 },
+}
+
+/// An account that cannot cover its part of a transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shortfall {
+    pub account: Pubkey,
+    pub asset: Asset,
+    /// Lamports or tokens the transaction moves out of the account
+    pub amount: u64,
+    /// Transaction fee, charged to the fee payer only
+    pub fee: u64,
+    /// Rent of the token accounts this account creates
+    pub rent: u64,
+    /// Current balance of the account
+    pub available: u64,
+}
+
+impl Shortfall {
+    pub fn required(&self) -> u64 {
+        self.amount
+            .saturating_add(self.fee)
+            .saturating_add(self.rent)
+    }
+
+    /// How much has to be added to the account for the transaction to go through
+    pub fn missing(&self) -> u64 {
+        self.required().saturating_sub(self.available)
+    }
+}
+
+impl fmt::Display for Shortfall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.asset {
+            Asset::Sol => write!(
+                f,
+                "{} has {} but needs {} (fee {}, rent {})",
+                self.account,
+                Lamports(self.available),
+                Lamports(self.required()),
+                Lamports(self.fee),
+                Lamports(self.rent),
+            ),
+            Asset::Token { mint, decimals } => write!(
+                f,
+                "{} has {} but needs {} of {}",
+                self.account,
+                TokenAmount::from_raw(self.available, decimals),
+                TokenAmount::from_raw(self.required(), decimals),
+                mint,
+            ),
+        }
+    }
+}
+
+/// Token account created by an associated token account instruction
+struct NewTokenAccount {
+    payer: Pubkey,
+    address: Pubkey,
+    mint: Pubkey,
+}
+
+/// Lamports and tokens a message moves, read from its instructions
+///
+/// `Unmint::preflight` fetches the accounts, mints and rent the requirements
+/// name and runs `check` before signing.
+#[derive(Default)]
+pub struct Requirements {
+    fee_payer: Pubkey,
+    /// Lamports sent by system transfers and account creations, per source
+    lamports: HashMap<Pubkey, u64>,
+    /// Tokens transferred or burned, per source token account
+    tokens: HashMap<Pubkey, u64>,
+    new_accounts: Vec<NewTokenAccount>,
+    /// Accounts in message order, so shortfalls are reported in a stable order
+    order: Vec<Pubkey>,
+}
+
+impl Requirements {
+    pub fn from_message(message: &Message) -> Self {
+        let mut requirements = Self {
+            fee_payer: message.account_keys[0],
+            order: message.account_keys.clone(),
+            ..Self::default()
+        };
+
+        for instruction in &message.instructions {
+            let program = message.account_keys[instruction.program_id_index as usize];
+            let account = |index: usize| {
+                instruction
+                    .accounts
+                    .get(index)
+                    .map(|key| message.account_keys[*key as usize])
+            };
+
+            if program == solana_system_interface::program::ID {
+                let lamports = match bincode::deserialize(&instruction.data) {
+                    Ok(SystemInstruction::Transfer { lamports })
+                    | Ok(SystemInstruction::CreateAccount { lamports, .. }) => lamports,
+                    _ => continue,
+                };
+                if let Some(source) = account(0) {
+                    *requirements.lamports.entry(source).or_default() += lamports;
+                }
+            } else if program == spl_associated_token_account::id() {
+                // Create and CreateIdempotent; RecoverNested creates nothing
+                if !matches!(instruction.data.first(), None | Some(0) | Some(1)) {
+                    continue;
+                }
+                if let (Some(payer), Some(address), Some(mint)) =
+                    (account(0), account(1), account(3))
+                {
+                    requirements.new_accounts.push(NewTokenAccount {
+                        payer,
+                        address,
+                        mint,
+                    });
+                }
+            } else if TokenProgram::is_token_program(&program) {
+                // legacy mints are sent with the plain, deprecated Transfer
+                #[allow(deprecated)]
+                let amount = match TokenInstruction::unpack(&instruction.data) {
+                    Ok(TokenInstruction::Transfer { amount })
+                    | Ok(TokenInstruction::TransferChecked { amount, .. })
+                    | Ok(TokenInstruction::Burn { amount })
+                    | Ok(TokenInstruction::BurnChecked { amount, .. }) => amount,
+                    Ok(TokenInstruction::TransferFeeExtension) => {
+                        match TransferFeeInstruction::unpack(&instruction.data[1..]) {
+                            Ok(TransferFeeInstruction::TransferCheckedWithFee {
+                                amount, ..
+                            }) => amount,
+                            _ => continue,
+                        }
+                    }
+                    _ => continue,
+                };
+                if let Some(source) = account(0) {
+                    *requirements.tokens.entry(source).or_default() += amount;
+                }
+            }
+        }
+
+        requirements
+    }
+
+    /// Accounts whose balances the check needs
+    pub fn addresses(&self) -> Vec<Pubkey> {
+        let mut addresses = vec![self.fee_payer];
+        addresses.extend(self.lamports.keys());
+        addresses.extend(self.tokens.keys());
+        for account in &self.new_accounts {
+            addresses.push(account.payer);
+            addresses.push(account.address);
+        }
+        addresses.sort();
+        addresses.dedup();
+        addresses
+    }
+
+    /// Mints of the debited token accounts and of the created ones
+    pub fn mints(&self, accounts: &HashMap<Pubkey, Account>) -> Vec<Pubkey> {
+        let mut mints: Vec<Pubkey> = self
+            .tokens
+            .keys()
+            .filter_map(|source| token_account(source, accounts))
+            .map(|token| token.account.mint)
+            .chain(self.new_accounts.iter().map(|account| account.mint))
+            .collect();
+        mints.sort();
+        mints.dedup();
+        mints
+    }
+
+    /// Sizes of the token accounts that do not exist yet and will be created
+    pub fn new_account_lens(
+        &self,
+        accounts: &HashMap<Pubkey, Account>,
+        mints: &HashMap<Pubkey, MintInfo>,
+    ) -> Result<Vec<usize>> {
+        let mut lens = vec![];
+        for account in &self.new_accounts {
+            if accounts.contains_key(&account.address) {
+                continue;
+            }
+            if let Some(mint) = mints.get(&account.mint) {
+                lens.push(mint.token_account_len()?);
+            }
+        }
+        lens.sort();
+        lens.dedup();
+        Ok(lens)
+    }
+
+    /// Compares what the message needs with the fetched balances
+    ///
+    /// `rent` maps every length from `new_account_lens` to its rent-exempt minimum.
+    pub fn check(
+        &self,
+        fee: u64,
+        accounts: &HashMap<Pubkey, Account>,
+        mints: &HashMap<Pubkey, MintInfo>,
+        rent: &HashMap<usize, u64>,
+    ) -> Result<()> {
+        let mut rent_by_payer: HashMap<Pubkey, u64> = HashMap::new();
+        for account in &self.new_accounts {
+            if accounts.contains_key(&account.address) {
+                continue;
+            }
+            let Some(mint) = mints.get(&account.mint) else {
+                continue;
+            };
+            let len = mint.token_account_len()?;
+            *rent_by_payer.entry(account.payer).or_default() +=
+                rent.get(&len).copied().unwrap_or_default();
+        }
+
+        let mut shortfalls = vec![];
+        for address in &self.order {
+            let sol = Shortfall {
+                account: *address,
+                asset: Asset::Sol,
+                amount: self.lamports.get(address).copied().unwrap_or_default(),
+                fee: if *address == self.fee_payer { fee } else { 0 },
+                rent: rent_by_payer.get(address).copied().unwrap_or_default(),
+                available: accounts
+                    .get(address)
+                    .map(|a| a.lamports)
+                    .unwrap_or_default(),
+            };
+            if sol.missing() > 0 {
+                shortfalls.push(sol);
+            }
+
+            // a source that is not a token account is left for the token program to reject
+            if let Some(amount) = self.tokens.get(address)
+                && let Some(token) = token_account(address, accounts)
+            {
+                let mint = token.account.mint;
+                let tokens = Shortfall {
+                    account: *address,
+                    asset: Asset::Token {
+                        mint,
+                        decimals: mints.get(&mint).map(|m| m.decimals).unwrap_or_default(),
+                    },
+                    amount: *amount,
+                    fee: 0,
+                    rent: 0,
+                    available: token.account.amount,
+                };
+                if tokens.missing() > 0 {
+                    shortfalls.push(tokens);
+                }
+            }
+        }
+
+        if shortfalls.is_empty() {
+            Ok(())
+        } else {
+            Err(UnmintError::Shortfall(shortfalls))
+        }
+    }
+}
+
+fn token_account(
+    address: &Pubkey,
+    accounts: &HashMap<Pubkey, Account>,
+) -> Option<OwnedTokenAccount> {
+    OwnedTokenAccount::decode(*address, accounts.get(address)?).ok()
+}
+
+impl Unmint {
+    /// Checks that the fee payer and senders can cover `message` before it is signed
+    ///
+    /// The fee is quoted with `getFeeForMessage`; the rent of every associated
+    /// token account the message creates is sized from the extensions of its mint.
+    pub(crate) fn preflight(&self, message: &Message) -> Result<()> {
+        let requirements = Requirements::from_message(message);
+
+        let addresses = requirements.addresses();
+        let accounts: HashMap<Pubkey, Account> = addresses
+            .iter()
+            .zip(self.multiple_accounts(&addresses)?)
+            .filter_map(|(address, account)| Some((*address, account?)))
+            .collect();
+
+        let mints = self.mint_infos(&requirements.mints(&accounts))?;
+
+        let mut rent = HashMap::new();
+        for len in requirements.new_account_lens(&accounts, &mints)? {
+            rent.insert(
+                len,
+                self.client.get_minimum_balance_for_rent_exemption(len)?,
+            );
+        }
+
+        let fee = self.client.get_fee_for_message(message)?;
+
+        requirements.check(fee, &accounts, &mints, &rent)
+    }
+}
+
+impl AsyncUnmint {
+    /// Async counterpart of `Unmint::preflight`
+    pub(crate) async fn preflight(&self, message: &Message) -> Result<()> {
+        let requirements = Requirements::from_message(message);
+
+        let addresses = requirements.addresses();
+        let accounts: HashMap<Pubkey, Account> = addresses
+            .iter()
+            .zip(self.multiple_accounts(&addresses).await?)
+            .filter_map(|(address, account)| Some((*address, account?)))
+            .collect();
+
+        let mut mints = HashMap::new();
+        for mint in requirements.mints(&accounts) {
+            mints.insert(mint, self.cached_mint_info(&mint).await?);
+        }
+
+        let mut rent = HashMap::new();
+        for len in requirements.new_account_lens(&accounts, &mints)? {
+            rent.insert(
+                len,
+                self.client
+                    .get_minimum_balance_for_rent_exemption(len)
+                    .await?,
+            );
+        }
+
+        let fee = self.client.get_fee_for_message(message).await?;
+
+        requirements.check(fee, &accounts, &mints, &rent)
+    }
+}
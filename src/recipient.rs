@@ -0,0 +1,153 @@
+use std::fmt;
+
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+use crate::{
+    async_unmint::AsyncUnmint,
+    error::{Result, UnmintError},
+    mint::MintInfo,
+    scan::OwnedTokenAccount,
+    token_program::TokenProgram,
+    unmint::Unmint,
+};
+
+/// Why an address cannot receive tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecipientKind {
+    /// A token account of another mint
+    TokenAccount {
+        mint: Pubkey,
+    },
+    Mint,
+    /// An executable program
+    Program,
+    /// Not an ed25519 public key, e.g. a PDA; see `with_allow_owner_off_curve`
+    OffCurve,
+}
+
+impl fmt::Display for RecipientKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecipientKind::TokenAccount { mint } => write!(f, "a token account of mint {}", mint),
+            RecipientKind::Mint => write!(f, "a mint"),
+            RecipientKind::Program => write!(f, "an executable program"),
+            RecipientKind::OffCurve => write!(
+                f,
+                "an off-curve address such as a PDA, see `with_allow_owner_off_curve`"
+            ),
+        }
+    }
+}
+
+/// Where tokens sent to an address end up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recipient {
+    /// A wallet; tokens go to its associated token account
+    Wallet(Pubkey),
+    /// A token account of the mint being sent; tokens go to it directly
+    TokenAccount(Pubkey),
+}
+
+/// Checks that `address`, fetched as `account`, can receive tokens of `mint`
+///
+/// Token accounts of other mints, mints and programs are refused. Off-curve
+/// addresses such as PDAs are only accepted with `allow_owner_off_curve`.
+pub fn validate_recipient(
+    address: &Pubkey,
+    account: Option<&Account>,
+    mint: &Pubkey,
+    allow_owner_off_curve: bool,
+) -> Result<Recipient> {
+    let invalid = |kind| UnmintError::InvalidRecipient {
+        address: *address,
+        kind,
+    };
+
+    if let Some(account) = account {
+        if account.executable {
+            return Err(invalid(RecipientKind::Program));
+        }
+
+        if TokenProgram::is_token_program(&account.owner) {
+            if let Ok(token) = OwnedTokenAccount::decode(*address, account) {
+                if token.account.mint != *mint {
+                    return Err(invalid(RecipientKind::TokenAccount {
+                        mint: token.account.mint,
+                    }));
+                }
+                return Ok(Recipient::TokenAccount(*address));
+            }
+            if MintInfo::decode(*address, account).is_ok() {
+                return Err(invalid(RecipientKind::Mint));
+            }
+        }
+    }
+
+    if !allow_owner_off_curve && !address.is_on_curve() {
+        return Err(invalid(RecipientKind::OffCurve));
+    }
+
+    Ok(Recipient::Wallet(*address))
+}
+
+/// Token account a transfer credits
+pub(crate) struct Destination {
+    pub address: Pubkey,
+    /// Wallet the associated token account is derived from
+    pub owner: Pubkey,
+    /// `None` when the account does not exist yet
+    pub account: Option<Account>,
+    /// Set for associated token accounts, which are created idempotently so
+    /// that a concurrent creation or close cannot fail the transfer
+    pub create: bool,
+}
+
+impl Unmint {
+    /// Resolves the token account receiving `mint` sent to `to`
+    pub(crate) fn destination(&self, to: &Pubkey, mint: &Pubkey) -> Result<Destination> {
+        let account = self.fetch_account(to)?;
+
+        match validate_recipient(to, account.as_ref(), mint, self.allow_owner_off_curve)? {
+            Recipient::TokenAccount(address) => Ok(Destination {
+                address,
+                owner: *to,
+                account,
+                create: false,
+            }),
+            Recipient::Wallet(owner) => {
+                let address = self.token_program.ata(&owner, mint);
+                Ok(Destination {
+                    address,
+                    owner,
+                    account: self.fetch_account(&address)?,
+                    create: true,
+                })
+            }
+        }
+    }
+}
+
+impl AsyncUnmint {
+    /// Async counterpart of `Unmint::destination`
+    pub(crate) async fn destination(&self, to: &Pubkey, mint: &Pubkey) -> Result<Destination> {
+        let account = self.fetch_account(to).await?;
+
+        match validate_recipient(to, account.as_ref(), mint, self.allow_owner_off_curve)? {
+            Recipient::TokenAccount(address) => Ok(Destination {
+                address,
+                owner: *to,
+                account,
+                create: false,
+            }),
+            Recipient::Wallet(owner) => {
+                let address = self.token_program.ata(&owner, mint);
+                Ok(Destination {
+                    address,
+                    owner,
+                    account: self.fetch_account(&address).await?,
+                    create: true,
+                })
+            }
+        }
+    }
+}
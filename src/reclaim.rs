@@ -0,0 +1,429 @@
+use solana_account_decoder_client_types::{UiAccountEncoding, UiDataSliceConfig};
+use solana_address_lookup_table_interface::{
+    instruction as lookup_table_instruction,
+    state::{LookupTableStatus, ProgramState, estimate_last_valid_slot},
+};
+use solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_loader_v3_interface::{
+    instruction as loader_instruction, state::UpgradeableLoaderState,
+};
+use solana_nonce::{state::State as NonceState, versions::Versions as NonceVersions};
+use solana_sdk::{
+    account::Account,
+    clock::Slot,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+    slot_hashes::SlotHashes,
+};
+use solana_sdk_ids::{
+    address_lookup_table::ID as LOOKUP_TABLE_PROGRAM_ID,
+    bpf_loader_upgradeable::ID as LOADER_PROGRAM_ID, system_program::ID as SYSTEM_PROGRAM_ID,
+    sysvar::slot_hashes::ID as SLOT_HASHES_ID,
+};
+use solana_system_interface::instruction as system_instruction;
+
+use crate::{
+    error::{Result, UnmintError, parse_pubkey},
+    instructions::{self, Signers},
+    metadata::{TokenMetadata, token_label},
+    scan::OwnedTokenAccount,
+    stake::StakeAccount,
+    unmint::{PubkeyInput, Unmint},
+};
+
+/// Buffer tag and `Some` tag preceding the authority of a loader buffer
+const BUFFER_AUTHORITY_PREFIX: [u8; 5] = [1, 0, 0, 0, 1];
+
+/// Length of a buffer header: tag, `Option` tag and authority
+const BUFFER_HEADER_LEN: usize = 37;
+
+/// Size of a nonce account
+const NONCE_ACCOUNT_LEN: u64 = 80;
+
+/// Offset of the authority in a nonce account: versions tag, then state tag
+const NONCE_AUTHORITY_OFFSET: usize = 8;
+
+/// Offset of the `Option` tag of the authority in a lookup table
+const LOOKUP_TABLE_AUTHORITY_OFFSET: usize = 21;
+
+/// Length of the lookup table metadata preceding its addresses
+const LOOKUP_TABLE_META_LEN: usize = 56;
+
+/// Where an address lookup table is on its way to being closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LookupTableState {
+    /// Usable; must be deactivated before it can be closed
+    Active,
+    /// Deactivated at `deactivation_slot` and cooling down
+    Deactivating {
+        deactivation_slot: Slot,
+        /// Earliest slot the table can be closed at; skipped slots can delay it further
+        closable_after: Slot,
+    },
+    /// Cooled down; can be closed
+    Deactivated,
+}
+
+/// Kind of a non-token account holding rent for a wallet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReclaimKind {
+    /// Upgradeable loader buffer left by an abandoned deploy
+    Buffer,
+    /// Durable nonce account
+    Nonce,
+    /// Address lookup table
+    LookupTable(LookupTableState),
+}
+
+/// A rent-holding account the wallet is the authority of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReclaimableAccount {
+    pub address: Pubkey,
+    pub kind: ReclaimKind,
+    /// Lamports returned by closing or emptying the account
+    pub lamports: u64,
+}
+
+impl ReclaimableAccount {
+    /// Decodes a fetched buffer, nonce or lookup table account
+    ///
+    /// Buffers and lookup tables may be sliced to their header. `slot` and
+    /// `slot_hashes` tell how far a deactivated lookup table has cooled down.
+    pub fn decode(
+        address: Pubkey,
+        account: &Account,
+        slot: Slot,
+        slot_hashes: &SlotHashes,
+    ) -> Result<Self> {
+        let invalid = |e: bincode::Error| UnmintError::InvalidAccountData {
+            address,
+            reason: e.to_string(),
+        };
+        let wrong_type = |expected| UnmintError::WrongAccountType { address, expected };
+
+        let kind = match account.owner {
+            LOADER_PROGRAM_ID => match bincode::deserialize(&account.data).map_err(invalid)? {
+                UpgradeableLoaderState::Buffer { .. } => ReclaimKind::Buffer,
+                _ => return Err(wrong_type("buffer")),
+            },
+            SYSTEM_PROGRAM_ID => {
+                let versions: NonceVersions =
+                    bincode::deserialize(&account.data).map_err(invalid)?;
+                match versions.state() {
+                    NonceState::Initialized(_) => ReclaimKind::Nonce,
+                    NonceState::Uninitialized => {
+                        return Err(wrong_type("nonce account"));
+                    }
+                }
+            }
+            LOOKUP_TABLE_PROGRAM_ID => {
+                match bincode::deserialize(&account.data).map_err(invalid)? {
+                    ProgramState::LookupTable(meta) => {
+                        let state = match meta.status(slot, slot_hashes) {
+                            LookupTableStatus::Activated => LookupTableState::Active,
+                            LookupTableStatus::Deactivating { .. } => {
+                                LookupTableState::Deactivating {
+                                    deactivation_slot: meta.deactivation_slot,
+                                    closable_after: estimate_last_valid_slot(
+                                        meta.deactivation_slot,
+                                    ),
+                                }
+                            }
+                            LookupTableStatus::Deactivated => LookupTableState::Deactivated,
+                        };
+                        ReclaimKind::LookupTable(state)
+                    }
+                    ProgramState::Uninitialized => {
+                        return Err(wrong_type("lookup table"));
+                    }
+                }
+            }
+            _ => return Err(wrong_type("buffer, nonce account or lookup table")),
+        };
+
+        Ok(Self {
+            address,
+            kind,
+            lamports: account.lamports,
+        })
+    }
+}
+
+/// An empty token account of the wallet that closing turns back into SOL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmptyTokenAccount {
+    pub address: Pubkey,
+    pub mint: Pubkey,
+    /// Symbol or shortened mint, see `token_label`
+    pub label: String,
+    /// Rent returned by closing the account
+    pub lamports: u64,
+}
+
+impl EmptyTokenAccount {
+    /// `account` when its owner can close it right away: no balance, no
+    /// withheld fees, not frozen and no other close authority
+    pub fn of(account: &OwnedTokenAccount, metadata: Option<&TokenMetadata>) -> Option<Self> {
+        let state = &account.account;
+        let close_authority: Option<Pubkey> = state.close_authority.into();
+        let closable = state.amount == 0
+            && account.withheld_amount == 0
+            && !state.is_frozen()
+            && close_authority.is_none_or(|authority| authority == state.owner);
+
+        closable.then(|| Self {
+            address: account.address,
+            mint: state.mint,
+            label: token_label(metadata, &state.mint),
+            lamports: account.rent(),
+        })
+    }
+}
+
+/// Everything a wallet can reclaim
+#[derive(Debug, Clone, Default)]
+pub struct ReclaimReport {
+    /// Stake accounts the wallet withdraws from
+    pub stake: Vec<StakeAccount>,
+    /// Buffers, nonce accounts and lookup tables the wallet is the authority of
+    pub accounts: Vec<ReclaimableAccount>,
+    /// Empty token accounts of the wallet
+    pub tokens: Vec<EmptyTokenAccount>,
+}
+
+impl ReclaimReport {
+    /// Lamports held by every account in the report
+    pub fn lamports(&self) -> u64 {
+        self.stake.iter().map(|stake| stake.lamports).sum::<u64>()
+            + self
+                .accounts
+                .iter()
+                .map(|account| account.lamports)
+                .sum::<u64>()
+            + self.tokens.iter().map(|token| token.lamports).sum::<u64>()
+    }
+}
+
+/// Everything `reclaim_accounts` did
+#[derive(Debug, Clone, Default)]
+pub struct AccountReclaim {
+    /// Buffers and lookup tables closed and nonce accounts emptied
+    pub closed: Vec<ReclaimableAccount>,
+    /// Active lookup tables deactivated; close them once they cool down
+    pub deactivated: Vec<ReclaimableAccount>,
+    /// Lookup tables still cooling down
+    pub waiting: Vec<ReclaimableAccount>,
+    /// Lamports sent to the destination
+    pub lamports_reclaimed: u64,
+    pub signatures: Vec<Signature>,
+}
+
+impl Unmint {
+    /// Lists the stake accounts, loader buffers, nonce accounts, lookup
+    /// tables and empty token accounts `wallet` can reclaim lamports from
+    ///
+    /// Scans each program with `getProgramAccounts`, so it needs an RPC node
+    /// that serves unrestricted program scans.
+    pub fn reclaim_report<'a, A>(&self, wallet: A) -> Result<ReclaimReport>
+    where
+        A: Into<PubkeyInput<'a>>,
+    {
+        let wallet_pubkey = wallet.into().to_pubkey()?;
+
+        let empty: Vec<OwnedTokenAccount> = self
+            .token_accounts(&wallet_pubkey)?
+            .into_iter()
+            .filter(|account| account.account.amount == 0)
+            .collect();
+        let mint_keys: Vec<Pubkey> = empty.iter().map(|a| a.account.mint).collect();
+        let metadata = self.token_metadata(&mint_keys)?;
+
+        Ok(ReclaimReport {
+            stake: self.stake_accounts(&wallet_pubkey)?,
+            accounts: self.reclaimable_accounts(&wallet_pubkey)?,
+            tokens: empty
+                .iter()
+                .filter_map(|account| {
+                    EmptyTokenAccount::of(account, metadata.get(&account.account.mint))
+                })
+                .collect(),
+        })
+    }
+
+    /// Lists the loader buffers, nonce accounts and lookup tables whose
+    /// authority is `authority`
+    pub fn reclaimable_accounts<'a, A>(&self, authority: A) -> Result<Vec<ReclaimableAccount>>
+    where
+        A: Into<PubkeyInput<'a>>,
+    {
+        let authority_pubkey = authority.into().to_pubkey()?;
+        let authority_bytes = authority_pubkey.to_bytes().to_vec();
+
+        let buffers = self.scan(
+            &LOADER_PROGRAM_ID,
+            vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                0,
+                [BUFFER_AUTHORITY_PREFIX.to_vec(), authority_bytes.clone()].concat(),
+            ))],
+            // buffers hold whole programs; only the header is needed
+            Some(BUFFER_HEADER_LEN),
+        )?;
+        let nonces = self.scan(
+            &SYSTEM_PROGRAM_ID,
+            vec![
+                RpcFilterType::DataSize(NONCE_ACCOUNT_LEN),
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                    NONCE_AUTHORITY_OFFSET,
+                    authority_bytes.clone(),
+                )),
+            ],
+            None,
+        )?;
+        let lookup_tables = self.scan(
+            &LOOKUP_TABLE_PROGRAM_ID,
+            vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                LOOKUP_TABLE_AUTHORITY_OFFSET,
+                [vec![1], authority_bytes].concat(),
+            ))],
+            Some(LOOKUP_TABLE_META_LEN),
+        )?;
+
+        let slot = self.client.get_slot()?;
+        let slot_hashes = if lookup_tables.is_empty() {
+            SlotHashes::default()
+        } else {
+            self.slot_hashes()?
+        };
+
+        buffers
+            .into_iter()
+            .chain(nonces)
+            .chain(lookup_tables)
+            .map(|(address, account)| {
+                ReclaimableAccount::decode(address, &account, slot, &slot_hashes)
+            })
+            .collect()
+    }
+
+    /// Closes the buffers, empties the nonce accounts and closes the cooled
+    /// down lookup tables of the wallet, sending their lamports to `destination`
+    ///
+    /// Active lookup tables are deactivated; they can be closed about 513
+    /// slots later, so run again afterwards. Stake accounts are left to
+    /// `reclaim_stake`.
+    ///
+    /// # Arguments
+    /// * `wallet_base58_string` - Base58 private key of the authority
+    /// * `destination` - Receives the lamports
+    /// * `fee_payer_base58_string` - Optional, who pays the fees
+    pub fn reclaim_accounts(
+        &self,
+        wallet_base58_string: &str,
+        destination: &str,
+        fee_payer_base58_string: Option<&str>,
+    ) -> Result<AccountReclaim> {
+        let signers = Signers::parse(wallet_base58_string, fee_payer_base58_string)?;
+        let destination = parse_pubkey(destination)?;
+        let wallet = signers.from.pubkey();
+        let mut reclaim = AccountReclaim::default();
+        let mut groups: Vec<Vec<Instruction>> = vec![];
+        for account in self.reclaimable_accounts(&wallet)? {
+            let instruction = match account.kind {
+                ReclaimKind::Buffer => {
+                    loader_instruction::close(&account.address, &destination, &wallet)
+                }
+                ReclaimKind::Nonce => system_instruction::withdraw_nonce_account(
+                    &account.address,
+                    &wallet,
+                    &destination,
+                    account.lamports,
+                ),
+                ReclaimKind::LookupTable(LookupTableState::Deactivated) => {
+                    lookup_table_instruction::close_lookup_table(
+                        account.address,
+                        wallet,
+                        destination,
+                    )
+                }
+                ReclaimKind::LookupTable(LookupTableState::Active) => {
+                    groups.push(vec![lookup_table_instruction::deactivate_lookup_table(
+                        account.address,
+                        wallet,
+                    )]);
+                    reclaim.deactivated.push(account);
+                    continue;
+                }
+                ReclaimKind::LookupTable(LookupTableState::Deactivating { .. }) => {
+                    reclaim.waiting.push(account);
+                    continue;
+                }
+            };
+            groups.push(vec![instruction]);
+            reclaim.lamports_reclaimed += account.lamports;
+            reclaim.closed.push(account);
+        }
+
+        if let Some(policy) = &self.policy {
+            policy.check(&instructions::sol_transfer_plan(
+                &signers.fee_payer.pubkey(),
+                &wallet,
+                &destination,
+                reclaim.closed.iter().map(|account| account.lamports),
+            ))?;
+        }
+
+        let batches = instructions::pack_groups(
+            &groups,
+            &signers.fee_payer.pubkey(),
+            self.priority_fee.as_ref(),
+        )?;
+        for batch in batches {
+            let batch_instructions: Vec<Instruction> =
+                batch.iter().flat_map(|&i| groups[i].clone()).collect();
+            reclaim
+                .signatures
+                .push(self.send_instructions(&batch_instructions, &signers)?);
+        }
+
+        Ok(reclaim)
+    }
+
+    /// Runs `getProgramAccounts` on `program_id`, optionally fetching only the
+    /// first `data_len` bytes of each account
+    fn scan(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+        data_len: Option<usize>,
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                data_slice: data_len.map(|length| UiDataSliceConfig { offset: 0, length }),
+                commitment: Some(self.client.commitment()),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+
+        Ok(self
+            .client
+            .get_program_accounts_with_config(program_id, config)?)
+    }
+
+    /// Fetches the SlotHashes sysvar, which tells when a lookup table has cooled down
+    fn slot_hashes(&self) -> Result<SlotHashes> {
+        let account = self
+            .fetch_account(&SLOT_HASHES_ID)?
+            .ok_or(UnmintError::AccountNotFound(SLOT_HASHES_ID))?;
+        bincode::deserialize(&account.data).map_err(|e| UnmintError::InvalidAccountData {
+            address: SLOT_HASHES_ID,
+            reason: e.to_string(),
+        })
+    }
+}
@@ -0,0 +1,490 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use solana_sdk::{
+    account::Account, instruction::Instruction, pubkey::Pubkey, signature::Signature,
+    signer::Signer,
+};
+use spl_token_2022::state::AccountState;
+
+use crate::{
+    amount::TokenAmount,
+    error::{Result, UnmintError, parse_pubkey},
+    instructions::{self, DrainMode, Signers},
+    journal::{Journal, display_fromstr},
+    metadata::token_label,
+    mint::MintInfo,
+    plan::{Plan, PlanAction},
+    scan::OwnedTokenAccount,
+    unmint::Unmint,
+};
+
+/// What `retire_wallet` does with a non-zero token balance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenDisposition {
+    /// Transfer the balance to the destination wallet's ATA
+    Transfer,
+    /// Burn the balance
+    Burn,
+    /// Leave the account untouched
+    Ignore,
+}
+
+/// Per-mint choice between transferring and burning token balances
+#[derive(Debug, Clone)]
+pub struct RetirePolicy {
+    pub default: TokenDisposition,
+    pub overrides: HashMap<Pubkey, TokenDisposition>,
+}
+
+impl Default for RetirePolicy {
+    fn default() -> Self {
+        Self {
+            default: TokenDisposition::Transfer,
+            overrides: HashMap::new(),
+        }
+    }
+}
+
+impl RetirePolicy {
+    pub fn disposition(&self, mint: &Pubkey) -> TokenDisposition {
+        self.overrides.get(mint).copied().unwrap_or(self.default)
+    }
+}
+
+/// What happened to one token account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenAction {
+    /// The balance was sent to `to` before closing
+    Transferred { to: Pubkey },
+    /// The balance was burned before closing
+    Burned,
+    /// Wrapped SOL was unwrapped by closing the account
+    Unwrapped,
+    /// The account was already empty and only closed
+    Closed,
+}
+
+/// A token account emptied by `retire_wallet_journaled`, as journaled under its address
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetireStep {
+    #[serde(with = "display_fromstr")]
+    pub mint: Pubkey,
+    /// Raw balance transferred or burned before closing
+    pub amount: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenReceipt {
+    pub account: Pubkey,
+    pub mint: Pubkey,
+    /// Symbol or shortened mint, see `token_label`
+    pub label: String,
+    pub amount: TokenAmount,
+    pub action: TokenAction,
+    /// Lamports returned to the wallet by closing the account
+    pub rent_reclaimed: u64,
+    pub signature: Signature,
+}
+
+/// Everything `retire_wallet` moved
+#[derive(Debug, Clone, Default)]
+pub struct RetireReceipt {
+    pub tokens: Vec<TokenReceipt>,
+    /// Lamports sent to the destination by the final SOL transfer
+    pub sol_transferred: u64,
+    pub sol_signature: Option<Signature>,
+    /// Token accounts left open because the policy ignores their mint
+    pub ignored: Vec<Pubkey>,
+}
+
+impl Unmint {
+    /// Empties and abandons a wallet
+    ///
+    /// Every token balance is transferred to `destination` or burned according to
+    /// `policy`, every token account is closed, wrapped SOL is unwrapped and the
+    /// remaining lamports are sent to `destination` so the wallet ends with zero.
+    /// Accounts of mints the policy ignores are left open. Nothing is sent if any
+    /// other token account cannot be handled.
+    ///
+    /// # Arguments
+    /// * `from_base58_string` - Base58 private key of the wallet to retire
+    /// * `destination` - Wallet receiving tokens and SOL
+    /// * `policy` - Whether to transfer or burn each mint
+    pub fn retire_wallet(
+        &self,
+        from_base58_string: &str,
+        destination: &str,
+        policy: &RetirePolicy,
+    ) -> Result<RetireReceipt> {
+        self.retire(from_base58_string, destination, policy, None)
+    }
+
+    /// `retire_wallet`, keeping every token account it empties in the `Journal`
+    /// at `journal_path`
+    ///
+    /// Pending transactions of a previous run are settled with `reconcile`
+    /// first; accounts they emptied are closed and no longer part of the run.
+    /// The receipt only covers the accounts emptied by this call.
+    pub fn retire_wallet_journaled<P: AsRef<Path>>(
+        &self,
+        from_base58_string: &str,
+        destination: &str,
+        policy: &RetirePolicy,
+        journal_path: P,
+    ) -> Result<RetireReceipt> {
+        // fail on a bad key before a journal file is created
+        Signers::parse(from_base58_string, None)?;
+        let mut journal = Journal::open(journal_path)?;
+        self.reconcile(&mut journal)?;
+
+        self.retire(from_base58_string, destination, policy, Some(&mut journal))
+    }
+
+    fn retire(
+        &self,
+        from_base58_string: &str,
+        destination: &str,
+        policy: &RetirePolicy,
+        mut journal: Option<&mut Journal<RetireStep>>,
+    ) -> Result<RetireReceipt> {
+        let signers = Signers::parse(from_base58_string, None)?;
+        let owner = signers.from.pubkey();
+        let destination = parse_pubkey(destination)?;
+
+        let (ignored, accounts): (Vec<_>, Vec<_>) = self
+            .token_accounts(&owner)?
+            .into_iter()
+            .partition(|account| {
+                policy.disposition(&account.account.mint) == TokenDisposition::Ignore
+            });
+        let mint_keys: Vec<Pubkey> = accounts.iter().map(|a| a.account.mint).collect();
+        let mints = self.mint_infos(&mint_keys)?;
+        let metadata = self.token_metadata(&mint_keys)?;
+
+        if let Some(guard) = &self.policy {
+            let lamports = self.client.get_balance(&owner)?;
+            guard.check(&retire_plan(
+                &owner,
+                &destination,
+                &accounts,
+                &mints,
+                policy,
+                lamports,
+            ))?;
+        }
+
+        // transfers carry no memo, so destinations requiring one are refused
+        let ata_destinations: Vec<Pubkey> = accounts
+            .iter()
+            .map(|account| {
+                account
+                    .token_program
+                    .ata(&destination, &account.account.mint)
+            })
+            .collect();
+        let destination_accounts = self.multiple_accounts(&ata_destinations)?;
+
+        let mut problems = vec![];
+        for (account, (ata_destination, destination_account)) in accounts
+            .iter()
+            .zip(ata_destinations.iter().zip(&destination_accounts))
+        {
+            if let Err(problem) = check_retirable(
+                account,
+                &owner,
+                mints.get(&account.account.mint),
+                policy,
+                ata_destination,
+                destination_account.as_ref(),
+            ) {
+                problems.push(format!("{}: {}", account.address, problem));
+            }
+        }
+        if !problems.is_empty() {
+            return Err(UnmintError::RetireRefused(problems));
+        }
+
+        let mut created = HashSet::new();
+        let mut groups = vec![];
+        let mut actions = vec![];
+
+        for account in &accounts {
+            let (group, action) = self.retire_instructions(
+                &signers,
+                &destination,
+                account,
+                mints.get(&account.account.mint),
+                policy,
+                &mut created,
+            )?;
+            groups.push(group);
+            actions.push(action);
+        }
+
+        let mut receipt = RetireReceipt {
+            ignored: ignored.iter().map(|account| account.address).collect(),
+            ..RetireReceipt::default()
+        };
+        let batches = instructions::pack_groups(&groups, &owner, self.priority_fee.as_ref())?;
+
+        for batch in batches {
+            let batch_instructions: Vec<Instruction> =
+                batch.iter().flat_map(|&i| groups[i].clone()).collect();
+            let signature = match journal.as_deref_mut() {
+                Some(journal) => {
+                    let ids: Vec<String> = batch
+                        .iter()
+                        .map(|&i| accounts[i].address.to_string())
+                        .collect();
+                    for &i in &batch {
+                        let account = &accounts[i];
+                        journal.replan(
+                            &account.address.to_string(),
+                            RetireStep {
+                                mint: account.account.mint,
+                                amount: account.account.amount,
+                            },
+                        )?;
+                    }
+                    self.send_journaled(journal, &ids, &batch_instructions, &signers)?
+                }
+                None => self.send_instructions(&batch_instructions, &signers)?,
+            };
+
+            for i in batch {
+                let account = &accounts[i];
+                let decimals = mints
+                    .get(&account.account.mint)
+                    .map(|mint| mint.decimals)
+                    .unwrap_or_default();
+                receipt.tokens.push(TokenReceipt {
+                    account: account.address,
+                    mint: account.account.mint,
+                    label: token_label(metadata.get(&account.account.mint), &account.account.mint),
+                    amount: TokenAmount::from_raw(account.account.amount, decimals),
+                    action: actions[i].clone(),
+                    rent_reclaimed: account.rent(),
+                    signature,
+                });
+            }
+        }
+
+        // dust below the fee of the final transfer stays behind
+        match self.drain_sol(&signers, &destination, DrainMode::Full) {
+            Ok((lamports, signature)) => {
+                receipt.sol_transferred = lamports;
+                receipt.sol_signature = Some(signature);
+            }
+            Err(UnmintError::InsufficientFunds(_)) => {}
+            Err(e) => return Err(e),
+        }
+
+        Ok(receipt)
+    }
+
+    fn retire_instructions(
+        &self,
+        signers: &Signers,
+        destination: &Pubkey,
+        account: &OwnedTokenAccount,
+        mint: Option<&MintInfo>,
+        policy: &RetirePolicy,
+        created: &mut HashSet<Pubkey>,
+    ) -> Result<(Vec<Instruction>, TokenAction)> {
+        let program = account.token_program;
+        let owner = signers.from.pubkey();
+        let token_mint = account.account.mint;
+        let mut group = vec![];
+
+        let action = if account.account.is_native() {
+            TokenAction::Unwrapped
+        } else if account.account.amount == 0 {
+            TokenAction::Closed
+        } else {
+            // check_retirable guarantees the mint exists for non-zero balances
+            let decimals = mint.map(|m| m.decimals).unwrap_or_default();
+            match policy.disposition(&token_mint) {
+                TokenDisposition::Burn => {
+                    group.push(program.burn_ix(
+                        &account.address,
+                        &token_mint,
+                        &signers.from,
+                        account.account.amount,
+                        decimals,
+                    )?);
+                    TokenAction::Burned
+                }
+                TokenDisposition::Ignore => unreachable!("ignored accounts are filtered out"),
+                TokenDisposition::Transfer => {
+                    let ata_destination = program.ata(destination, &token_mint);
+                    // idempotent, so an existing ATA needs no lookup
+                    if created.insert(ata_destination) {
+                        group.push(program.create_ata_idempotent_ix(
+                            &owner,
+                            destination,
+                            &token_mint,
+                        ));
+                    }
+                    group.push(self.transfer_ix(
+                        &program,
+                        &account.address,
+                        &ata_destination,
+                        &signers.from,
+                        account.account.amount,
+                        decimals,
+                        &token_mint,
+                        None,
+                    )?);
+                    TokenAction::Transferred {
+                        to: ata_destination,
+                    }
+                }
+            }
+        };
+
+        group.push(program.close_ix(&account.address, &owner, &signers.from)?);
+
+        Ok((group, action))
+    }
+}
+
+/// The steps `retire` takes, for `Policy::check`
+///
+/// The final SOL transfer is taken to move
+/// `lamports` plus everything the closed accounts hold, before fees.
+fn retire_plan(
+    owner: &Pubkey,
+    destination: &Pubkey,
+    accounts: &[OwnedTokenAccount],
+    mints: &HashMap<Pubkey, MintInfo>,
+    policy: &RetirePolicy,
+    lamports: u64,
+) -> Plan {
+    let mut actions = vec![];
+    let mut created = HashSet::new();
+    let mut sol = lamports;
+
+    for account in accounts {
+        let program = account.token_program.program_id();
+        let mint = account.account.mint;
+        let decimals = mints.get(&mint).map(|m| m.decimals).unwrap_or_default();
+        sol = sol.saturating_add(account.lamports);
+
+        if account.account.is_native() {
+            actions.push(PlanAction::Unwrap {
+                program,
+                account: account.address,
+                mint,
+                destination: *owner,
+                authority: *owner,
+            });
+            continue;
+        }
+
+        if account.account.amount > 0 {
+            match policy.disposition(&mint) {
+                TokenDisposition::Burn => actions.push(PlanAction::Burn {
+                    program,
+                    account: account.address,
+                    authority: *owner,
+                    mint,
+                    amount: account.account.amount,
+                    decimals,
+                }),
+                TokenDisposition::Ignore => {}
+                TokenDisposition::Transfer => {
+                    let address = account.token_program.ata(destination, &mint);
+                    if created.insert(address) {
+                        actions.push(PlanAction::CreateAta {
+                            program,
+                            payer: *owner,
+                            owner: *destination,
+                            mint,
+                            address,
+                        });
+                    }
+                    actions.push(PlanAction::Transfer {
+                        program,
+                        source: account.address,
+                        destination: address,
+                        authority: *owner,
+                        mint,
+                        amount: account.account.amount,
+                        decimals,
+                        fee: None,
+                    });
+                }
+            }
+        }
+        actions.push(PlanAction::Close {
+            program,
+            account: account.address,
+            mint,
+            destination: *owner,
+            authority: *owner,
+        });
+    }
+
+    actions.push(PlanAction::TransferSol {
+        from: *owner,
+        to: *destination,
+        lamports: sol,
+    });
+
+    Plan::from_actions(*owner, actions)
+}
+
+fn check_retirable(
+    account: &OwnedTokenAccount,
+    owner: &Pubkey,
+    mint: Option<&MintInfo>,
+    policy: &RetirePolicy,
+    ata_destination: &Pubkey,
+    destination_account: Option<&Account>,
+) -> std::result::Result<(), String> {
+    let state = &account.account;
+
+    if state.state == AccountState::Frozen {
+        return Err("account is frozen".to_string());
+    }
+    if let Some(close_authority) = Option::<Pubkey>::from(state.close_authority)
+        && close_authority != *owner
+    {
+        return Err(format!("close authority is {}", close_authority));
+    }
+    if account.withheld_amount > 0 {
+        return Err(format!(
+            "{} withheld transfer fees must be harvested first",
+            account.withheld_amount
+        ));
+    }
+    if state.is_native() || state.amount == 0 {
+        return Ok(());
+    }
+
+    let Some(mint) = mint else {
+        return Err(format!("mint {} no longer exists", state.mint));
+    };
+    if mint.is_non_transferable() && policy.disposition(&state.mint) == TokenDisposition::Transfer {
+        return Err("mint is non-transferable; burn it instead".to_string());
+    }
+    if policy.disposition(&state.mint) == TokenDisposition::Transfer {
+        match instructions::check_memo(ata_destination, destination_account, None) {
+            Ok(()) => {}
+            Err(UnmintError::MemoRequired(_)) => {
+                return Err(format!(
+                    "destination {} requires a memo; burn it instead",
+                    ata_destination
+                ));
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+
+    Ok(())
+}
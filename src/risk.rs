@@ -0,0 +1,246 @@
+use std::collections::{HashMap, HashSet};
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    error::Result,
+    metadata::{TokenMetadata, token_label},
+    mint::{MintExtension, MintInfo},
+    retire::{RetirePolicy, TokenDisposition},
+    scan::OwnedTokenAccount,
+    unmint::{PubkeyInput, Unmint},
+};
+
+/// Symbols of well-known tokens and their genuine mints
+const KNOWN_TICKERS: [(&str, &str); 6] = [
+    ("USDC", "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"),
+    ("USDT", "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB"),
+    ("SOL", "So11111111111111111111111111111111111111112"),
+    ("JUP", "JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN"),
+    ("BONK", "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263"),
+    ("PYUSD", "2b1kV6DkPAnxd5ixfnxCpjxmKwqjjaYmCZfHsFu24GXo"),
+];
+
+/// Words scam airdrops use to lure holders to a site
+const LURE_WORDS: [&str; 8] = [
+    "claim", "airdrop", "reward", "voucher", "visit", "free", "bonus", "giveaway",
+];
+
+/// How much a flag matters when deciding what to do with a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RiskLevel {
+    /// Common on legitimate tokens, worth showing
+    Info,
+    /// Limits what can be done with the token
+    Warning,
+    /// Typical of scams
+    High,
+}
+
+/// One reason a token looks risky
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RiskFlag {
+    /// A permanent delegate can move or burn the balance at any time
+    PermanentDelegate(Pubkey),
+    /// The balance can be frozen
+    FreezeAuthority(Pubkey),
+    /// The token cannot be transferred, only burned
+    NonTransferable,
+    /// Transfers invoke a program not listed in `RiskConfig::trusted_hook_programs`
+    UnknownTransferHook(Pubkey),
+    /// More tokens can still be minted
+    MintAuthority(Pubkey),
+    /// The metadata URI or text points holders somewhere suspicious
+    SuspiciousMetadata(String),
+    /// The symbol matches a well-known token but the mint is not the genuine one
+    ImpersonatedTicker(String),
+    /// Zero-decimal token without metadata, typical of NFT spam
+    NftSpam,
+}
+
+impl RiskFlag {
+    pub fn level(&self) -> RiskLevel {
+        match self {
+            RiskFlag::FreezeAuthority(_) | RiskFlag::MintAuthority(_) => RiskLevel::Info,
+            RiskFlag::NonTransferable | RiskFlag::NftSpam => RiskLevel::Warning,
+            RiskFlag::PermanentDelegate(_)
+            | RiskFlag::UnknownTransferHook(_)
+            | RiskFlag::SuspiciousMetadata(_)
+            | RiskFlag::ImpersonatedTicker(_) => RiskLevel::High,
+        }
+    }
+}
+
+/// What `assess_token` trusts
+#[derive(Debug, Clone)]
+pub struct RiskConfig {
+    /// Transfer hook programs that are not flagged
+    pub trusted_hook_programs: HashSet<Pubkey>,
+    /// Upper-case symbol to the genuine mint of that symbol
+    pub known_tickers: HashMap<String, Pubkey>,
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        let known_tickers = KNOWN_TICKERS
+            .iter()
+            .map(|(symbol, mint)| (symbol.to_string(), mint.parse().unwrap()))
+            .collect();
+
+        Self {
+            trusted_hook_programs: HashSet::new(),
+            known_tickers,
+        }
+    }
+}
+
+/// Risk assessment of one token account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RiskReport {
+    pub account: Pubkey,
+    pub mint: Pubkey,
+    /// Symbol or shortened mint, see `token_label`
+    pub label: String,
+    pub flags: Vec<RiskFlag>,
+}
+
+impl RiskReport {
+    /// Highest level among the flags, `None` when nothing was flagged
+    pub fn level(&self) -> Option<RiskLevel> {
+        self.flags.iter().map(RiskFlag::level).max()
+    }
+}
+
+/// Assesses a token account from its mint and metadata, without any RPC call
+pub fn assess_token(
+    account: &OwnedTokenAccount,
+    mint: &MintInfo,
+    metadata: Option<&TokenMetadata>,
+    config: &RiskConfig,
+) -> RiskReport {
+    let mut flags = vec![];
+
+    for extension in &mint.extensions {
+        match extension {
+            MintExtension::PermanentDelegate {
+                delegate: Some(delegate),
+            } => flags.push(RiskFlag::PermanentDelegate(*delegate)),
+            MintExtension::NonTransferable => flags.push(RiskFlag::NonTransferable),
+            MintExtension::TransferHook {
+                program_id: Some(program_id),
+                ..
+            } if !config.trusted_hook_programs.contains(program_id) => {
+                flags.push(RiskFlag::UnknownTransferHook(*program_id))
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(freeze_authority) = mint.freeze_authority {
+        flags.push(RiskFlag::FreezeAuthority(freeze_authority));
+    }
+    if let Some(mint_authority) = mint.mint_authority {
+        flags.push(RiskFlag::MintAuthority(mint_authority));
+    }
+
+    match metadata {
+        Some(metadata) => {
+            for text in [&metadata.name, &metadata.symbol, &metadata.uri] {
+                if is_suspicious(text) {
+                    flags.push(RiskFlag::SuspiciousMetadata(text.clone()));
+                }
+            }
+
+            let symbol = normalize_ticker(&metadata.symbol);
+            if let Some(genuine) = config.known_tickers.get(&symbol)
+                && *genuine != mint.address
+            {
+                flags.push(RiskFlag::ImpersonatedTicker(metadata.symbol.clone()));
+            }
+        }
+        None if mint.decimals == 0 => flags.push(RiskFlag::NftSpam),
+        None => {}
+    }
+
+    RiskReport {
+        account: account.address,
+        mint: mint.address,
+        label: token_label(metadata, &mint.address),
+        flags,
+    }
+}
+
+/// Upper-cases a symbol and drops everything but letters and digits, so
+/// `"usdc "` and `"U.S.D.C"` both become `"USDC"`
+fn normalize_ticker(symbol: &str) -> String {
+    symbol
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+fn is_suspicious(text: &str) -> bool {
+    let text = text.to_lowercase();
+
+    // whole words only, so "Freedom" or "Claimable" are not taken for a lure
+    if text
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| LURE_WORDS.contains(&word))
+    {
+        return true;
+    }
+
+    // metadata URIs are expected on https, IPFS or Arweave; anything else is odd
+    match text.split_once("://") {
+        Some((scheme, _)) => !matches!(scheme, "https" | "ipfs" | "ar"),
+        None => text.contains("www.") || text.contains(".com"),
+    }
+}
+
+impl RetirePolicy {
+    /// Applies `disposition` to every mint whose report reaches `min_level`
+    ///
+    /// Non-transferable mints that would otherwise be transferred are burned.
+    pub fn apply_risk(
+        &mut self,
+        reports: &[RiskReport],
+        min_level: RiskLevel,
+        disposition: TokenDisposition,
+    ) {
+        for report in reports {
+            let flagged = report.level().is_some_and(|level| level >= min_level);
+            let non_transferable = report.flags.contains(&RiskFlag::NonTransferable);
+
+            if flagged && !(non_transferable && disposition == TokenDisposition::Transfer) {
+                self.overrides.insert(report.mint, disposition);
+            } else if non_transferable {
+                self.overrides.insert(report.mint, TokenDisposition::Burn);
+            }
+        }
+    }
+}
+
+impl Unmint {
+    /// Assesses every token account owned by `owner`
+    ///
+    /// Accounts whose mint no longer exists are left out.
+    pub fn risk_report<'a, A>(&self, owner: A, config: &RiskConfig) -> Result<Vec<RiskReport>>
+    where
+        A: Into<PubkeyInput<'a>>,
+    {
+        let accounts = self.token_accounts(owner)?;
+        let mint_keys: Vec<Pubkey> = accounts.iter().map(|a| a.account.mint).collect();
+        let mints = self.mint_infos(&mint_keys)?;
+        let metadata = self.token_metadata(&mint_keys)?;
+
+        Ok(accounts
+            .iter()
+            .filter_map(|account| {
+                let mint = mints.get(&account.account.mint)?;
+                let metadata = metadata.get(&account.account.mint);
+                Some(assess_token(account, mint, metadata, config))
+            })
+            .collect())
+    }
+}
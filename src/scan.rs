@@ -0,0 +1,127 @@
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use spl_token_2022::{
+    extension::{BaseStateWithExtensions, StateWithExtensions, transfer_fee::TransferFeeAmount},
+    state::Account as TokenAccount,
+};
+
+use crate::{
+    error::{Result, UnmintError},
+    token_program::TokenProgram,
+    unmint::{PubkeyInput, Unmint},
+};
+
+/// `getMultipleAccounts` accepts at most this many keys per request
+pub(crate) const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Offset of the owner field in a token account
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+
+/// A token account found by `Unmint::token_accounts`
+#[derive(Debug, Clone)]
+pub struct OwnedTokenAccount {
+    pub address: Pubkey,
+    pub token_program: TokenProgram,
+    /// Lamports held by the account, including rent and wrapped SOL
+    pub lamports: u64,
+    pub account: TokenAccount,
+    /// Transfer fees withheld in the account, Token-2022 only
+    pub withheld_amount: u64,
+}
+
+impl OwnedTokenAccount {
+    /// Decodes a fetched token account
+    pub fn decode(address: Pubkey, account: &Account) -> Result<Self> {
+        let token_program =
+            TokenProgram::from_program_id(&account.owner).ok_or(UnmintError::NotTokenProgram {
+                account: address,
+                owner: account.owner,
+            })?;
+
+        let state = StateWithExtensions::<TokenAccount>::unpack(&account.data).map_err(|e| {
+            UnmintError::InvalidAccountData {
+                address,
+                reason: e.to_string(),
+            }
+        })?;
+
+        let withheld_amount = state
+            .get_extension::<TransferFeeAmount>()
+            .map(|fee| u64::from(fee.withheld_amount))
+            .unwrap_or_default();
+
+        Ok(Self {
+            address,
+            token_program,
+            lamports: account.lamports,
+            account: state.base,
+            withheld_amount,
+        })
+    }
+
+    /// Lamports that closing the account returns as rent
+    ///
+    /// For wrapped SOL `is_native` holds the rent-exempt reserve and `amount`
+    /// the wrapped lamports, which are not rent.
+    pub fn rent(&self) -> u64 {
+        if self.account.is_native() {
+            self.lamports.saturating_sub(self.account.amount)
+        } else {
+            self.lamports
+        }
+    }
+}
+
+impl Unmint {
+    /// Lists every token account owned by `owner` under both token programs
+    pub fn token_accounts<'a, A>(&self, owner: A) -> Result<Vec<OwnedTokenAccount>>
+    where
+        A: Into<PubkeyInput<'a>>,
+    {
+        let owner_pubkey = owner.into().to_pubkey()?;
+        let mut accounts = vec![];
+
+        for token_program in [TokenProgram::Legacy, TokenProgram::Token2022] {
+            let config = RpcProgramAccountsConfig {
+                filters: Some(vec![
+                    RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                        TOKEN_ACCOUNT_OWNER_OFFSET,
+                        owner_pubkey.to_bytes().to_vec(),
+                    )),
+                    RpcFilterType::TokenAccountState,
+                ]),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    commitment: Some(self.client.commitment()),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            };
+
+            let found = self
+                .client
+                .get_program_accounts_with_config(&token_program.program_id(), config)?;
+
+            for (address, account) in found {
+                accounts.push(OwnedTokenAccount::decode(address, &account)?);
+            }
+        }
+
+        Ok(accounts)
+    }
+
+    /// Fetches `addresses` with `getMultipleAccounts`, in request-sized chunks
+    pub(crate) fn multiple_accounts(&self, addresses: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        let mut accounts = Vec::with_capacity(addresses.len());
+
+        for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            accounts.extend(self.client.get_multiple_accounts(chunk)?);
+        }
+
+        Ok(accounts)
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_client::rpc_config::{
+    RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig,
+};
+use solana_sdk::{account::Account, instruction::Instruction, pubkey::Pubkey, signer::Signer};
+
+use crate::{
+    amount::{AmountInput, SOL_DECIMALS, TokenAmount},
+    error::{Result, UnmintError, parse_pubkey},
+    instructions::{self, Signers},
+    scan::OwnedTokenAccount,
+    transfer_fee::FeeMode,
+    unmint::Unmint,
+};
+
+/// Token balance of an account before and after a simulated transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenBalanceChange {
+    pub mint: Pubkey,
+    pub before: TokenAmount,
+    pub after: TokenAmount,
+}
+
+/// Balances of one writable account before and after a simulated transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalanceChange {
+    pub address: Pubkey,
+    pub lamports_before: u64,
+    /// Zero when the transaction closes the account
+    pub lamports_after: u64,
+    /// Set when the account is a token account before or after the transaction
+    pub token: Option<TokenBalanceChange>,
+}
+
+/// Outcome of running an operation with `simulateTransaction`
+#[derive(Debug)]
+pub struct Simulation {
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+    /// Every account the transaction may write, in message order
+    pub balances: Vec<BalanceChange>,
+    /// Decoded failure, `None` when the transaction would succeed
+    pub error: Option<UnmintError>,
+}
+
+impl Simulation {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Pairs the accounts fetched before the simulation with the ones it returned
+///
+/// `decimals` maps the mints of the token accounts to their decimals. An
+/// account missing from `after` was closed by the transaction.
+pub fn balance_changes(
+    addresses: &[Pubkey],
+    before: &[Option<Account>],
+    after: &[Option<Account>],
+    decimals: &HashMap<Pubkey, u8>,
+) -> Vec<BalanceChange> {
+    let token = |address: &Pubkey, account: &Option<Account>| {
+        account
+            .as_ref()
+            .and_then(|account| OwnedTokenAccount::decode(*address, account).ok())
+            .map(|token| (token.account.mint, token.account.amount))
+    };
+
+    addresses
+        .iter()
+        .zip(before.iter().zip(after))
+        .map(|(address, (before, after))| {
+            let token_before = token(address, before);
+            let token_after = token(address, after);
+
+            let token = token_before.or(token_after).map(|(mint, _)| {
+                let decimals = decimals.get(&mint).copied().unwrap_or_default();
+                let amount = |token: Option<(Pubkey, u64)>| {
+                    TokenAmount::from_raw(
+                        token.map(|(_, amount)| amount).unwrap_or_default(),
+                        decimals,
+                    )
+                };
+                TokenBalanceChange {
+                    mint,
+                    before: amount(token_before),
+                    after: amount(token_after),
+                }
+            });
+
+            BalanceChange {
+                address: *address,
+                lamports_before: before.as_ref().map(|a| a.lamports).unwrap_or_default(),
+                lamports_after: after.as_ref().map(|a| a.lamports).unwrap_or_default(),
+                token,
+            }
+        })
+        .collect()
+}
+
+impl Unmint {
+    /// Simulates `send_token` without sending anything
+    ///
+    /// # Returns
+    /// * `Simulation` with the logs, compute units and balance changes; a
+    ///   failing transfer is reported in `Simulation::error`
+    pub fn simulate_send_token<'a, A>(
+        &self,
+        from_base58_string: &str,
+        to_address: &str,
+        token_mint_address: &str,
+        amount: A,
+        fee_payer_base58_string: Option<&str>,
+        memo: Option<&str>,
+    ) -> Result<Simulation>
+    where
+        A: Into<AmountInput<'a>>,
+    {
+        let to_pubkey = parse_pubkey(to_address)?;
+        let token_mint_pubkey = parse_pubkey(token_mint_address)?;
+        let signers = Signers::parse(from_base58_string, fee_payer_base58_string)?;
+
+        let send = self.send_token_instruction(
+            &signers,
+            &signers.fee_payer.pubkey(),
+            &to_pubkey,
+            &token_mint_pubkey,
+            Some(amount.into()),
+            FeeMode::SendExact,
+            memo,
+        )?;
+
+        self.simulate_instructions(&send.instructions, &signers)
+    }
+
+    /// Simulates `send_max_token` without sending anything
+    pub fn simulate_send_max_token(
+        &self,
+        from_base58_string: &str,
+        to_address: &str,
+        token_mint_address: &str,
+        fee_payer_base58_string: Option<&str>,
+        memo: Option<&str>,
+    ) -> Result<Simulation> {
+        let to_pubkey = parse_pubkey(to_address)?;
+        let token_mint_pubkey = parse_pubkey(token_mint_address)?;
+        let signers = Signers::parse(from_base58_string, fee_payer_base58_string)?;
+
+        let instructions = self.send_max_token_instruction(
+            &signers,
+            &signers.from.pubkey(),
+            &to_pubkey,
+            &token_mint_pubkey,
+            memo,
+        )?;
+
+        self.simulate_instructions(&instructions, &signers)
+    }
+
+    /// Simulates `send_and_close` without sending anything
+    pub fn simulate_send_and_close(
+        &self,
+        from_base58_string: &str,
+        to_address: &str,
+        token_mint_address: &str,
+        fee_payer_base58_string: Option<&str>,
+        memo: Option<&str>,
+    ) -> Result<Simulation> {
+        let to_pubkey = parse_pubkey(to_address)?;
+        let token_mint_pubkey = parse_pubkey(token_mint_address)?;
+        let signers = Signers::parse(from_base58_string, fee_payer_base58_string)?;
+
+        let (instructions, _) =
+            self.send_and_close_instructions(&signers, &to_pubkey, &token_mint_pubkey, memo)?;
+
+        self.simulate_instructions(&instructions, &signers)
+    }
+
+    /// Simulates `transfer_sol` without sending anything
+    pub fn simulate_transfer_sol<'a, A>(
+        &self,
+        from_base58_string: &str,
+        to: &str,
+        amount_sol: A,
+        memo: Option<&str>,
+    ) -> Result<Simulation>
+    where
+        A: Into<AmountInput<'a>>,
+    {
+        let signers = Signers::parse(from_base58_string, None)?;
+        let to_pubkey = parse_pubkey(to)?;
+
+        let instructions = instructions::transfer_sol_instructions(
+            &signers.from.pubkey(),
+            &to_pubkey,
+            amount_sol.into().to_token_amount(SOL_DECIMALS)?.raw(),
+            memo,
+        );
+
+        self.simulate_instructions(&instructions, &signers)
+    }
+
+    /// Signs `instructions` and runs them with `simulateTransaction`
+    ///
+    /// The writable accounts are fetched right before the simulation and
+    /// requested back from it, so both sides come from nearly the same slot.
+    pub(crate) fn simulate_instructions(
+        &self,
+        instructions: &[Instruction],
+        signers: &Signers,
+    ) -> Result<Simulation> {
+        let blockhash = self.client.get_latest_blockhash()?;
+        let message = instructions::build_message(
+            instructions,
+            &signers.fee_payer.pubkey(),
+            &blockhash,
+            self.priority_fee.as_ref(),
+        );
+
+        let addresses: Vec<Pubkey> = message
+            .account_keys
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| message.is_maybe_writable(*i, None))
+            .map(|(_, key)| *key)
+            .collect();
+
+        let before = self.multiple_accounts(&addresses)?;
+
+        let transaction = signers.sign(message)?;
+
+        let result = self
+            .client
+            .simulate_transaction_with_config(
+                &transaction,
+                RpcSimulateTransactionConfig {
+                    sig_verify: false,
+                    replace_recent_blockhash: true,
+                    accounts: Some(RpcSimulateTransactionAccountsConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        addresses: addresses.iter().map(Pubkey::to_string).collect(),
+                    }),
+                    ..RpcSimulateTransactionConfig::default()
+                },
+            )
+            .map_err(|e| UnmintError::from_client_error(e, Some(&transaction.message)))?
+            .value;
+
+        // a failed simulation returns no accounts; nothing changed then
+        let after: Vec<Option<Account>> = match result.accounts {
+            Some(accounts) => accounts
+                .iter()
+                .map(|account| account.as_ref().and_then(|a| a.decode::<Account>()))
+                .collect(),
+            None => before.clone(),
+        };
+
+        let mints: Vec<Pubkey> = before
+            .iter()
+            .chain(&after)
+            .zip(addresses.iter().chain(&addresses))
+            .filter_map(|(account, address)| {
+                OwnedTokenAccount::decode(*address, account.as_ref()?).ok()
+            })
+            .map(|token| token.account.mint)
+            .collect();
+        let decimals = self
+            .mint_infos(&mints)?
+            .into_iter()
+            .map(|(mint, info)| (mint, info.decimals))
+            .collect();
+
+        Ok(Simulation {
+            logs: result.logs.unwrap_or_default(),
+            units_consumed: result.units_consumed,
+            balances: balance_changes(&addresses, &before, &after, &decimals),
+            error: result
+                .err
+                .map(|e| UnmintError::from_transaction_error(e, Some(&transaction.message))),
+        })
+    }
+}
@@ -0,0 +1,379 @@
+use std::fmt;
+
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{
+    account::Account,
+    clock::{Clock, Epoch},
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+    sysvar,
+};
+use solana_stake_interface::{
+    instruction as stake_instruction,
+    program::ID as STAKE_PROGRAM_ID,
+    stake_history::{self, StakeHistory},
+    state::{Lockup, StakeStateV2},
+};
+
+use crate::{
+    error::{Result, UnmintError, parse_pubkey},
+    instructions::{self, Signers},
+    unmint::{PubkeyInput, Unmint},
+};
+
+/// Offset of the withdrawer in a stake account: enum tag, rent-exempt reserve, then staker
+const WITHDRAWER_OFFSET: usize = 44;
+
+/// Where a stake account is in its activation cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StakeActivation {
+    /// Not delegated, or fully cooled down; the balance can be withdrawn
+    Inactive,
+    /// Delegated and warming up
+    Activating,
+    /// Delegated and earning rewards
+    Active,
+    /// Deactivated and cooling down
+    Deactivating,
+}
+
+impl StakeActivation {
+    /// Activation of `state` at `epoch`
+    pub fn of(state: &StakeStateV2, epoch: Epoch, history: &StakeHistory) -> Self {
+        let Some(delegation) = state.delegation() else {
+            return StakeActivation::Inactive;
+        };
+
+        // the warmup rate only changes how fast stake moves, not which phase it is in
+        let status = delegation.stake_activating_and_deactivating(epoch, history, None);
+        if status.deactivating > 0 {
+            StakeActivation::Deactivating
+        } else if status.activating > 0 {
+            StakeActivation::Activating
+        } else if status.effective > 0 {
+            StakeActivation::Active
+        } else {
+            StakeActivation::Inactive
+        }
+    }
+}
+
+impl fmt::Display for StakeActivation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let activation = match self {
+            StakeActivation::Inactive => "inactive",
+            StakeActivation::Activating => "activating",
+            StakeActivation::Active => "active",
+            StakeActivation::Deactivating => "deactivating",
+        };
+        f.write_str(activation)
+    }
+}
+
+/// A stake account found by `Unmint::stake_accounts`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StakeAccount {
+    pub address: Pubkey,
+    /// Lamports held by the account, including rent
+    pub lamports: u64,
+    /// Authority allowed to delegate and deactivate
+    pub staker: Pubkey,
+    /// Authority allowed to withdraw
+    pub withdrawer: Pubkey,
+    /// Vote account the stake is delegated to
+    pub voter: Option<Pubkey>,
+    pub activation: StakeActivation,
+    /// Lockup still in force; the account cannot be withdrawn until it ends
+    pub lockup: Option<Lockup>,
+}
+
+impl StakeAccount {
+    /// Decodes a fetched stake account, as of `clock`
+    pub fn decode(
+        address: Pubkey,
+        account: &Account,
+        clock: &Clock,
+        history: &StakeHistory,
+    ) -> Result<Self> {
+        if account.owner != STAKE_PROGRAM_ID {
+            return Err(UnmintError::WrongAccountType {
+                address,
+                expected: "stake account",
+            });
+        }
+
+        let state: StakeStateV2 =
+            bincode::deserialize(&account.data).map_err(|e| UnmintError::InvalidAccountData {
+                address,
+                reason: e.to_string(),
+            })?;
+        let authorized = state.authorized().ok_or(UnmintError::WrongAccountType {
+            address,
+            expected: "initialized stake account",
+        })?;
+
+        Ok(Self {
+            address,
+            lamports: account.lamports,
+            staker: authorized.staker,
+            withdrawer: authorized.withdrawer,
+            voter: state.delegation().map(|delegation| delegation.voter_pubkey),
+            activation: StakeActivation::of(&state, clock.epoch, history),
+            // withdrawals are not signed by the custodian, so any lockup applies
+            lockup: state
+                .lockup()
+                .filter(|lockup| lockup.is_in_force(clock, None)),
+        })
+    }
+}
+
+/// Everything `reclaim_stake` did
+#[derive(Debug, Clone, Default)]
+pub struct StakeReclaim {
+    /// Inactive accounts withdrawn in full and closed
+    pub withdrawn: Vec<StakeAccount>,
+    /// Active or activating accounts deactivated; withdraw them once they cool down
+    pub deactivated: Vec<StakeAccount>,
+    /// Accounts left alone: still cooling down, locked, or staked by another authority
+    pub waiting: Vec<StakeAccount>,
+    /// Lamports sent to the destination
+    pub lamports_withdrawn: u64,
+    pub signatures: Vec<Signature>,
+}
+
+impl Unmint {
+    /// Lists the stake accounts whose withdraw authority is `withdrawer`
+    ///
+    /// Scans the stake program with `getProgramAccounts`, so it needs an RPC
+    /// node that serves unrestricted program scans.
+    pub fn stake_accounts<'a, A>(&self, withdrawer: A) -> Result<Vec<StakeAccount>>
+    where
+        A: Into<PubkeyInput<'a>>,
+    {
+        let withdrawer_pubkey = withdrawer.into().to_pubkey()?;
+
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                WITHDRAWER_OFFSET,
+                withdrawer_pubkey.to_bytes().to_vec(),
+            ))]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(self.client.commitment()),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+
+        let found = self
+            .client
+            .get_program_accounts_with_config(&STAKE_PROGRAM_ID, config)?;
+        let (clock, history) = self.stake_clock()?;
+
+        found
+            .into_iter()
+            .map(|(address, account)| StakeAccount::decode(address, &account, &clock, &history))
+            .collect()
+    }
+
+    /// Deactivates a delegated stake account; it can be withdrawn once it cools down
+    ///
+    /// # Arguments
+    /// * `stake_address` - Stake account to deactivate
+    /// * `staker_base58_string` - Base58 private key of the stake authority
+    /// * `fee_payer_base58_string` - Optional, who pays the fee
+    pub fn deactivate_stake(
+        &self,
+        stake_address: &str,
+        staker_base58_string: &str,
+        fee_payer_base58_string: Option<&str>,
+    ) -> Result<Signature> {
+        let stake_pubkey = parse_pubkey(stake_address)?;
+        let signers = Signers::parse(staker_base58_string, fee_payer_base58_string)?;
+
+        let stake = self.stake_account(&stake_pubkey)?;
+        if stake.staker != signers.from.pubkey() {
+            return Err(UnmintError::WrongAuthority {
+                address: stake_pubkey,
+                expected: Some(stake.staker),
+                found: signers.from.pubkey(),
+            });
+        }
+        if !matches!(
+            stake.activation,
+            StakeActivation::Active | StakeActivation::Activating
+        ) {
+            return Err(UnmintError::WrongAccountType {
+                address: stake_pubkey,
+                expected: "delegated stake account",
+            });
+        }
+
+        let deactivate_ix = stake_instruction::deactivate_stake(&stake_pubkey, &stake.staker);
+        self.send_instructions(&[deactivate_ix], &signers)
+    }
+
+    /// Withdraws the whole balance of an inactive stake account, closing it
+    ///
+    /// Fails with `StakeLocked` while a lockup is in force.
+    ///
+    /// # Arguments
+    /// * `stake_address` - Stake account to empty
+    /// * `withdrawer_base58_string` - Base58 private key of the withdraw authority
+    /// * `destination` - Receives the lamports
+    /// * `fee_payer_base58_string` - Optional, who pays the fee
+    pub fn withdraw_stake(
+        &self,
+        stake_address: &str,
+        withdrawer_base58_string: &str,
+        destination: &str,
+        fee_payer_base58_string: Option<&str>,
+    ) -> Result<Signature> {
+        let stake_pubkey = parse_pubkey(stake_address)?;
+        let signers = Signers::parse(withdrawer_base58_string, fee_payer_base58_string)?;
+        let destination = parse_pubkey(destination)?;
+
+        let stake = self.stake_account(&stake_pubkey)?;
+        if stake.withdrawer != signers.from.pubkey() {
+            return Err(UnmintError::WrongAuthority {
+                address: stake_pubkey,
+                expected: Some(stake.withdrawer),
+                found: signers.from.pubkey(),
+            });
+        }
+        if stake.activation != StakeActivation::Inactive {
+            return Err(UnmintError::StakeNotInactive {
+                address: stake_pubkey,
+                activation: stake.activation,
+            });
+        }
+        if let Some(lockup) = stake.lockup {
+            return Err(UnmintError::StakeLocked {
+                address: stake_pubkey,
+                lockup,
+            });
+        }
+
+        if let Some(policy) = &self.policy {
+            policy.check(&instructions::sol_transfer_plan(
+                &signers.fee_payer.pubkey(),
+                &signers.from.pubkey(),
+                &destination,
+                [stake.lamports],
+            ))?;
+        }
+
+        let withdraw_ix = withdraw_ix(&stake, &destination);
+        self.send_instructions(&[withdraw_ix], &signers)
+    }
+
+    /// Withdraws every inactive stake account of the wallet to `destination`
+    /// and deactivates the delegated ones it stakes
+    ///
+    /// Deactivated accounts need one epoch to cool down; run again afterwards
+    /// to withdraw them. Accounts under a lockup are left in `waiting`.
+    ///
+    /// # Arguments
+    /// * `wallet_base58_string` - Base58 private key of the withdraw authority
+    /// * `destination` - Receives the lamports
+    /// * `fee_payer_base58_string` - Optional, who pays the fees
+    pub fn reclaim_stake(
+        &self,
+        wallet_base58_string: &str,
+        destination: &str,
+        fee_payer_base58_string: Option<&str>,
+    ) -> Result<StakeReclaim> {
+        let signers = Signers::parse(wallet_base58_string, fee_payer_base58_string)?;
+        let destination = parse_pubkey(destination)?;
+        let wallet = signers.from.pubkey();
+        let mut reclaim = StakeReclaim::default();
+        let mut groups: Vec<Vec<Instruction>> = vec![];
+        for stake in self.stake_accounts(&wallet)? {
+            match stake.activation {
+                StakeActivation::Inactive if stake.lockup.is_none() => {
+                    groups.push(vec![withdraw_ix(&stake, &destination)]);
+                    reclaim.lamports_withdrawn += stake.lamports;
+                    reclaim.withdrawn.push(stake);
+                }
+                StakeActivation::Active | StakeActivation::Activating if stake.staker == wallet => {
+                    groups.push(vec![stake_instruction::deactivate_stake(
+                        &stake.address,
+                        &wallet,
+                    )]);
+                    reclaim.deactivated.push(stake);
+                }
+                _ => reclaim.waiting.push(stake),
+            }
+        }
+
+        if let Some(policy) = &self.policy {
+            policy.check(&instructions::sol_transfer_plan(
+                &signers.fee_payer.pubkey(),
+                &wallet,
+                &destination,
+                reclaim.withdrawn.iter().map(|stake| stake.lamports),
+            ))?;
+        }
+
+        let batches = instructions::pack_groups(
+            &groups,
+            &signers.fee_payer.pubkey(),
+            self.priority_fee.as_ref(),
+        )?;
+        for batch in batches {
+            let batch_instructions: Vec<Instruction> =
+                batch.iter().flat_map(|&i| groups[i].clone()).collect();
+            reclaim
+                .signatures
+                .push(self.send_instructions(&batch_instructions, &signers)?);
+        }
+
+        Ok(reclaim)
+    }
+
+    /// Fetches and decodes a single stake account
+    fn stake_account(&self, address: &Pubkey) -> Result<StakeAccount> {
+        let account = self
+            .fetch_account(address)?
+            .ok_or(UnmintError::AccountNotFound(*address))?;
+        let (clock, history) = self.stake_clock()?;
+        StakeAccount::decode(*address, &account, &clock, &history)
+    }
+
+    /// Clock and stake history, needed to tell the activation and lockup of stake
+    fn stake_clock(&self) -> Result<(Clock, StakeHistory)> {
+        let account = self
+            .fetch_account(&sysvar::clock::ID)?
+            .ok_or(UnmintError::AccountNotFound(sysvar::clock::ID))?;
+        let clock =
+            bincode::deserialize(&account.data).map_err(|e| UnmintError::InvalidAccountData {
+                address: sysvar::clock::ID,
+                reason: e.to_string(),
+            })?;
+
+        let account = self
+            .fetch_account(&stake_history::ID)?
+            .ok_or(UnmintError::AccountNotFound(stake_history::ID))?;
+        let history =
+            bincode::deserialize(&account.data).map_err(|e| UnmintError::InvalidAccountData {
+                address: stake_history::ID,
+                reason: e.to_string(),
+            })?;
+        Ok((clock, history))
+    }
+}
+
+fn withdraw_ix(stake: &StakeAccount, destination: &Pubkey) -> Instruction {
+    stake_instruction::withdraw(
+        &stake.address,
+        &stake.withdrawer,
+        destination,
+        stake.lamports,
+        None,
+    )
+}
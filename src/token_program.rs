@@ -1,14 +1,34 @@
-use anyhow::Result;
+use std::future::Future;
+
+use anyhow::{Result, anyhow};
 use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
 use spl_associated_token_account::{
     get_associated_token_address, get_associated_token_address_with_program_id,
-    instruction::create_associated_token_account,
+    instruction::{create_associated_token_account, create_associated_token_account_idempotent},
 };
 use spl_token::ID as TOKEN_PROGRAM_ID;
-use spl_token_2022::ID as TOKEN_2022_PROGRAM_ID;
+use spl_token_2022::{
+    ID as TOKEN_2022_PROGRAM_ID,
+    offchain::{AccountDataResult, add_extra_account_metas},
+};
+
+/// Authority `set_authority_ix` hands over or renounces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthorityKind {
+    /// Mint authority of a mint
+    MintTokens,
+    /// Freeze authority of a mint
+    FreezeAccount,
+    /// Owner of a token account
+    AccountOwner,
+    /// Close authority of a token account
+    CloseAccount,
+    /// MintCloseAuthority of a Token-2022 mint
+    CloseMint,
+}
 
 /// Enum to select the SPL token program version
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum TokenProgram {
     /// Standard SPL token
     Legacy,
@@ -31,6 +51,20 @@ impl TokenProgram {
         }
     }
 
+    /// Returns the `TokenProgram` owning accounts with `program_id` as owner
+    pub fn from_program_id(program_id: &Pubkey) -> Option<Self> {
+        match *program_id {
+            TOKEN_PROGRAM_ID => Some(TokenProgram::Legacy),
+            TOKEN_2022_PROGRAM_ID => Some(TokenProgram::Token2022),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if `program_id` is either SPL token program
+    pub fn is_token_program(program_id: &Pubkey) -> bool {
+        *program_id == TOKEN_PROGRAM_ID || *program_id == TOKEN_2022_PROGRAM_ID
+    }
+
     /// Returns the associated token account (ATA) for a given owner and mint
     ///
     /// # Arguments
@@ -65,6 +99,29 @@ impl TokenProgram {
         }
     }
 
+    /// Creates the ATA of `owner` for `mint` unless it already exists
+    ///
+    /// Unlike `create_ata_instraction` it does not fail when the account was
+    /// created in the meantime, so it is safe to include without checking first.
+    ///
+    /// # Arguments
+    /// * `payer` - Pays the rent of the new account
+    /// * `owner` - Wallet the ATA belongs to
+    /// * `mint` - Pubkey of the token mint
+    ///
+    /// # Example
+    /// ```ignore
+    /// let create_ix = prog.create_ata_idempotent_ix(&payer, &owner_pubkey, &mint_pubkey);
+    /// ```
+    pub fn create_ata_idempotent_ix(
+        &self,
+        payer: &Pubkey,
+        owner: &Pubkey,
+        mint: &Pubkey,
+    ) -> Instruction {
+        create_associated_token_account_idempotent(payer, owner, mint, &self.program_id())
+    }
+
     /// Creates a transfer instruction for SPL tokens
     ///
     /// # Arguments
@@ -110,6 +167,104 @@ impl TokenProgram {
         }
     }
 
+    /// Creates a Token2022 `transfer_checked_with_fee` instruction
+    ///
+    /// The transfer fails if `fee` does not match the fee the mint charges on
+    /// `amount`. Legacy mints have no transfer fees.
+    ///
+    /// # Arguments
+    /// * `fee` - Expected transfer fee in the smallest unit
+    ///
+    /// # Example
+    /// ```ignore
+    /// let ix = prog.transfer_with_fee_ix(&ata_from, &ata_to, &payer, 1_000_000, 6, &mint_pubkey, 5_000)?;
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub fn transfer_with_fee_ix(
+        &self,
+        from: &Pubkey,
+        to: &Pubkey,
+        authority: &Keypair,
+        amount: u64,
+        decimals: u8,
+        mint: &Pubkey,
+        fee: u64,
+    ) -> Result<Instruction> {
+        match self {
+            TokenProgram::Legacy => Err(anyhow!("legacy token mints have no transfer fee")),
+            TokenProgram::Token2022 => Ok(
+                spl_token_2022::extension::transfer_fee::instruction::transfer_checked_with_fee(
+                    &TOKEN_2022_PROGRAM_ID,
+                    from,
+                    mint,
+                    to,
+                    &authority.pubkey(),
+                    &[&authority.pubkey()],
+                    amount,
+                    decimals,
+                    fee,
+                )?,
+            ),
+        }
+    }
+
+    /// Creates a transfer instruction like `transfer_ix`, with the extra accounts
+    /// required by the mint's transfer hook appended
+    ///
+    /// For Token2022 the mint is read through `fetch_account_data_fn`; when it has
+    /// the TransferHook extension, the ExtraAccountMetaList PDA of the hook program
+    /// is resolved and its accounts are appended. Legacy transfers need no extra accounts.
+    ///
+    /// # Arguments
+    /// * `fee` - Expected transfer fee; when set, `transfer_with_fee_ix` is used
+    /// * `fetch_account_data_fn` - Returns the data of an account, or `None` if it does not exist
+    ///
+    /// # Example
+    /// ```ignore
+    /// let ix = prog
+    ///     .transfer_ix_with_extra_metas(&ata_from, &ata_to, &payer, 1_000_000, 6, &mint_pubkey, None, fetch)
+    ///     .await?;
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub async fn transfer_ix_with_extra_metas<F, Fut>(
+        &self,
+        from: &Pubkey,
+        to: &Pubkey,
+        authority: &Keypair,
+        amount: u64,
+        decimals: u8,
+        mint: &Pubkey,
+        fee: Option<u64>,
+        fetch_account_data_fn: F,
+    ) -> Result<Instruction>
+    where
+        F: Fn(Pubkey) -> Fut,
+        Fut: Future<Output = AccountDataResult>,
+    {
+        let mut instruction = match fee {
+            Some(fee) => {
+                self.transfer_with_fee_ix(from, to, authority, amount, decimals, mint, fee)?
+            }
+            None => self.transfer_ix(from, to, authority, amount, decimals, mint)?,
+        };
+
+        if let TokenProgram::Token2022 = self {
+            add_extra_account_metas(
+                &mut instruction,
+                from,
+                mint,
+                to,
+                &authority.pubkey(),
+                amount,
+                fetch_account_data_fn,
+            )
+            .await
+            .map_err(|e| anyhow!("failed to resolve transfer hook accounts: {}", e))?;
+        }
+
+        Ok(instruction)
+    }
+
     /// Creates an instruction to close an associated token account (ATA)
     ///
     /// # Arguments
@@ -144,4 +299,278 @@ impl TokenProgram {
             )?),
         }
     }
+
+    /// Creates a burn instruction for SPL tokens
+    ///
+    /// # Arguments
+    /// * `account` - Token account to burn from
+    /// * `mint` - Mint Pubkey
+    /// * `authority` - Keypair of the account authority
+    /// * `amount` - Token amount in the smallest unit
+    /// * `decimals` - Mint decimals
+    ///
+    /// # Example
+    /// ```ignore
+    /// let burn_ix = prog.burn_ix(&ata, &mint_pubkey, &payer, 1_000_000, 6)?;
+    /// ```
+    pub fn burn_ix(
+        &self,
+        account: &Pubkey,
+        mint: &Pubkey,
+        authority: &Keypair,
+        amount: u64,
+        decimals: u8,
+    ) -> Result<Instruction> {
+        match self {
+            TokenProgram::Legacy => Ok(spl_token::instruction::burn_checked(
+                &TOKEN_PROGRAM_ID,
+                account,
+                mint,
+                &authority.pubkey(),
+                &[&authority.pubkey()],
+                amount,
+                decimals,
+            )?),
+            TokenProgram::Token2022 => Ok(spl_token_2022::instruction::burn_checked(
+                &TOKEN_2022_PROGRAM_ID,
+                account,
+                mint,
+                &authority.pubkey(),
+                &[&authority.pubkey()],
+                amount,
+                decimals,
+            )?),
+        }
+    }
+
+    /// Creates an `InitializeMint2` instruction for a freshly allocated mint account
+    ///
+    /// # Example
+    /// ```ignore
+    /// let init_ix = prog.initialize_mint_ix(&mint_pubkey, &authority, None, 6)?;
+    /// ```
+    pub fn initialize_mint_ix(
+        &self,
+        mint: &Pubkey,
+        mint_authority: &Pubkey,
+        freeze_authority: Option<&Pubkey>,
+        decimals: u8,
+    ) -> Result<Instruction> {
+        match self {
+            TokenProgram::Legacy => Ok(spl_token::instruction::initialize_mint2(
+                &TOKEN_PROGRAM_ID,
+                mint,
+                mint_authority,
+                freeze_authority,
+                decimals,
+            )?),
+            TokenProgram::Token2022 => Ok(spl_token_2022::instruction::initialize_mint2(
+                &TOKEN_2022_PROGRAM_ID,
+                mint,
+                mint_authority,
+                freeze_authority,
+                decimals,
+            )?),
+        }
+    }
+
+    /// Creates a checked mint-to instruction
+    ///
+    /// # Example
+    /// ```ignore
+    /// let mint_ix = prog.mint_to_ix(&mint_pubkey, &ata, &authority, 1_000_000, 6)?;
+    /// ```
+    pub fn mint_to_ix(
+        &self,
+        mint: &Pubkey,
+        account: &Pubkey,
+        authority: &Keypair,
+        amount: u64,
+        decimals: u8,
+    ) -> Result<Instruction> {
+        match self {
+            TokenProgram::Legacy => Ok(spl_token::instruction::mint_to_checked(
+                &TOKEN_PROGRAM_ID,
+                mint,
+                account,
+                &authority.pubkey(),
+                &[&authority.pubkey()],
+                amount,
+                decimals,
+            )?),
+            TokenProgram::Token2022 => Ok(spl_token_2022::instruction::mint_to_checked(
+                &TOKEN_2022_PROGRAM_ID,
+                mint,
+                account,
+                &authority.pubkey(),
+                &[&authority.pubkey()],
+                amount,
+                decimals,
+            )?),
+        }
+    }
+
+    /// Creates an instruction freezing `account`, signed by the mint freeze authority
+    pub fn freeze_ix(
+        &self,
+        account: &Pubkey,
+        mint: &Pubkey,
+        authority: &Keypair,
+    ) -> Result<Instruction> {
+        match self {
+            TokenProgram::Legacy => Ok(spl_token::instruction::freeze_account(
+                &TOKEN_PROGRAM_ID,
+                account,
+                mint,
+                &authority.pubkey(),
+                &[&authority.pubkey()],
+            )?),
+            TokenProgram::Token2022 => Ok(spl_token_2022::instruction::freeze_account(
+                &TOKEN_2022_PROGRAM_ID,
+                account,
+                mint,
+                &authority.pubkey(),
+                &[&authority.pubkey()],
+            )?),
+        }
+    }
+
+    /// Creates an instruction thawing `account`, signed by the mint freeze authority
+    pub fn thaw_ix(
+        &self,
+        account: &Pubkey,
+        mint: &Pubkey,
+        authority: &Keypair,
+    ) -> Result<Instruction> {
+        match self {
+            TokenProgram::Legacy => Ok(spl_token::instruction::thaw_account(
+                &TOKEN_PROGRAM_ID,
+                account,
+                mint,
+                &authority.pubkey(),
+                &[&authority.pubkey()],
+            )?),
+            TokenProgram::Token2022 => Ok(spl_token_2022::instruction::thaw_account(
+                &TOKEN_2022_PROGRAM_ID,
+                account,
+                mint,
+                &authority.pubkey(),
+                &[&authority.pubkey()],
+            )?),
+        }
+    }
+
+    /// Creates an instruction handing `kind` of `owned` to `new_authority`;
+    /// `None` renounces it for good
+    ///
+    /// # Example
+    /// ```ignore
+    /// let renounce_ix = prog.set_authority_ix(&mint_pubkey, None, AuthorityKind::MintTokens, &authority)?;
+    /// ```
+    pub fn set_authority_ix(
+        &self,
+        owned: &Pubkey,
+        new_authority: Option<&Pubkey>,
+        kind: AuthorityKind,
+        current_authority: &Keypair,
+    ) -> Result<Instruction> {
+        match self {
+            TokenProgram::Legacy => {
+                use spl_token::instruction::AuthorityType;
+                let authority_type = match kind {
+                    AuthorityKind::MintTokens => AuthorityType::MintTokens,
+                    AuthorityKind::FreezeAccount => AuthorityType::FreezeAccount,
+                    AuthorityKind::AccountOwner => AuthorityType::AccountOwner,
+                    AuthorityKind::CloseAccount => AuthorityType::CloseAccount,
+                    AuthorityKind::CloseMint => {
+                        return Err(anyhow!("legacy mints have no close authority"));
+                    }
+                };
+                Ok(spl_token::instruction::set_authority(
+                    &TOKEN_PROGRAM_ID,
+                    owned,
+                    new_authority,
+                    authority_type,
+                    &current_authority.pubkey(),
+                    &[&current_authority.pubkey()],
+                )?)
+            }
+            TokenProgram::Token2022 => {
+                use spl_token_2022::instruction::AuthorityType;
+                let authority_type = match kind {
+                    AuthorityKind::MintTokens => AuthorityType::MintTokens,
+                    AuthorityKind::FreezeAccount => AuthorityType::FreezeAccount,
+                    AuthorityKind::AccountOwner => AuthorityType::AccountOwner,
+                    AuthorityKind::CloseAccount => AuthorityType::CloseAccount,
+                    AuthorityKind::CloseMint => AuthorityType::CloseMint,
+                };
+                Ok(spl_token_2022::instruction::set_authority(
+                    &TOKEN_2022_PROGRAM_ID,
+                    owned,
+                    new_authority,
+                    authority_type,
+                    &current_authority.pubkey(),
+                    &[&current_authority.pubkey()],
+                )?)
+            }
+        }
+    }
+
+    /// Creates a checked approve instruction letting `delegate` move up to
+    /// `amount` out of `account`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let approve_ix = prog.approve_checked_ix(&ata, &mint_pubkey, &delegate, &owner, 1_000_000, 6)?;
+    /// ```
+    pub fn approve_checked_ix(
+        &self,
+        account: &Pubkey,
+        mint: &Pubkey,
+        delegate: &Pubkey,
+        owner: &Keypair,
+        amount: u64,
+        decimals: u8,
+    ) -> Result<Instruction> {
+        match self {
+            TokenProgram::Legacy => Ok(spl_token::instruction::approve_checked(
+                &TOKEN_PROGRAM_ID,
+                account,
+                mint,
+                delegate,
+                &owner.pubkey(),
+                &[&owner.pubkey()],
+                amount,
+                decimals,
+            )?),
+            TokenProgram::Token2022 => Ok(spl_token_2022::instruction::approve_checked(
+                &TOKEN_2022_PROGRAM_ID,
+                account,
+                mint,
+                delegate,
+                &owner.pubkey(),
+                &[&owner.pubkey()],
+                amount,
+                decimals,
+            )?),
+        }
+    }
+
+    /// Creates an instruction removing the delegate of `account`
+    pub fn revoke_ix(&self, account: &Pubkey, owner: &Keypair) -> Result<Instruction> {
+        match self {
+            TokenProgram::Legacy => Ok(spl_token::instruction::revoke(
+                &TOKEN_PROGRAM_ID,
+                account,
+                &owner.pubkey(),
+                &[&owner.pubkey()],
+            )?),
+            TokenProgram::Token2022 => Ok(spl_token_2022::instruction::revoke(
+                &TOKEN_2022_PROGRAM_ID,
+                account,
+                &owner.pubkey(),
+                &[&owner.pubkey()],
+            )?),
+        }
+    }
 }
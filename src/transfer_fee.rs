@@ -0,0 +1,105 @@
+use solana_sdk::signature::Signature;
+use spl_token_2022::{
+    extension::{BaseStateWithExtensions, StateWithExtensions, transfer_fee::TransferFeeConfig},
+    state::Mint,
+};
+
+use crate::{
+    amount::TokenAmount,
+    error::{Result, UnmintError},
+};
+
+/// Which side of a transfer the Token-2022 transfer fee is taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FeeMode {
+    /// The sender is debited exactly the requested amount and the recipient
+    /// receives it minus the fee
+    #[default]
+    SendExact,
+    /// The recipient receives exactly the requested amount and the sender is
+    /// debited the fee on top
+    ReceiveExact,
+}
+
+/// How a transfer amount splits into the fee and what the recipient receives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeQuote {
+    /// Debited from the sender
+    pub sent: u64,
+    /// Withheld by the mint, `None` when the mint has no transfer fee
+    pub fee: Option<u64>,
+}
+
+impl FeeQuote {
+    /// Quote for a mint without the TransferFeeConfig extension
+    pub fn without_fee(amount: u64) -> Self {
+        Self {
+            sent: amount,
+            fee: None,
+        }
+    }
+
+    /// Computes the fee charged on a transfer of `mint_data` during `epoch`
+    ///
+    /// `amount` is what the sender pays with `FeeMode::SendExact` and what the
+    /// recipient receives with `FeeMode::ReceiveExact`.
+    pub fn new(mint_data: &[u8], epoch: u64, amount: u64, mode: FeeMode) -> Result<Self> {
+        let mint = StateWithExtensions::<Mint>::unpack(mint_data)
+            .map_err(|e| anyhow::anyhow!("invalid mint: {}", e))?;
+
+        match mint.get_extension::<TransferFeeConfig>() {
+            Ok(config) => Self::with_config(config, epoch, amount, mode),
+            Err(_) => Ok(Self::without_fee(amount)),
+        }
+    }
+
+    /// Computes the fee charged by `config` during `epoch`, see `FeeQuote::new`
+    pub fn with_config(
+        config: &TransferFeeConfig,
+        epoch: u64,
+        amount: u64,
+        mode: FeeMode,
+    ) -> Result<Self> {
+        let transfer_fee = config.get_epoch_fee(epoch);
+        let overflow = || UnmintError::InvalidAmount(format!("{}: fee overflows u64", amount));
+
+        let sent = match mode {
+            FeeMode::SendExact => amount,
+            FeeMode::ReceiveExact => transfer_fee
+                .calculate_pre_fee_amount(amount)
+                .ok_or_else(overflow)?,
+        };
+        let fee = transfer_fee.calculate_fee(sent).ok_or_else(overflow)?;
+
+        Ok(Self {
+            sent,
+            fee: Some(fee),
+        })
+    }
+
+    /// Credited to the recipient
+    pub fn received(&self) -> u64 {
+        self.sent - self.fee.unwrap_or_default()
+    }
+
+    pub(crate) fn receipt(&self, signature: Signature, decimals: u8) -> TokenTransfer {
+        TokenTransfer {
+            signature,
+            sent: TokenAmount::from_raw(self.sent, decimals),
+            fee: TokenAmount::from_raw(self.fee.unwrap_or_default(), decimals),
+            received: TokenAmount::from_raw(self.received(), decimals),
+        }
+    }
+}
+
+/// Result of `Unmint::send_token_with_fee`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenTransfer {
+    pub signature: Signature,
+    /// Debited from the sender
+    pub sent: TokenAmount,
+    /// Withheld by the mint's transfer fee
+    pub fee: TokenAmount,
+    /// Credited to the recipient
+    pub received: TokenAmount,
+}
@@ -1,16 +1,28 @@
-use std::{str::FromStr, sync::Arc};
-
-use crate::token_program::TokenProgram;
-use anyhow::{Ok, Result, anyhow};
+use std::{sync::Arc, thread, time::Duration};
+
+use crate::{
+    amount::{AmountInput, SOL_DECIMALS, TokenAmount},
+    error::{Result, UnmintError, parse_pubkey},
+    instructions::{
+        self, BuiltSend, CONFIRM_POLL_INTERVAL, ConfirmAnswer, ConfirmQuery, Confirmation,
+        DrainMode, PriorityFee, Signers, SolDrain, TokenSend,
+    },
+    mint::{MintCache, MintInfo},
+    plan::Plan,
+    policy::Policy,
+    scan::OwnedTokenAccount,
+    token_program::TokenProgram,
+    transfer_fee::{FeeMode, TokenTransfer},
+};
 use solana_sdk::{
-    instruction::Instruction, signature::Signature, system_instruction, transaction::Transaction,
+    account::Account, instruction::Instruction, message::Message, signature::Signature,
+    signer::keypair::Keypair, transaction::Transaction,
 };
+use spl_token_2022::state::Account as TokenAccount;
 
 use {
     solana_client::rpc_client::RpcClient,
-    solana_sdk::{
-        commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Keypair, signer::Signer,
-    },
+    solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signer::Signer},
 };
 
 pub enum PubkeyInput<'a> {
@@ -31,10 +43,10 @@ impl<'a> From<&'a str> for PubkeyInput<'a> {
 }
 
 impl<'a> PubkeyInput<'a> {
-    pub fn to_pubkey(&self) -> Result<Pubkey, anyhow::Error> {
+    pub fn to_pubkey(&self) -> Result<Pubkey> {
         match self {
-            PubkeyInput::Key(k) => Ok((*k).clone()),
-            PubkeyInput::Str(s) => Ok(Pubkey::from_str(s)?),
+            PubkeyInput::Key(k) => Ok(**k),
+            PubkeyInput::Str(s) => parse_pubkey(s),
         }
     }
 }
@@ -47,10 +59,48 @@ pub struct UiTokenAmount {
     pub ui_amount_string: String,
 }
 
+impl From<solana_account_decoder_client_types::token::UiTokenAmount> for UiTokenAmount {
+    fn from(balances: solana_account_decoder_client_types::token::UiTokenAmount) -> Self {
+        UiTokenAmount {
+            ui_amount: balances.ui_amount,
+            decimals: balances.decimals,
+            amount: balances.amount,
+            ui_amount_string: balances.ui_amount_string,
+        }
+    }
+}
+
+impl From<TokenAmount> for UiTokenAmount {
+    fn from(amount: TokenAmount) -> Self {
+        let ui_amount_string = amount.to_ui_string();
+        UiTokenAmount {
+            ui_amount: ui_amount_string.parse().ok(),
+            decimals: amount.decimals(),
+            amount: amount.raw().to_string(),
+            ui_amount_string,
+        }
+    }
+}
+
+impl UiTokenAmount {
+    /// Returns the exact balance as a `TokenAmount`
+    pub fn token_amount(&self) -> Result<TokenAmount> {
+        let raw = self
+            .amount
+            .parse::<u64>()
+            .map_err(|_| UnmintError::InvalidAmount(self.amount.clone()))?;
+        Ok(TokenAmount::from_raw(raw, self.decimals))
+    }
+}
+
 #[derive(Clone)]
 pub struct Unmint {
-    client: Arc<RpcClient>,
-    token_program: TokenProgram,
+    pub(crate) client: Arc<RpcClient>,
+    pub(crate) token_program: TokenProgram,
+    pub(crate) priority_fee: Option<PriorityFee>,
+    pub(crate) mints: Arc<MintCache>,
+    pub(crate) allow_owner_off_curve: bool,
+    pub(crate) policy: Option<Arc<Policy>>,
 }
 
 /// Create a new instance of Unmint
@@ -69,102 +119,252 @@ impl Unmint {
         Self {
             client: client.into(),
             token_program,
+            priority_fee: None,
+            mints: Arc::default(),
+            allow_owner_off_curve: false,
+            policy: None,
         }
     }
 
-    fn close_token_account_instruction(
-        &self,
-        from_base58_string: &str,
-        token_mint_address: &str,
-        address_reedem_sol: Option<&Pubkey>,
-    ) -> Result<Instruction> {
-        let from_keypair = Keypair::from_base58_string(from_base58_string);
-        let token_mint_pubkey = Pubkey::from_str(token_mint_address)?;
-        let address = *address_reedem_sol.unwrap_or(&from_keypair.pubkey());
+    /// Prepends compute unit limit and price instructions to every transaction
+    pub fn with_priority_fee(mut self, priority_fee: PriorityFee) -> Self {
+        self.priority_fee = Some(priority_fee);
+        self
+    }
 
-        let ata_sender = self
-            .token_program
-            .ata(&from_keypair.pubkey(), &token_mint_pubkey);
+    /// Sets how long sends reuse fetched mints; `Duration::ZERO` disables the cache
+    ///
+    /// Only decimals, program and extensions are reused; `mint_info` always fetches.
+    pub fn with_mint_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.mints = Arc::new(MintCache::new(ttl));
+        self
+    }
 
-        let instraction = self
-            .token_program
-            .close_ix(&ata_sender, &address, &from_keypair)?;
+    /// Lets tokens be sent to off-curve owners such as PDAs
+    ///
+    /// Without it, sends to an off-curve address fail with `UnmintError::InvalidRecipient`
+    /// instead of creating an associated token account nobody may be able to sign for.
+    pub fn with_allow_owner_off_curve(mut self, allow: bool) -> Self {
+        self.allow_owner_off_curve = allow;
+        self
+    }
 
-        Ok(instraction)
+    /// Evaluates `policy` against every plan `execute` runs
+    ///
+    /// Token sends, SOL transfers, `send_and_close`, `retire_wallet`,
+    /// `batch_send`, `withdraw_stake`, `reclaim_stake` and `reclaim_accounts`
+    /// are checked against it as well, through the plan of what they send.
+    /// They cannot be dry run, so they are refused above the confirmation
+    /// thresholds.
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = Some(Arc::new(policy));
+        self
     }
 
-    fn send_max_token_instruction(
+    /// Signs `instructions` and sends them, decoding any failure into `UnmintError`
+    pub(crate) fn send_instructions(
         &self,
-        from_base58_string: &str,
-        to_address: &Pubkey,
-        token_mint_address: &Pubkey,
-    ) -> Result<(Instruction, Pubkey)> {
-        let from_keypair = Keypair::from_base58_string(from_base58_string);
+        instructions: &[Instruction],
+        signers: &Signers,
+    ) -> Result<Signature> {
+        let blockhash = self.client.get_latest_blockhash()?;
+        let message = instructions::build_message(
+            instructions,
+            &signers.fee_payer.pubkey(),
+            &blockhash,
+            self.priority_fee.as_ref(),
+        );
+
+        self.send_message(message, signers)
+    }
 
-        let ata_sender = self
-            .token_program
-            .ata(&from_keypair.pubkey(), token_mint_address);
+    /// Runs the preflight checks, then signs and sends `message`
+    pub(crate) fn send_message(&self, message: Message, signers: &Signers) -> Result<Signature> {
+        let transaction = self.sign_message(message, signers)?;
+        self.send_transaction(&transaction)
+    }
 
-        let ata_destinaton = self.token_program.ata(to_address, token_mint_address);
+    /// Runs the preflight checks and signs `message` without sending it
+    pub(crate) fn sign_message(&self, message: Message, signers: &Signers) -> Result<Transaction> {
+        self.preflight(&message)?;
 
-        let balances = self.balance(&from_keypair.pubkey(), token_mint_address)?;
+        signers.sign(message)
+    }
 
-        let instraction = self.token_program.transfer_ix(
-            &ata_sender,
-            &ata_destinaton,
-            &from_keypair,
-            balances.amount.parse::<u64>()?,
-            balances.decimals,
-            token_mint_address,
-        )?;
+    /// Sends `transaction` and waits until it is confirmed or its blockhash expires
+    ///
+    /// See `Confirmation` for the rebroadcast.
+    pub(crate) fn send_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+        let message = &transaction.message;
+        let decode = |e| UnmintError::from_client_error(e, Some(message));
+        let commitment = self.client.commitment();
+
+        let signature = self.client.send_transaction(transaction).map_err(decode)?;
+        let mut confirmation = Confirmation::new(signature, message);
+        loop {
+            let answer = match confirmation.query() {
+                ConfirmQuery::Status => ConfirmAnswer::Status(
+                    self.client
+                        .get_signature_status_with_commitment(&signature, commitment)
+                        .map_err(decode)?,
+                ),
+                ConfirmQuery::BlockhashValid => ConfirmAnswer::BlockhashValid(
+                    self.client
+                        .is_blockhash_valid(&message.recent_blockhash, commitment)
+                        .map_err(decode)?,
+                ),
+                ConfirmQuery::History => ConfirmAnswer::Status(
+                    self.client
+                        .get_signature_status_with_commitment_and_history(
+                            &signature, commitment, true,
+                        )
+                        .map_err(decode)?,
+                ),
+                ConfirmQuery::Rebroadcast => {
+                    thread::sleep(CONFIRM_POLL_INTERVAL);
+                    let _ = self.client.send_transaction(transaction);
+                    ConfirmAnswer::Rebroadcast
+                }
+            };
+            if let Some(result) = confirmation.answer(answer) {
+                return result;
+            }
+        }
+    }
+
+    /// Fetches `address`, `None` when it does not exist; RPC failures are errors
+    pub(crate) fn fetch_account(&self, address: &Pubkey) -> Result<Option<Account>> {
+        Ok(self
+            .client
+            .get_account_with_commitment(address, self.client.commitment())?
+            .value)
+    }
+
+    /// Fetches a token account and checks it can be moved by `token_program`
+    pub(crate) fn token_account(&self, address: &Pubkey) -> Result<TokenAccount> {
+        let account = self
+            .fetch_account(address)?
+            .ok_or(UnmintError::AccountNotFound(*address))?;
 
-        Ok((instraction, ata_destinaton))
+        instructions::checked_token_account(&self.token_program, address, &account)
     }
 
-    fn send_token_instruction(
+    /// Builds a transfer between two token accounts, appending the accounts
+    /// required by a Token-2022 transfer hook
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn transfer_ix(
         &self,
-        from_base58_string: &str,
-        to_pubkey: &Pubkey,
-        token_mint_address: &str,
-        amount: f64,
+        token_program: &TokenProgram,
+        from: &Pubkey,
+        to: &Pubkey,
+        authority: &Keypair,
+        amount: u64,
+        decimals: u8,
+        mint: &Pubkey,
+        fee: Option<u64>,
     ) -> Result<Instruction> {
-        let from_keypair = Keypair::from_base58_string(from_base58_string);
-        let token_mint_pubkey = Pubkey::from_str(token_mint_address)?;
+        let fetch = |address: Pubkey| {
+            std::future::ready(
+                self.client
+                    .get_account_with_commitment(&address, self.client.commitment())
+                    .map(|response| response.value.map(|account| account.data))
+                    .map_err(|e| e.into()),
+            )
+        };
+
+        Ok(futures::executor::block_on(
+            token_program.transfer_ix_with_extra_metas(
+                from, to, authority, amount, decimals, mint, fee, fetch,
+            ),
+        )?)
+    }
 
-        let ata_sender = self
-            .token_program
-            .ata(&from_keypair.pubkey(), &token_mint_pubkey);
+    /// Current epoch when `mint` charges a transfer fee, needed to quote it
+    pub(crate) fn fee_epoch(&self, mint: &MintInfo) -> Result<Option<u64>> {
+        if mint.transfer_fee_config().is_none() {
+            return Ok(None);
+        }
 
-        let ata_destinaton = self.token_program.ata(&to_pubkey, &token_mint_pubkey);
+        Ok(Some(self.client.get_epoch_info()?.epoch))
+    }
 
-        let balances = self
-            .client
-            .get_token_account_balance(&ata_sender)
-            .map_err(|e| {
-                if let solana_client::client_error::ClientErrorKind::RpcError(
-                    solana_client::rpc_request::RpcError::RpcResponseError { message, .. },
-                ) = e.kind()
-                {
-                    if message.contains("could not find account") {
-                        return anyhow!("token account not found");
-                    }
-                }
-                anyhow!("rpc error: {:?}", e)
-            })?;
-
-        let decimal: u8 = balances.decimals;
-        let amount_to_send = (amount * 10u64.pow(decimal.into()) as f64) as u64;
-
-        let instraction = self.token_program.transfer_ix(
-            &ata_sender,
-            &ata_destinaton,
-            &from_keypair,
-            amount_to_send,
-            balances.decimals,
-            &token_mint_pubkey,
+    /// Fetches what a send of `token_mint_pubkey` from `from` to `to` is built from
+    pub(crate) fn token_send(
+        &self,
+        from: &Pubkey,
+        to: &Pubkey,
+        token_mint_pubkey: &Pubkey,
+    ) -> Result<TokenSend> {
+        let source = self.token_program.ata(from, token_mint_pubkey);
+        let mint = self.cached_mint_info(token_mint_pubkey)?;
+        let balance = self.token_account(&source)?.amount;
+        let destination = self.destination(to, token_mint_pubkey)?;
+        let epoch = self.fee_epoch(&mint)?;
+
+        Ok(TokenSend {
+            mint,
+            source,
+            balance,
+            destination,
+            epoch,
+        })
+    }
+
+    pub(crate) fn send_max_token_instruction(
+        &self,
+        signers: &Signers,
+        ata_payer: &Pubkey,
+        to_address: &Pubkey,
+        token_mint_address: &Pubkey,
+        memo: Option<&str>,
+    ) -> Result<Vec<Instruction>> {
+        let send = self.send_token_instruction(
+            signers,
+            ata_payer,
+            to_address,
+            token_mint_address,
+            None,
+            FeeMode::SendExact,
+            memo,
+        )?;
+
+        Ok(send.instructions)
+    }
+
+    /// Builds a transfer of `amount`, or of the whole balance when `None`
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn send_token_instruction(
+        &self,
+        signers: &Signers,
+        ata_payer: &Pubkey,
+        to_pubkey: &Pubkey,
+        token_mint_pubkey: &Pubkey,
+        amount: Option<AmountInput<'_>>,
+        mode: FeeMode,
+        memo: Option<&str>,
+    ) -> Result<BuiltSend> {
+        let send = self.token_send(&signers.from.pubkey(), to_pubkey, token_mint_pubkey)?;
+        let quote = send.quote(amount, mode, memo)?;
+
+        let transfer_ix = self.transfer_ix(
+            &self.token_program,
+            &send.source,
+            &send.destination.address,
+            &signers.from,
+            quote.sent,
+            send.mint.decimals,
+            token_mint_pubkey,
+            quote.fee,
         )?;
 
-        Ok(instraction)
+        Ok(send.build(
+            &self.token_program,
+            signers,
+            ata_payer,
+            quote,
+            memo,
+            transfer_ix,
+        ))
     }
 
     /// Sends all tokens from `from` to `to` and closes the ATA.
@@ -174,6 +374,7 @@ impl Unmint {
     /// * `to_base58_string` - Base58 private key of the recipient
     /// * `token_mint_address` - Token mint address
     /// * `fee_payer_base58_string` - Optional, who pays the fee
+    /// * `memo` - Optional memo placed right before the transfer
     ///
     /// # Returns
     /// * `Signature` of the transaction
@@ -183,54 +384,48 @@ impl Unmint {
         to_address: &str,
         token_mint_address: &str,
         fee_payer_base58_string: Option<&str>,
+        memo: Option<&str>,
     ) -> Result<Signature> {
-        let mut instructions = vec![];
-
-        let to_address_pubkey = Pubkey::from_str(to_address).unwrap();
-        let from_keypair = Keypair::from_base58_string(from_base58_string);
-        let token_mint_pubkey = Pubkey::from_str(token_mint_address)?;
-
-        let fee_payer: Keypair = fee_payer_base58_string
-            .map(|s| Keypair::from_base58_string(s))
-            .unwrap_or_else(|| Keypair::from_base58_string(from_base58_string));
+        let to_address_pubkey = parse_pubkey(to_address)?;
+        let token_mint_pubkey = parse_pubkey(token_mint_address)?;
+        let signers = Signers::parse(from_base58_string, fee_payer_base58_string)?;
 
-        let (send_token_instruction, ata_destination) = self.send_max_token_instruction(
-            from_base58_string,
+        let (instructions, plan) = self.send_and_close_instructions(
+            &signers,
             &to_address_pubkey,
             &token_mint_pubkey,
+            memo,
         )?;
-
-        let close_token_account_instruction = self.close_token_account_instruction(
-            from_base58_string,
-            token_mint_address,
-            Some(&fee_payer.pubkey()),
-        )?;
-
-        if self.client.get_account(&ata_destination).is_err() {
-            let ata = self.token_program.create_ata_instraction(
-                &fee_payer.pubkey(),
-                &to_address_pubkey,
-                &token_mint_pubkey,
-            );
-
-            instructions.push(ata);
-        }
-
-        instructions.push(send_token_instruction);
-        instructions.push(close_token_account_instruction);
-
-        let mut transaction = Transaction::new_with_payer(&instructions, Some(&fee_payer.pubkey()));
-
-        let mut signers: Vec<&Keypair> = vec![&from_keypair];
-        if fee_payer.pubkey() != from_keypair.pubkey() {
-            signers.push(&fee_payer);
+        if let Some(policy) = &self.policy {
+            policy.check(&plan)?;
         }
 
-        transaction.sign(&signers, self.client.get_latest_blockhash()?);
+        self.send_instructions(&instructions, &signers)
+    }
 
-        let confirm = self.client.send_and_confirm_transaction(&transaction)?;
+    /// Builds `send_and_close`, returning the instructions with their plan
+    pub(crate) fn send_and_close_instructions(
+        &self,
+        signers: &Signers,
+        to_address: &Pubkey,
+        token_mint_address: &Pubkey,
+        memo: Option<&str>,
+    ) -> Result<(Vec<Instruction>, Plan)> {
+        let send = self.token_send(&signers.from.pubkey(), to_address, token_mint_address)?;
+        let quote = send.quote(None, FeeMode::SendExact, memo)?;
+
+        let transfer_ix = self.transfer_ix(
+            &self.token_program,
+            &send.source,
+            &send.destination.address,
+            &signers.from,
+            quote.sent,
+            send.mint.decimals,
+            token_mint_address,
+            quote.fee,
+        )?;
 
-        Ok(confirm)
+        send.send_and_close(&self.token_program, signers, &quote, memo, transfer_ix)
     }
 
     pub fn send_max_token(
@@ -239,44 +434,26 @@ impl Unmint {
         to_address: &str,
         token_mint_address: &str,
         fee_payer_base58_string: Option<&str>,
+        memo: Option<&str>,
     ) -> Result<Signature> {
-        let from_keypair = Keypair::from_base58_string(from_base58_string);
-        let to_pubkey = Pubkey::from_str(to_address)?;
-        let token_mint_pubkey = Pubkey::from_str(token_mint_address)?;
-
-        let mut instructions = vec![];
-
-        let fee_payer: Keypair = fee_payer_base58_string
-            .map(|s| Keypair::from_base58_string(s))
-            .unwrap_or_else(|| Keypair::from_base58_string(from_base58_string));
-
-        let (send_token_instruction, ata_destination) =
-            self.send_max_token_instruction(from_base58_string, &to_pubkey, &token_mint_pubkey)?;
-
-        if self.client.get_account(&ata_destination).is_err() {
-            let ata = self.token_program.create_ata_instraction(
-                &from_keypair.pubkey(),
-                &to_pubkey,
-                &token_mint_pubkey,
-            );
-
-            instructions.push(ata);
-        }
-
-        instructions.push(send_token_instruction);
-
-        let mut transaction = Transaction::new_with_payer(&instructions, Some(&fee_payer.pubkey()));
+        let to_pubkey = parse_pubkey(to_address)?;
+        let token_mint_pubkey = parse_pubkey(token_mint_address)?;
+        let signers = Signers::parse(from_base58_string, fee_payer_base58_string)?;
 
-        let mut signers: Vec<&Keypair> = vec![&from_keypair];
-        if fee_payer.pubkey() != from_keypair.pubkey() {
-            signers.push(&fee_payer);
+        let send = self.send_token_instruction(
+            &signers,
+            &signers.from.pubkey(),
+            &to_pubkey,
+            &token_mint_pubkey,
+            None,
+            FeeMode::SendExact,
+            memo,
+        )?;
+        if let Some(policy) = &self.policy {
+            policy.check(&send.plan)?;
         }
 
-        transaction.sign(&signers, self.client.get_latest_blockhash()?);
-
-        let confirm = self.client.send_and_confirm_transaction(&transaction)?;
-
-        Ok(confirm)
+        self.send_instructions(&send.instructions, &signers)
     }
 
     pub fn balance<'a, A, M>(&self, address: A, token_mint_address: M) -> Result<UiTokenAmount>
@@ -289,27 +466,13 @@ impl Unmint {
 
         let ata_sender = self.token_program.ata(&address_pubkey, &token_mint_pubkey);
 
-        let balances = self
-            .client
-            .get_token_account_balance(&ata_sender)
-            .map_err(|e| {
-                if let solana_client::client_error::ClientErrorKind::RpcError(
-                    solana_client::rpc_request::RpcError::RpcResponseError { message, .. },
-                ) = e.kind()
-                {
-                    if message.contains("could not find account") {
-                        return anyhow!("token account not found");
-                    }
-                }
-                anyhow!("rpc error: {:?}", e)
-            })?;
+        let account = self
+            .fetch_account(&ata_sender)?
+            .ok_or(UnmintError::AccountNotFound(ata_sender))?;
+        let token_account = OwnedTokenAccount::decode(ata_sender, &account)?;
+        let mint = self.cached_mint_info(&token_mint_pubkey)?;
 
-        Ok(UiTokenAmount {
-            ui_amount: balances.ui_amount,
-            decimals: balances.decimals,
-            amount: balances.amount,
-            ui_amount_string: balances.ui_amount_string,
-        })
+        Ok(TokenAmount::from_raw(token_account.account.amount, mint.decimals).into())
     }
 
     pub fn balance_sol<'a, A>(&self, address: A) -> Result<f64>
@@ -321,29 +484,98 @@ impl Unmint {
         Ok(lamports as f64 / solana_sdk::native_token::LAMPORTS_PER_SOL as f64)
     }
 
-    pub fn transfer_sol(
+    pub fn transfer_sol<'a, A>(
         &self,
         from_base58_string: &str,
         to: &str,
-        amount_sol: f64,
-    ) -> Result<Signature> {
-        let from_keypair = Keypair::from_base58_string(from_base58_string);
-        let to_pubkey = Pubkey::from_str(to)?;
+        amount_sol: A,
+        memo: Option<&str>,
+    ) -> Result<Signature>
+    where
+        A: Into<AmountInput<'a>>,
+    {
+        let signers = Signers::parse(from_base58_string, None)?;
+        let to_pubkey = parse_pubkey(to)?;
+        let lamports = amount_sol.into().to_token_amount(SOL_DECIMALS)?.raw();
+        if let Some(policy) = &self.policy {
+            let from = signers.from.pubkey();
+            policy.check(&instructions::sol_transfer_plan(
+                &from,
+                &from,
+                &to_pubkey,
+                [lamports],
+            ))?;
+        }
 
-        let lamports = (amount_sol * 1_000_000_000.0) as u64;
+        let instructions = instructions::transfer_sol_instructions(
+            &signers.from.pubkey(),
+            &to_pubkey,
+            lamports,
+            memo,
+        );
 
-        let transfer_ix =
-            system_instruction::transfer(&from_keypair.pubkey(), &to_pubkey, lamports);
+        self.send_instructions(&instructions, &signers)
+    }
 
-        let recent_blockhash = self.client.get_latest_blockhash()?;
+    /// Sends the whole SOL balance of the sender to `to`, minus the exact fee
+    ///
+    /// The fee is quoted with `getFeeForMessage` for the exact message that is
+    /// sent, so it includes the priority fee set with `with_priority_fee`.
+    /// `DrainMode::KeepRentExempt` leaves the rent-exempt minimum of an empty
+    /// account behind; `DrainMode::Full` empties the account.
+    pub fn transfer_max_sol(
+        &self,
+        from_base58_string: &str,
+        to: &str,
+        mode: DrainMode,
+    ) -> Result<Signature> {
+        let signers = Signers::parse(from_base58_string, None)?;
+        let to_pubkey = parse_pubkey(to)?;
 
-        let mut tx = Transaction::new_with_payer(&[transfer_ix], Some(&from_keypair.pubkey()));
+        let (_, signature) = self.drain_sol(&signers, &to_pubkey, mode)?;
 
-        tx.sign(&[from_keypair], recent_blockhash);
+        Ok(signature)
+    }
 
-        let sig = self.client.send_and_confirm_transaction(&tx)?;
+    /// Sends everything but the fee and the `mode` reserve from `signers.from` to `to`
+    ///
+    /// Returns the lamports sent with the signature.
+    pub(crate) fn drain_sol(
+        &self,
+        signers: &Signers,
+        to_pubkey: &Pubkey,
+        mode: DrainMode,
+    ) -> Result<(u64, Signature)> {
+        let from_pubkey = signers.from.pubkey();
+
+        let balance = self.client.get_balance(&from_pubkey)?;
+        let reserve = match mode {
+            DrainMode::KeepRentExempt => self.client.get_minimum_balance_for_rent_exemption(0)?,
+            DrainMode::Full => 0,
+        };
+
+        let drain = SolDrain {
+            from: from_pubkey,
+            to: *to_pubkey,
+            balance,
+            reserve,
+            blockhash: self.client.get_latest_blockhash()?,
+            priority_fee: self.priority_fee.as_ref(),
+        };
+        let fee = self.client.get_fee_for_message(&drain.fee_message())?;
+        let (lamports, message) = drain.finish(fee)?;
+        if let Some(policy) = &self.policy {
+            policy.check(&instructions::sol_transfer_plan(
+                &from_pubkey,
+                &from_pubkey,
+                to_pubkey,
+                [lamports],
+            ))?;
+        }
 
-        Ok(sig)
+        let signature = self.send_message(message, signers)?;
+
+        Ok((lamports, signature))
     }
 
     /// Sends a specified amount of SPL token from one account to another.
@@ -353,47 +585,82 @@ impl Unmint {
     /// * `from_base58_string` - The sender's private key in Base58 string format.
     /// * `to_address` - The recipient's public key (Base58 string).
     /// * `token_mint_address` - The SPL token mint address (Base58 string).
-    /// * `amount` - The amount of tokens to send as a floating-point number.
+    /// * `amount` - The amount of tokens to send: a UI amount as `f64` or decimal string,
+    ///   a raw `u64` in the smallest unit, or a `TokenAmount`.
     /// * `fee_payer_base58_string` - Optional: the Base58 private key of the fee payer.
-    ///    If `None`, the sender will pay the transaction fee.
+    ///   If `None`, the sender will pay the transaction fee.
+    /// * `memo` - Optional memo, e.g. an invoice reference. Required by Token-2022
+    ///   recipients with the MemoTransfer extension; sending to them without one
+    ///   fails with `UnmintError::MemoRequired` before anything is sent.
     ///
     /// # Returns
     /// * `Result<Signature>` - Returns the transaction signature if successful.
-    pub fn send_token(
+    pub fn send_token<'a, A>(
         &self,
         from_base58_string: &str,
         to_address: &str,
         token_mint_address: &str,
-        amount: f64,
+        amount: A,
         fee_payer_base58_string: Option<&str>,
-    ) -> Result<Signature> {
-        let from_keypair = Keypair::from_base58_string(from_base58_string);
-        let to_pubkey = Pubkey::from_str(to_address)?;
-
-        let fee_payer: Keypair = fee_payer_base58_string
-            .map(|s| Keypair::from_base58_string(s))
-            .unwrap_or_else(|| Keypair::from_base58_string(from_base58_string));
-
-        let send_token_instruction = self.send_token_instruction(
+        memo: Option<&str>,
+    ) -> Result<Signature>
+    where
+        A: Into<AmountInput<'a>>,
+    {
+        self.send_token_with_fee(
             from_base58_string,
-            &to_pubkey,
+            to_address,
             token_mint_address,
             amount,
-        )?;
-
-        let instructions = vec![send_token_instruction];
+            FeeMode::SendExact,
+            fee_payer_base58_string,
+            memo,
+        )
+        .map(|transfer| transfer.signature)
+    }
 
-        let mut transaction = Transaction::new_with_payer(&instructions, Some(&fee_payer.pubkey()));
+    /// Sends SPL tokens, accounting for the Token-2022 transfer fee of the mint
+    ///
+    /// With `FeeMode::SendExact` the sender is debited `amount`; with
+    /// `FeeMode::ReceiveExact` the recipient is credited `amount` and the fee is
+    /// added on top. The fee is computed from the mint's current-epoch
+    /// TransferFeeConfig and checked on-chain by `transfer_checked_with_fee`.
+    ///
+    /// # Returns
+    /// * `TokenTransfer` with the signature and the amounts sent, withheld and received
+    #[allow(clippy::too_many_arguments)]
+    pub fn send_token_with_fee<'a, A>(
+        &self,
+        from_base58_string: &str,
+        to_address: &str,
+        token_mint_address: &str,
+        amount: A,
+        mode: FeeMode,
+        fee_payer_base58_string: Option<&str>,
+        memo: Option<&str>,
+    ) -> Result<TokenTransfer>
+    where
+        A: Into<AmountInput<'a>>,
+    {
+        let to_pubkey = parse_pubkey(to_address)?;
+        let token_mint_pubkey = parse_pubkey(token_mint_address)?;
+        let signers = Signers::parse(from_base58_string, fee_payer_base58_string)?;
 
-        let mut signers: Vec<&Keypair> = vec![&from_keypair];
-        if fee_payer.pubkey() != from_keypair.pubkey() {
-            signers.push(&fee_payer);
+        let send = self.send_token_instruction(
+            &signers,
+            &signers.fee_payer.pubkey(),
+            &to_pubkey,
+            &token_mint_pubkey,
+            Some(amount.into()),
+            mode,
+            memo,
+        )?;
+        if let Some(policy) = &self.policy {
+            policy.check(&send.plan)?;
         }
 
-        transaction.sign(&signers, self.client.get_latest_blockhash()?);
-
-        let confirm = self.client.send_and_confirm_transaction(&transaction)?;
+        let signature = self.send_instructions(&send.instructions, &signers)?;
 
-        Ok(confirm)
+        Ok(send.quote.receipt(signature, send.decimals))
     }
 }
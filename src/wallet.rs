@@ -1,8 +1,13 @@
 use solana_sdk::{
-    signature::{Keypair, keypair_from_seed},
+    signature::{Keypair, Signature, keypair_from_seed},
     signer::Signer,
 };
 
+use crate::{
+    error::Result,
+    offchain::{self, SignInMessage},
+};
+
 pub enum WalletKey {
     StringKey(String),
     ArryKey(Vec<u8>),
@@ -27,6 +32,18 @@ impl Wallet {
     pub fn address(&self) -> String {
         self.keypair.pubkey().to_string()
     }
+
+    /// Signs `message` using the Solana off-chain message signing format
+    ///
+    /// Verify the signature with `verify_message`.
+    pub fn sign_message(&self, message: &[u8]) -> Result<Signature> {
+        offchain::sign_message(&self.keypair, message)
+    }
+
+    /// Signs a Sign-In-With-Solana message addressed to this wallet
+    pub fn sign_in(&self, message: &SignInMessage) -> Result<Signature> {
+        message.sign(&self.keypair)
+    }
 }
 
 pub struct Generate {
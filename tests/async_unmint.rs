@@ -0,0 +1,31 @@
+use anyhow::Result;
+use futures::executor::block_on;
+use sol_unmint::{AsyncUnmint, SendAndClose, TokenProgram, UnmintError};
+
+#[test]
+fn test_async_invalid_input() -> Result<()> {
+    let unmint = AsyncUnmint::new("https://api.mainnet-beta.solana.com", TokenProgram::Legacy);
+
+    let jobs = [
+        SendAndClose {
+            from_base58_string: "not-a-key",
+            to_address: "57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj",
+            token_mint_address: "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB",
+            fee_payer_base58_string: None,
+            memo: None,
+        },
+        SendAndClose {
+            from_base58_string: "",
+            to_address: "not-a-pubkey",
+            token_mint_address: "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB",
+            fee_payer_base58_string: None,
+            memo: None,
+        },
+    ];
+
+    let results = block_on(unmint.send_and_close_many(&jobs, 2));
+
+    assert!(matches!(results[0], Err(UnmintError::InvalidKey)));
+    assert!(matches!(results[1], Err(UnmintError::InvalidPubkey(_))));
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use anyhow::Result;
+use sol_unmint::{ActionStatus, Payout, PayoutResult, TokenProgram, UnmintError};
+use solana_sdk::signature::Signature;
+
+use crate::setup_unmint::setup_unmint;
+mod setup_unmint;
+
+#[test]
+fn test_payouts_from_csv() -> Result<()> {
+    let csv = "address,amount\n\
+               # january\n\
+               57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj, 1.5\n\
+               \n\
+               \"EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v\",\"0.25\"\n\
+               \"9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin\",\"1,000\"\n\
+               missing-amount\n";
+
+    assert_eq!(
+        Payout::from_csv(csv)?,
+        vec![
+            Payout::new("57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj", "1.5"),
+            Payout::new("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "0.25"),
+            Payout::new("9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin", "1,000"),
+            Payout::new("missing-amount", ""),
+        ]
+    );
+
+    // an unquoted thousands separator is a third field, not a smaller amount
+    let csv = "57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj,1,000\n";
+    assert!(Payout::from_csv(csv).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_payouts_from_json() -> Result<()> {
+    let json = r#"[
+        {"address": "57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj", "amount": "1.5"},
+        {"address": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "amount": 0.25}
+    ]"#;
+
+    assert_eq!(
+        Payout::from_json(json)?,
+        vec![
+            Payout::new("57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj", "1.5"),
+            Payout::new("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "0.25"),
+        ]
+    );
+    assert!(Payout::from_json(r#"[{"address": "x", "amount": true}]"#).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_payout_result_line() -> Result<()> {
+    let signature = Signature::from([7; 64]);
+    let result = PayoutResult {
+        id: "3".to_string(),
+        action: Payout::new("57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj", "1.5"),
+        status: ActionStatus::Pending {
+            signature,
+            last_valid_block_height: 1_000,
+            transaction: "AQID".to_string(),
+        },
+    };
+
+    let line = serde_json::to_string(&result)?;
+    assert_eq!(
+        line,
+        format!(
+            r#"{{"id":"3","address":"57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj","amount":"1.5","status":"pending","signature":"{}","last_valid_block_height":1000,"transaction":"AQID"}}"#,
+            signature
+        )
+    );
+    assert_eq!(serde_json::from_str::<PayoutResult>(&line)?, result);
+    Ok(())
+}
+
+#[test]
+fn test_batch_send_invalid_key() -> Result<()> {
+    let unmint = setup_unmint(TokenProgram::Legacy);
+    let journal_path = std::env::temp_dir().join("sol_unmint_batch_send_invalid_key.jsonl");
+
+    let results = unmint.batch_send(
+        "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+        "", //Base58 private key of the sender
+        &[Payout::new(
+            "57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj",
+            "1",
+        )],
+        &journal_path,
+    );
+
+    assert!(matches!(results, Err(UnmintError::InvalidKey)));
+    assert!(!journal_path.exists());
+    Ok(())
+}
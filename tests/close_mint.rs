@@ -0,0 +1,73 @@
+use anyhow::Result;
+use sol_unmint::{MintExtension, MintInfo, TokenProgram, UnmintError};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::setup_unmint::setup_unmint;
+mod setup_unmint;
+
+fn mint(token_program: TokenProgram, supply: u64, close_authority: Option<Pubkey>) -> MintInfo {
+    MintInfo {
+        address: Pubkey::new_unique(),
+        token_program,
+        supply,
+        decimals: 6,
+        mint_authority: None,
+        freeze_authority: None,
+        extensions: vec![MintExtension::MintCloseAuthority { close_authority }],
+    }
+}
+
+#[test]
+fn test_check_closable() -> Result<()> {
+    let authority = Pubkey::new_unique();
+
+    let closable = mint(TokenProgram::Token2022, 0, Some(authority));
+    assert_eq!(closable.close_authority(), Some(authority));
+    assert!(closable.check_closable(&authority).is_ok());
+
+    let other = Pubkey::new_unique();
+    let err = closable.check_closable(&other).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "cannot close mint {}: its close authority is {}",
+            closable.address, authority
+        )
+    );
+
+    let circulating = mint(TokenProgram::Token2022, 1_500_000, Some(authority));
+    assert_eq!(
+        circulating
+            .check_closable(&authority)
+            .unwrap_err()
+            .to_string(),
+        format!(
+            "cannot close mint {}: 1.5 tokens are still in circulation",
+            circulating.address
+        )
+    );
+
+    let renounced = mint(TokenProgram::Token2022, 0, None);
+    assert!(matches!(
+        renounced.check_closable(&authority),
+        Err(UnmintError::MintNotClosable { .. })
+    ));
+
+    let legacy = mint(TokenProgram::Legacy, 0, Some(authority));
+    assert!(legacy.check_closable(&authority).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_close_mint_invalid_key() -> Result<()> {
+    let unmint = setup_unmint(TokenProgram::Token2022);
+
+    let signature = unmint.close_mint(
+        "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+        "", //Base58 private key of the mint close authority
+        "57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj",
+    );
+
+    assert!(matches!(signature, Err(UnmintError::InvalidKey)));
+    Ok(())
+}
@@ -0,0 +1,22 @@
+use anyhow::Result;
+use sol_unmint::TokenProgram;
+use solana_sdk::pubkey::Pubkey;
+
+#[test]
+fn test_create_ata_idempotent_ix() -> Result<()> {
+    let payer = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+
+    for program in [TokenProgram::Legacy, TokenProgram::Token2022] {
+        let ix = program.create_ata_idempotent_ix(&payer, &owner, &mint);
+
+        assert_eq!(ix.program_id, spl_associated_token_account::id());
+        // AssociatedTokenAccountInstruction::CreateIdempotent
+        assert_eq!(ix.data, vec![1]);
+        assert_eq!(ix.accounts[0].pubkey, payer);
+        assert_eq!(ix.accounts[1].pubkey, program.ata(&owner, &mint));
+        assert_eq!(ix.accounts[5].pubkey, program.program_id());
+    }
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use anyhow::Result;
+use sol_unmint::{Delegation, OwnedTokenAccount, TokenProgram, UnmintError};
+use solana_sdk::{program_option::COption, pubkey::Pubkey};
+use spl_token_2022::state::{Account as TokenAccount, AccountState};
+
+use crate::setup_unmint::setup_unmint;
+mod setup_unmint;
+
+fn token_account(
+    delegate: Option<Pubkey>,
+    delegated_amount: u64,
+    state: AccountState,
+) -> OwnedTokenAccount {
+    OwnedTokenAccount {
+        address: Pubkey::new_unique(),
+        token_program: TokenProgram::Legacy,
+        lamports: 2_039_280,
+        account: TokenAccount {
+            mint: Pubkey::new_unique(),
+            amount: 1_000,
+            delegate: delegate.into(),
+            delegated_amount,
+            state,
+            ..TokenAccount::default()
+        },
+        withheld_amount: 0,
+    }
+}
+
+#[test]
+fn test_delegation() -> Result<()> {
+    let delegate = Pubkey::new_unique();
+
+    let approved = token_account(Some(delegate), 500, AccountState::Initialized);
+    assert_eq!(
+        approved.delegation(),
+        Some(Delegation {
+            account: approved.address,
+            token_program: TokenProgram::Legacy,
+            mint: approved.account.mint,
+            delegate,
+            delegated_amount: 500,
+            frozen: false,
+        })
+    );
+
+    // revoke_all skips it, the token program rejects revokes on frozen accounts
+    let frozen = token_account(Some(delegate), 500, AccountState::Frozen);
+    assert!(
+        frozen
+            .delegation()
+            .is_some_and(|delegation| delegation.frozen)
+    );
+
+    let untouched = token_account(None, 0, AccountState::Initialized);
+    assert_eq!(untouched.account.delegate, COption::None);
+    assert_eq!(untouched.delegation(), None);
+    Ok(())
+}
+
+#[test]
+fn test_revoke_all_invalid_key() -> Result<()> {
+    let unmint = setup_unmint(TokenProgram::Legacy);
+
+    let signatures = unmint.revoke_all(
+        "", //Base58 private key of the token account owner
+        None,
+    );
+
+    assert!(matches!(signatures, Err(UnmintError::InvalidKey)));
+    Ok(())
+}
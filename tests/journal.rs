@@ -0,0 +1,123 @@
+use std::{fs, io::Write};
+
+use anyhow::Result;
+use sol_unmint::{
+    ActionStatus, Journal, Payout, RetirePolicy, RetireStep, TokenProgram, UnmintError,
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use crate::setup_unmint::setup_unmint;
+mod setup_unmint;
+
+#[test]
+fn test_journal_reload() -> Result<()> {
+    let path = std::env::temp_dir().join("sol_unmint_journal_reload.jsonl");
+    let _ = fs::remove_file(&path);
+
+    let first = Payout::new("57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj", "1.5");
+    let second = Payout::new("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "2");
+    let signature = Signature::from([3; 64]);
+
+    {
+        let mut journal = Journal::open(&path)?;
+        assert!(journal.plan("0", first.clone())?);
+        assert!(journal.plan("1", second.clone())?);
+        journal.record("0", ActionStatus::Confirmed { signature })?;
+        journal.record(
+            "1",
+            ActionStatus::Failed {
+                error: "blockhash not found".to_string(),
+            },
+        )?;
+    }
+
+    // a crash in the middle of a write leaves a partial line behind
+    fs::OpenOptions::new()
+        .append(true)
+        .open(&path)?
+        .write_all(br#"{"id":"1","address":"EPj"#)?;
+
+    let mut journal = Journal::<Payout>::open(&path)?;
+    assert_eq!(
+        journal.get("0").map(|entry| &entry.status),
+        Some(&ActionStatus::Confirmed { signature })
+    );
+    let outstanding: Vec<&str> = journal.outstanding().map(|e| e.id.as_str()).collect();
+    assert_eq!(outstanding, vec!["1"]);
+
+    // confirmed actions are not run again, failed ones are
+    assert!(!journal.plan("0", first)?);
+    assert!(journal.plan("1", second.clone())?);
+    assert!(matches!(
+        journal.plan("0", second),
+        Err(UnmintError::JournalMismatch(_))
+    ));
+    let skipped = ActionStatus::Skipped {
+        reason: "retried later".to_string(),
+    };
+    journal.record("1", skipped.clone())?;
+    drop(journal);
+
+    // the partial line was cut off, so the entry appended after it reloads
+    let journal = Journal::<Payout>::open(&path)?;
+    assert_eq!(journal.get("1").map(|entry| &entry.status), Some(&skipped));
+
+    fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_journal_corrupt_line() -> Result<()> {
+    let path = std::env::temp_dir().join("sol_unmint_journal_corrupt_line.jsonl");
+    let _ = fs::remove_file(&path);
+
+    {
+        let mut journal = Journal::open(&path)?;
+        journal.plan(
+            "0",
+            Payout::new("57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj", "1.5"),
+        )?;
+    }
+
+    // a damaged line followed by valid ones is not a crash mid-write
+    let contents = fs::read_to_string(&path)?;
+    fs::write(&path, format!("{{\"id\":\"0\"\n{}", contents))?;
+
+    assert!(matches!(
+        Journal::<Payout>::open(&path),
+        Err(UnmintError::JournalMismatch(_))
+    ));
+
+    fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_retire_step_line() -> Result<()> {
+    let step = RetireStep {
+        mint: Pubkey::new_from_array([1; 32]),
+        amount: 42,
+    };
+
+    let line = serde_json::to_string(&step)?;
+    assert_eq!(line, format!(r#"{{"mint":"{}","amount":42}}"#, step.mint));
+    assert_eq!(serde_json::from_str::<RetireStep>(&line)?, step);
+    Ok(())
+}
+
+#[test]
+fn test_retire_wallet_journaled_invalid_key() -> Result<()> {
+    let unmint = setup_unmint(TokenProgram::Legacy);
+    let journal_path = std::env::temp_dir().join("sol_unmint_retire_invalid_key.jsonl");
+
+    let receipt = unmint.retire_wallet_journaled(
+        "", //Base58 private key of the wallet to retire
+        "57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj",
+        &RetirePolicy::default(),
+        &journal_path,
+    );
+
+    assert!(matches!(receipt, Err(UnmintError::InvalidKey)));
+    assert!(!journal_path.exists());
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use anyhow::Result;
+use sol_unmint::{TokenProgram, UnmintError, check_memo};
+use solana_sdk::{account::Account, program_pack::Pack, pubkey::Pubkey};
+use spl_token_2022::{
+    extension::{
+        BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+        memo_transfer::MemoTransfer,
+    },
+    state::{Account as TokenAccount, AccountState},
+};
+
+fn token_account(token_program: TokenProgram, require_memo: bool) -> Result<Account> {
+    let data = match token_program {
+        TokenProgram::Legacy => {
+            let mut data = vec![0; spl_token::state::Account::LEN];
+            spl_token::state::Account {
+                mint: Pubkey::new_unique(),
+                owner: Pubkey::new_unique(),
+                state: spl_token::state::AccountState::Initialized,
+                ..Default::default()
+            }
+            .pack_into_slice(&mut data);
+            data
+        }
+        TokenProgram::Token2022 => {
+            let len = ExtensionType::try_calculate_account_len::<TokenAccount>(&[
+                ExtensionType::MemoTransfer,
+            ])?;
+            let mut data = vec![0; len];
+            let mut state =
+                StateWithExtensionsMut::<TokenAccount>::unpack_uninitialized(&mut data)?;
+            state
+                .init_extension::<MemoTransfer>(true)?
+                .require_incoming_transfer_memos = require_memo.into();
+            state.base = TokenAccount {
+                mint: Pubkey::new_unique(),
+                owner: Pubkey::new_unique(),
+                state: AccountState::Initialized,
+                ..TokenAccount::default()
+            };
+            state.pack_base();
+            state.init_account_type()?;
+            data
+        }
+    };
+
+    Ok(Account {
+        lamports: 2_039_280,
+        data,
+        owner: token_program.program_id(),
+        executable: false,
+        rent_epoch: 0,
+    })
+}
+
+#[test]
+fn test_check_memo_required() -> Result<()> {
+    let address = Pubkey::new_unique();
+    let account = token_account(TokenProgram::Token2022, true)?;
+
+    let checked = check_memo(&address, Some(&account), None);
+
+    assert!(matches!(checked, Err(UnmintError::MemoRequired(a)) if a == address));
+    Ok(())
+}
+
+#[test]
+fn test_check_memo_given() -> Result<()> {
+    let address = Pubkey::new_unique();
+    let account = token_account(TokenProgram::Token2022, true)?;
+
+    assert!(check_memo(&address, Some(&account), Some("invoice 42")).is_ok());
+
+    // a destination that does not exist yet is created without the requirement
+    assert!(check_memo(&address, None, None).is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_check_memo_legacy_owner() -> Result<()> {
+    let address = Pubkey::new_unique();
+    let legacy = token_account(TokenProgram::Legacy, false)?;
+    let optional = token_account(TokenProgram::Token2022, false)?;
+
+    assert!(check_memo(&address, Some(&legacy), None).is_ok());
+    assert!(check_memo(&address, Some(&optional), None).is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_memo_required_message() -> Result<()> {
+    let account = Pubkey::new_unique();
+
+    assert_eq!(
+        UnmintError::MemoRequired(account).to_string(),
+        format!(
+            "token account {} requires a memo on incoming transfers",
+            account
+        )
+    );
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use anyhow::Result;
+use sol_unmint::{AuthorityKind, NewMintExtension, TokenMetadata, TokenProgram, UnmintError};
+use solana_sdk::{pubkey::Pubkey, signature::Keypair};
+
+use crate::setup_unmint::setup_unmint;
+mod setup_unmint;
+
+#[test]
+fn test_create_mint_refuses_before_sending() -> Result<()> {
+    let payer = Keypair::new().to_base58_string();
+
+    let legacy = setup_unmint(TokenProgram::Legacy);
+    let mint = legacy.create_mint(
+        &payer,
+        6,
+        None,
+        None,
+        &[NewMintExtension::CloseAuthority(Pubkey::new_unique())],
+    );
+    assert!(mint.is_err());
+
+    // metadata is signed by the mint authority, which must be the payer
+    let token2022 = setup_unmint(TokenProgram::Token2022);
+    let other_authority = Pubkey::new_unique().to_string();
+    let mint = token2022.create_mint(
+        &payer,
+        6,
+        Some(&other_authority),
+        None,
+        &[NewMintExtension::Metadata(TokenMetadata::new(
+            "Test", "TST", "",
+        ))],
+    );
+    assert!(mint.is_err());
+
+    let mint = token2022.create_mint("", 6, None, None, &[]);
+    assert!(matches!(mint, Err(UnmintError::InvalidKey)));
+    Ok(())
+}
+
+#[test]
+fn test_set_authority_close_mint_legacy() -> Result<()> {
+    let unmint = setup_unmint(TokenProgram::Legacy);
+
+    let signature = unmint.set_authority(
+        "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+        AuthorityKind::CloseMint,
+        &Keypair::new().to_base58_string(),
+        None,
+    );
+
+    assert!(signature.is_err());
+    Ok(())
+}
+
+#[test]
+fn test_wrong_authority_message() -> Result<()> {
+    let mint = Pubkey::new_unique();
+    let found = Pubkey::new_unique();
+    let expected = Pubkey::new_unique();
+
+    let err = UnmintError::WrongAuthority {
+        address: mint,
+        expected: Some(expected),
+        found,
+    };
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "{} is not the authority of {}, expected {}",
+            found, mint, expected
+        )
+    );
+
+    let renounced = UnmintError::WrongAuthority {
+        address: mint,
+        expected: None,
+        found,
+    };
+    assert_eq!(
+        renounced.to_string(),
+        format!("{} is not the authority of {}, which has none", found, mint)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mint_to_invalid_key() -> Result<()> {
+    let unmint = setup_unmint(TokenProgram::Legacy);
+
+    let signature = unmint.mint_to(
+        "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+        "", //Base58 private key of the mint authority
+        "57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj",
+        1.5,
+        None,
+    );
+
+    assert!(matches!(signature, Err(UnmintError::InvalidKey)));
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use anyhow::Result;
+use sol_unmint::{MintExtension, MintInfo, TokenProgram, UnmintError};
+use solana_sdk::{account::Account, program_option::COption, program_pack::Pack, pubkey::Pubkey};
+use spl_token_2022::{
+    extension::{
+        BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+        mint_close_authority::MintCloseAuthority, non_transferable::NonTransferable,
+        transfer_hook::TransferHook,
+    },
+    state::Mint,
+};
+
+use crate::setup_unmint::setup_unmint;
+mod setup_unmint;
+
+fn base_mint(mint_authority: Option<Pubkey>) -> Mint {
+    Mint {
+        mint_authority: mint_authority.into(),
+        supply: 1_000_000,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+}
+
+#[test]
+fn test_decode_legacy_mint() -> Result<()> {
+    let authority = Pubkey::new_unique();
+    let mut data = vec![0; Mint::LEN];
+    Mint::pack(base_mint(Some(authority)), &mut data)?;
+
+    let account = Account {
+        lamports: 1_461_600,
+        data,
+        owner: TokenProgram::Legacy.program_id(),
+        ..Account::default()
+    };
+    let info = MintInfo::decode(Pubkey::new_unique(), &account)?;
+
+    assert_eq!(info.token_program, TokenProgram::Legacy);
+    assert_eq!(info.supply, 1_000_000);
+    assert_eq!(info.decimals, 6);
+    assert_eq!(info.mint_authority, Some(authority));
+    assert_eq!(info.freeze_authority, None);
+    assert!(info.extensions.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_decode_token2022_extensions() -> Result<()> {
+    let close_authority = Pubkey::new_unique();
+    let hook_program = Pubkey::new_unique();
+
+    let len = ExtensionType::try_calculate_account_len::<Mint>(&[
+        ExtensionType::MintCloseAuthority,
+        ExtensionType::NonTransferable,
+        ExtensionType::TransferHook,
+    ])?;
+    let mut data = vec![0; len];
+    let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data)?;
+    state
+        .init_extension::<MintCloseAuthority>(true)?
+        .close_authority = Some(close_authority).try_into()?;
+    state.init_extension::<NonTransferable>(true)?;
+    state.init_extension::<TransferHook>(true)?.program_id = Some(hook_program).try_into()?;
+    state.base = base_mint(None);
+    state.pack_base();
+    state.init_account_type()?;
+
+    let account = Account {
+        lamports: 2_000_000,
+        data,
+        owner: TokenProgram::Token2022.program_id(),
+        ..Account::default()
+    };
+    let info = MintInfo::decode(Pubkey::new_unique(), &account)?;
+
+    assert_eq!(info.token_program, TokenProgram::Token2022);
+    assert_eq!(info.mint_authority, None);
+    assert!(info.is_non_transferable());
+    assert!(info.transfer_fee_config().is_none());
+    assert_eq!(
+        info.extensions,
+        vec![
+            MintExtension::MintCloseAuthority {
+                close_authority: Some(close_authority)
+            },
+            MintExtension::NonTransferable,
+            MintExtension::TransferHook {
+                authority: None,
+                program_id: Some(hook_program)
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_decode_wrong_owner() -> Result<()> {
+    let account = Account {
+        data: vec![0; Mint::LEN],
+        owner: Pubkey::new_unique(),
+        ..Account::default()
+    };
+
+    let info = MintInfo::decode(Pubkey::new_unique(), &account);
+
+    assert!(matches!(info, Err(UnmintError::NotTokenProgram { .. })));
+    Ok(())
+}
+
+#[test]
+fn test_mint_info_invalid_pubkey() -> Result<()> {
+    let unmint = setup_unmint(TokenProgram::Token2022);
+
+    let info = unmint.mint_info("not-a-pubkey");
+
+    assert!(matches!(info, Err(UnmintError::InvalidPubkey(_))));
+    Ok(())
+}
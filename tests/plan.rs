@@ -0,0 +1,166 @@
+use anyhow::Result;
+use sol_unmint::{
+    Balance, BalanceDelta, Drift, Plan, PlanAction, PlanStep, TokenProgram, UnmintError,
+};
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+use crate::setup_unmint::setup_unmint;
+mod setup_unmint;
+
+fn sample_plan() -> Plan {
+    let owner = Pubkey::new_from_array([1; 32]);
+    let recipient = Pubkey::new_from_array([2; 32]);
+    let source = Pubkey::new_from_array([3; 32]);
+    let destination = Pubkey::new_from_array([4; 32]);
+    let mint = Pubkey::new_from_array([5; 32]);
+    let program = TokenProgram::Legacy.program_id();
+
+    Plan::new(
+        owner,
+        vec![
+            PlanStep {
+                action: PlanAction::CreateAta {
+                    program,
+                    payer: owner,
+                    owner: recipient,
+                    mint,
+                    address: destination,
+                },
+                deltas: vec![
+                    BalanceDelta {
+                        address: owner,
+                        balance: Balance::Lamports,
+                        before: 10_000_000,
+                        after: 7_960_720,
+                    },
+                    BalanceDelta {
+                        address: destination,
+                        balance: Balance::Lamports,
+                        before: 0,
+                        after: 2_039_280,
+                    },
+                ],
+            },
+            PlanStep {
+                action: PlanAction::Transfer {
+                    program,
+                    source,
+                    destination,
+                    authority: owner,
+                    mint,
+                    amount: 500,
+                    decimals: 6,
+                    fee: None,
+                },
+                deltas: vec![
+                    BalanceDelta {
+                        address: source,
+                        balance: Balance::Tokens { mint },
+                        before: 1_000,
+                        after: 500,
+                    },
+                    BalanceDelta {
+                        address: destination,
+                        balance: Balance::Tokens { mint },
+                        before: 0,
+                        after: 500,
+                    },
+                ],
+            },
+            PlanStep {
+                action: PlanAction::TransferSol {
+                    from: owner,
+                    to: recipient,
+                    lamports: 1_000,
+                },
+                deltas: vec![BalanceDelta {
+                    address: owner,
+                    balance: Balance::Lamports,
+                    before: 7_960_720,
+                    after: 7_959_720,
+                }],
+            },
+        ],
+    )
+}
+
+#[test]
+fn test_plan_json() -> Result<()> {
+    let plan = sample_plan();
+
+    let json = plan.to_json()?;
+    assert_eq!(Plan::from_json(&json)?, plan);
+
+    let value: serde_json::Value = serde_json::from_str(&json)?;
+    let transfer = &value["steps"][1];
+    assert_eq!(transfer["type"], "transfer");
+    assert_eq!(transfer["amount"], 500);
+    assert_eq!(transfer["deltas"][0]["balance"], "tokens");
+    assert_eq!(
+        transfer["deltas"][0]["mint"],
+        Pubkey::new_from_array([5; 32]).to_string()
+    );
+
+    assert!(Plan::from_json(r#"{"fee_payer": "x", "steps": []}"#).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_plan_assumed_balances() -> Result<()> {
+    let plan = sample_plan();
+    let owner = Pubkey::new_from_array([1; 32]);
+    let destination = Pubkey::new_from_array([4; 32]);
+    let mint = Pubkey::new_from_array([5; 32]);
+
+    let assumed = plan.assumed_balances();
+    assert_eq!(assumed.len(), 4);
+    // the owner's lamports are assumed as they were before the first step
+    assert_eq!(assumed[0], (owner, Balance::Lamports, 10_000_000));
+    assert!(assumed.contains(&(destination, Balance::Tokens { mint }, 0)));
+
+    let drift = UnmintError::PlanDrift(vec![Drift {
+        address: destination,
+        balance: Balance::Tokens { mint },
+        planned: 0,
+        found: 7,
+    }]);
+    assert_eq!(
+        drift.to_string(),
+        format!(
+            "plan is out of date: {} holds 7 tokens of {} but the plan assumed 0",
+            destination, mint
+        )
+    );
+    Ok(())
+}
+
+#[test]
+fn test_execute_invalid_key() -> Result<()> {
+    let unmint = setup_unmint(TokenProgram::Legacy);
+
+    let result = unmint.execute(
+        &sample_plan(),
+        "", //Base58 private key of the plan authority
+        None,
+    );
+
+    assert!(matches!(result, Err(UnmintError::InvalidKey)));
+    Ok(())
+}
+
+#[test]
+fn test_dry_run_fee_payer_mismatch() -> Result<()> {
+    let unmint = setup_unmint(TokenProgram::Legacy);
+    let mut plan = sample_plan();
+    let payer = Keypair::new();
+
+    let result = unmint.dry_run(&mut plan, &payer.to_base58_string(), None);
+
+    assert!(matches!(
+        result,
+        Err(UnmintError::FeePayerMismatch { expected, found })
+            if expected == Pubkey::new_from_array([1; 32]) && found == payer.pubkey()
+    ));
+    assert!(!plan.is_confirmed());
+    Ok(())
+}
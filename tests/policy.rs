@@ -0,0 +1,202 @@
+use anyhow::Result;
+use sol_unmint::{Plan, PlanAction, PlanStep, Policy, TokenProgram, UnmintError, Violation};
+use solana_sdk::{pubkey::Pubkey, signature::Keypair};
+
+use crate::setup_unmint::setup_unmint;
+mod setup_unmint;
+
+const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+const USDT: &str = "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB";
+const TREASURY: &str = "57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj";
+
+fn plan(actions: Vec<PlanAction>) -> Plan {
+    Plan::new(
+        Pubkey::new_from_array([1; 32]),
+        actions
+            .into_iter()
+            .map(|action| PlanStep {
+                action,
+                deltas: vec![],
+            })
+            .collect(),
+    )
+}
+
+fn close(mint: &Pubkey) -> PlanAction {
+    PlanAction::Close {
+        program: TokenProgram::Legacy.program_id(),
+        account: Pubkey::new_unique(),
+        mint: *mint,
+        destination: Pubkey::new_from_array([1; 32]),
+        authority: Pubkey::new_from_array([1; 32]),
+    }
+}
+
+fn transfer(to_wallet: &Pubkey, mint: &Pubkey, amount: u64) -> PlanAction {
+    let program = TokenProgram::Legacy;
+    PlanAction::Transfer {
+        program: program.program_id(),
+        source: Pubkey::new_unique(),
+        destination: program.ata(to_wallet, mint),
+        authority: Pubkey::new_from_array([1; 32]),
+        mint: *mint,
+        amount,
+        decimals: 6,
+        fee: None,
+    }
+}
+
+#[test]
+fn test_policy_from_toml() -> Result<()> {
+    let policy = Policy::from_toml(&format!(
+        r#"
+        protected_mints = ["{USDC}"]
+        allowed_recipients = ["{TREASURY}"]
+        max_sol_transfer = "1.5"
+        confirm_closes_above = 2
+
+        [mints.{USDT}]
+        max_transfer = "1000"
+        confirm_above = "100.5"
+        "#
+    ))?;
+
+    assert!(policy.protected_mints.contains(&USDC.parse()?));
+    assert!(policy.closable_mints.is_empty());
+    assert_eq!(policy.max_sol_transfer, Some(1_500_000_000));
+    assert_eq!(policy.confirm_closes_above, Some(2));
+    assert_eq!(
+        policy.mint_limits[&USDT.parse()?].confirm_above.as_deref(),
+        Some("100.5")
+    );
+
+    assert!(Policy::from_toml("unknown_rule = true").is_err());
+    assert!(Policy::from_toml(r#"protected_mints = ["not-a-key"]"#).is_err());
+
+    // mint limits are parsed with the mint's decimals once a plan moves the mint
+    let policy = Policy::from_toml(&format!("[mints.{USDT}]\nmax_transfer = \"1.0000001\""))?;
+    let usdt: Pubkey = USDT.parse()?;
+    let moves_usdt = plan(vec![transfer(&usdt, &usdt, 1)]);
+    assert!(policy.evaluate(&moves_usdt).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_policy_evaluate() -> Result<()> {
+    let usdc: Pubkey = USDC.parse()?;
+    let usdt: Pubkey = USDT.parse()?;
+    let treasury: Pubkey = TREASURY.parse()?;
+    let stranger = Pubkey::new_unique();
+
+    let policy = Policy::from_toml(&format!(
+        r#"
+        protected_mints = ["{USDC}"]
+        allowed_recipients = ["{TREASURY}"]
+        confirm_closes_above = 2
+
+        [mints.{USDT}]
+        max_transfer = "1000"
+        confirm_above = "100"
+        "#
+    ))?;
+
+    // transfers to the treasury ATA under the limits pass
+    assert!(
+        policy
+            .evaluate(&plan(vec![transfer(&treasury, &usdt, 50_000_000)]))?
+            .is_empty()
+    );
+
+    // the fee payer is not exempt unless it is the owner funds come from
+    let owner = Pubkey::new_unique();
+    let fee_payer = Pubkey::new_from_array([1; 32]);
+    let to_fee_payer = plan(vec![PlanAction::TransferSol {
+        from: owner,
+        to: fee_payer,
+        lamports: 1_000,
+    }]);
+    assert_eq!(
+        policy.evaluate(&to_fee_payer)?,
+        vec![Violation::RecipientNotAllowed(fee_payer)]
+    );
+    let rent_to_fee_payer = plan(vec![PlanAction::Close {
+        program: TokenProgram::Legacy.program_id(),
+        account: Pubkey::new_unique(),
+        mint: usdt,
+        destination: fee_payer,
+        authority: owner,
+    }]);
+    assert_eq!(
+        policy.evaluate(&rent_to_fee_payer)?,
+        vec![Violation::RecipientNotAllowed(fee_payer)]
+    );
+
+    let violations = policy.evaluate(&plan(vec![
+        close(&usdc),
+        transfer(&stranger, &usdt, 2_000_000_000),
+        close(&usdt),
+        close(&usdt),
+    ]))?;
+
+    assert!(matches!(violations[0], Violation::ProtectedMint { mint, .. } if mint == usdc));
+    assert!(matches!(violations[1], Violation::RecipientNotAllowed(_)));
+    assert_eq!(
+        violations[2],
+        Violation::TransferLimit {
+            mint: Some(usdt),
+            amount: "2000".to_string(),
+            limit: "1000".to_string(),
+        }
+    );
+    assert_eq!(
+        violations[3].to_string(),
+        format!("moving 2000 of {} needs a successful dry run first", usdt)
+    );
+    assert_eq!(
+        violations[4],
+        Violation::ConfirmationRequired("closing 3 accounts".to_string())
+    );
+    assert_eq!(violations.len(), 5);
+
+    // only Unmint::dry_run confirms a plan; a confirmation cannot be read from JSON
+    let closes = plan(vec![close(&usdt), close(&usdt), close(&usdt)]);
+    let mut json: serde_json::Value = serde_json::from_str(&closes.to_json()?)?;
+    json["confirmed"] = true.into();
+    json["confirmation"] = "11111111111111111111111111111111".into();
+    let claimed = Plan::from_json(&json.to_string())?;
+    assert!(!claimed.is_confirmed());
+    assert_eq!(
+        policy.evaluate(&claimed)?,
+        vec![Violation::ConfirmationRequired(
+            "closing 3 accounts".to_string()
+        )]
+    );
+
+    let err = policy.check(&plan(vec![close(&usdc)])).unwrap_err();
+    assert!(matches!(err, UnmintError::PolicyViolation(_)));
+    assert!(err.to_string().starts_with("policy violation: "));
+    Ok(())
+}
+
+#[test]
+fn test_transfer_sol_policy() -> Result<()> {
+    let unmint = setup_unmint(TokenProgram::Legacy).with_policy(Policy::from_toml(
+        r#"
+        max_sol_transfer = "1"
+        "#,
+    )?);
+
+    // refused before anything is fetched or sent
+    let result = unmint.transfer_sol(&Keypair::new().to_base58_string(), TREASURY, "1.5", None);
+
+    assert!(matches!(
+        result,
+        Err(UnmintError::PolicyViolation(violations))
+            if violations == vec![Violation::TransferLimit {
+                mint: None,
+                amount: "1.5".to_string(),
+                limit: "1".to_string(),
+            }]
+    ));
+    Ok(())
+}
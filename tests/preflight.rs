@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use sol_unmint::{
+    Asset, MintExtension, MintInfo, Requirements, Shortfall, TokenProgram, UnmintError,
+};
+use solana_sdk::{
+    account::Account,
+    message::Message,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use spl_token::state::{Account as TokenAccount, AccountState};
+
+fn mint(token_program: TokenProgram, extensions: Vec<MintExtension>) -> MintInfo {
+    MintInfo {
+        address: Pubkey::new_unique(),
+        token_program,
+        supply: 1_000_000,
+        decimals: 6,
+        mint_authority: None,
+        freeze_authority: None,
+        extensions,
+    }
+}
+
+#[test]
+fn test_shortfall() -> Result<()> {
+    let payer = Pubkey::new_unique();
+    let shortfall = Shortfall {
+        account: payer,
+        asset: Asset::Sol,
+        amount: 1_000_000,
+        fee: 5_000,
+        rent: 2_039_280,
+        available: 2_000_000,
+    };
+
+    assert_eq!(shortfall.required(), 3_044_280);
+    assert_eq!(shortfall.missing(), 1_044_280);
+    assert_eq!(
+        UnmintError::Shortfall(vec![shortfall]).to_string(),
+        format!(
+            "insufficient funds: {} has 0.002 SOL but needs 0.00304428 SOL (fee 0.000005 SOL, rent 0.00203928 SOL)",
+            payer
+        )
+    );
+    Ok(())
+}
+
+#[test]
+fn test_token_shortfall() -> Result<()> {
+    let account = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let shortfall = Shortfall {
+        account,
+        asset: Asset::Token { mint, decimals: 6 },
+        amount: 1_500_000,
+        fee: 0,
+        rent: 0,
+        available: 1_000_000,
+    };
+
+    assert_eq!(shortfall.missing(), 500_000);
+    assert_eq!(
+        shortfall.to_string(),
+        format!("{} has 1 but needs 1.5 of {}", account, mint)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_token_account_len() -> Result<()> {
+    assert_eq!(mint(TokenProgram::Legacy, vec![]).token_account_len()?, 165);
+    // ImmutableOwner is always added by the associated token account program
+    assert_eq!(
+        mint(TokenProgram::Token2022, vec![]).token_account_len()?,
+        170
+    );
+    assert_eq!(
+        mint(
+            TokenProgram::Token2022,
+            vec![
+                MintExtension::NonTransferable,
+                MintExtension::TransferHook {
+                    authority: None,
+                    program_id: None,
+                },
+            ]
+        )
+        .token_account_len()?,
+        179
+    );
+    Ok(())
+}
+
+#[test]
+fn test_requirements_check() -> Result<()> {
+    let prog = TokenProgram::Legacy;
+    let payer = Pubkey::new_unique();
+    let sender = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let mint = mint(prog, vec![]);
+
+    let source = prog.ata(&sender.pubkey(), &mint.address);
+    let destination = prog.ata(&recipient, &mint.address);
+    let message = Message::new(
+        &[
+            prog.create_ata_idempotent_ix(&payer, &recipient, &mint.address),
+            prog.transfer_ix(&source, &destination, &sender, 1_500_000, 6, &mint.address)?,
+        ],
+        Some(&payer),
+    );
+
+    let requirements = Requirements::from_message(&message);
+
+    let mut addresses = vec![payer, source, destination];
+    addresses.sort();
+    assert_eq!(requirements.addresses(), addresses);
+
+    let token_account = |amount| {
+        let mut data = vec![0; TokenAccount::LEN];
+        TokenAccount {
+            mint: mint.address,
+            owner: sender.pubkey(),
+            amount,
+            state: AccountState::Initialized,
+            ..TokenAccount::default()
+        }
+        .pack_into_slice(&mut data);
+        Account {
+            lamports: 2_039_280,
+            data,
+            owner: prog.program_id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    };
+    let wallet = |lamports| Account {
+        lamports,
+        ..Account::default()
+    };
+    let accounts = HashMap::from([
+        (payer, wallet(1_000_000)),
+        (source, token_account(1_000_000)),
+    ]);
+
+    assert_eq!(requirements.mints(&accounts), vec![mint.address]);
+    let mints = HashMap::from([(mint.address, mint.clone())]);
+    assert_eq!(requirements.new_account_lens(&accounts, &mints)?, vec![165]);
+    let rent = HashMap::from([(165, 2_039_280)]);
+
+    // the payer cannot cover the fee and the new ATA, the sender lacks 0.5 tokens
+    let checked = requirements.check(5_000, &accounts, &mints, &rent);
+    let Err(UnmintError::Shortfall(shortfalls)) = checked else {
+        panic!("expected a shortfall, got {:?}", checked);
+    };
+    assert_eq!(
+        shortfalls,
+        vec![
+            Shortfall {
+                account: payer,
+                asset: Asset::Sol,
+                amount: 0,
+                fee: 5_000,
+                rent: 2_039_280,
+                available: 1_000_000,
+            },
+            Shortfall {
+                account: source,
+                asset: Asset::Token {
+                    mint: mint.address,
+                    decimals: 6,
+                },
+                amount: 1_500_000,
+                fee: 0,
+                rent: 0,
+                available: 1_000_000,
+            },
+        ]
+    );
+
+    // once the destination exists only the fee is due, and enough tokens pass
+    let funded = HashMap::from([
+        (payer, wallet(5_000)),
+        (source, token_account(1_500_000)),
+        (destination, token_account(0)),
+    ]);
+    assert!(requirements.check(5_000, &funded, &mints, &rent).is_ok());
+    Ok(())
+}
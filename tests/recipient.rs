@@ -0,0 +1,108 @@
+use anyhow::Result;
+use sol_unmint::{Recipient, RecipientKind, TokenProgram, UnmintError, validate_recipient};
+use solana_sdk::{
+    account::Account, program_pack::Pack, pubkey::Pubkey, signature::Keypair, signer::Signer,
+};
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+fn token_account(mint: Pubkey) -> Result<Account> {
+    let mut data = vec![0; TokenAccount::LEN];
+    TokenAccount::pack(
+        TokenAccount {
+            mint,
+            owner: Keypair::new().pubkey(),
+            state: AccountState::Initialized,
+            ..TokenAccount::default()
+        },
+        &mut data,
+    )?;
+
+    Ok(Account {
+        data,
+        owner: TokenProgram::Legacy.program_id(),
+        ..Account::default()
+    })
+}
+
+#[test]
+fn test_wallet_recipient() -> Result<()> {
+    let mint = Pubkey::new_unique();
+    let wallet = Keypair::new().pubkey();
+
+    assert_eq!(
+        validate_recipient(&wallet, None, &mint, false)?,
+        Recipient::Wallet(wallet)
+    );
+
+    let (pda, _) = Pubkey::find_program_address(&[b"vault"], &Pubkey::new_unique());
+    assert!(matches!(
+        validate_recipient(&pda, None, &mint, false),
+        Err(UnmintError::InvalidRecipient {
+            kind: RecipientKind::OffCurve,
+            ..
+        })
+    ));
+    assert_eq!(
+        validate_recipient(&pda, None, &mint, true)?,
+        Recipient::Wallet(pda)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_token_account_recipient() -> Result<()> {
+    let mint = Pubkey::new_unique();
+    let other_mint = Pubkey::new_unique();
+    let address = Pubkey::new_unique();
+
+    assert_eq!(
+        validate_recipient(&address, Some(&token_account(mint)?), &mint, false)?,
+        Recipient::TokenAccount(address)
+    );
+    assert!(matches!(
+        validate_recipient(&address, Some(&token_account(other_mint)?), &mint, false),
+        Err(UnmintError::InvalidRecipient {
+            kind: RecipientKind::TokenAccount { mint },
+            ..
+        }) if mint == other_mint
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_mint_and_program_recipients() -> Result<()> {
+    let mint = Pubkey::new_unique();
+
+    let mut data = vec![0; Mint::LEN];
+    Mint::pack(
+        Mint {
+            is_initialized: true,
+            ..Mint::default()
+        },
+        &mut data,
+    )?;
+    let mint_account = Account {
+        data,
+        owner: TokenProgram::Legacy.program_id(),
+        ..Account::default()
+    };
+    assert!(matches!(
+        validate_recipient(&mint, Some(&mint_account), &mint, false),
+        Err(UnmintError::InvalidRecipient {
+            kind: RecipientKind::Mint,
+            ..
+        })
+    ));
+
+    let program = TokenProgram::Legacy.program_id();
+    let program_account = Account {
+        executable: true,
+        ..Account::default()
+    };
+    let error = validate_recipient(&program, Some(&program_account), &mint, false).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!("cannot send to {}: it is an executable program", program)
+    );
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use anyhow::Result;
+use sol_unmint::{
+    EmptyTokenAccount, LookupTableState, OwnedTokenAccount, ReclaimKind, ReclaimReport,
+    ReclaimableAccount, TokenMetadata, TokenProgram, UnmintError,
+};
+use solana_address_lookup_table_interface::state::{LookupTableMeta, ProgramState};
+use solana_loader_v3_interface::state::UpgradeableLoaderState;
+use solana_nonce::{
+    state::{DurableNonce, State},
+    versions::Versions,
+};
+use solana_sdk::{account::Account, hash::Hash, pubkey::Pubkey, slot_hashes::SlotHashes};
+use solana_sdk_ids::{address_lookup_table, bpf_loader_upgradeable, system_program};
+use spl_token_2022::state::{Account as TokenAccount, AccountState};
+
+use crate::setup_unmint::setup_unmint;
+mod setup_unmint;
+
+fn account(owner: Pubkey, data: Vec<u8>) -> Account {
+    Account {
+        lamports: 1_000_000,
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn decode(account: &Account, slot: u64) -> Result<ReclaimKind> {
+    let decoded =
+        ReclaimableAccount::decode(Pubkey::new_unique(), account, slot, &SlotHashes::default())?;
+    Ok(decoded.kind)
+}
+
+#[test]
+fn test_decode_buffer_and_nonce() -> Result<()> {
+    let authority = Pubkey::new_unique();
+
+    let buffer = bincode::serialize(&UpgradeableLoaderState::Buffer {
+        authority_address: Some(authority),
+    })?;
+    // reclaimable_accounts filters on the tags and the authority
+    assert_eq!(&buffer[..5], &[1, 0, 0, 0, 1]);
+    assert_eq!(&buffer[5..37], authority.as_ref());
+    assert_eq!(
+        decode(&account(bpf_loader_upgradeable::ID, buffer), 0)?,
+        ReclaimKind::Buffer
+    );
+
+    let program = bincode::serialize(&UpgradeableLoaderState::Program {
+        programdata_address: Pubkey::new_unique(),
+    })?;
+    assert!(decode(&account(bpf_loader_upgradeable::ID, program), 0).is_err());
+
+    let nonce = bincode::serialize(&Versions::new(State::new_initialized(
+        &authority,
+        DurableNonce::from_blockhash(&Hash::new_unique()),
+        5_000,
+    )))?;
+    assert_eq!(nonce.len(), 80);
+    assert_eq!(&nonce[8..40], authority.as_ref());
+    assert_eq!(
+        decode(&account(system_program::ID, nonce), 0)?,
+        ReclaimKind::Nonce
+    );
+
+    assert!(decode(&account(Pubkey::new_unique(), vec![]), 0).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_decode_lookup_table() -> Result<()> {
+    let authority = Pubkey::new_unique();
+    let table = |deactivation_slot: u64| -> Result<Account> {
+        let meta = LookupTableMeta {
+            deactivation_slot,
+            ..LookupTableMeta::new(authority)
+        };
+        let data = bincode::serialize(&ProgramState::LookupTable(meta))?;
+        Ok(account(address_lookup_table::ID, data))
+    };
+
+    let active = table(u64::MAX)?;
+    assert_eq!(active.data[21], 1);
+    assert_eq!(&active.data[22..54], authority.as_ref());
+    assert_eq!(
+        decode(&active, 1_000)?,
+        ReclaimKind::LookupTable(LookupTableState::Active)
+    );
+
+    assert_eq!(
+        decode(&table(1_000)?, 1_000)?,
+        ReclaimKind::LookupTable(LookupTableState::Deactivating {
+            deactivation_slot: 1_000,
+            closable_after: 1_512,
+        })
+    );
+
+    // the deactivation slot is no longer in the slot hashes
+    assert_eq!(
+        decode(&table(400)?, 1_000)?,
+        ReclaimKind::LookupTable(LookupTableState::Deactivated)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_reclaim_report_lamports() -> Result<()> {
+    let owner = Pubkey::new_unique();
+    let token_account = |amount, state| OwnedTokenAccount {
+        address: Pubkey::new_unique(),
+        token_program: TokenProgram::Legacy,
+        lamports: 2_039_280,
+        account: TokenAccount {
+            mint: Pubkey::new_unique(),
+            owner,
+            amount,
+            state,
+            ..TokenAccount::default()
+        },
+        withheld_amount: 0,
+    };
+    let metadata = TokenMetadata {
+        name: "USD Coin".to_string(),
+        symbol: "USDC".to_string(),
+        uri: String::new(),
+    };
+
+    let empty = token_account(0, AccountState::Initialized);
+    let token = EmptyTokenAccount::of(&empty, Some(&metadata)).expect("closable");
+    assert_eq!(token.label, "USDC");
+    assert_eq!(token.lamports, 2_039_280);
+    assert!(EmptyTokenAccount::of(&token_account(1, AccountState::Initialized), None).is_none());
+    assert!(EmptyTokenAccount::of(&token_account(0, AccountState::Frozen), None).is_none());
+
+    let report = ReclaimReport {
+        stake: vec![],
+        accounts: vec![
+            ReclaimableAccount {
+                address: Pubkey::new_unique(),
+                kind: ReclaimKind::Buffer,
+                lamports: 1_500_000,
+            },
+            ReclaimableAccount {
+                address: Pubkey::new_unique(),
+                kind: ReclaimKind::Nonce,
+                lamports: 1_447_680,
+            },
+        ],
+        tokens: vec![token],
+    };
+    assert_eq!(report.lamports(), 4_986_960);
+    Ok(())
+}
+
+#[test]
+fn test_reclaim_accounts_invalid_key() -> Result<()> {
+    let unmint = setup_unmint(TokenProgram::Legacy);
+
+    let reclaim = unmint.reclaim_accounts(
+        "", //Base58 private key of the buffer, nonce and lookup table authority
+        "57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj",
+        None,
+    );
+
+    assert!(matches!(reclaim, Err(UnmintError::InvalidKey)));
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use sol_unmint::{OwnedTokenAccount, RetirePolicy, TokenDisposition, TokenProgram, UnmintError};
+use solana_sdk::{program_option::COption, pubkey::Pubkey};
+use spl_token_2022::state::Account as TokenAccount;
+
+use crate::setup_unmint::setup_unmint;
+mod setup_unmint;
+
+#[test]
+fn test_retire_wallet() -> Result<()> {
+    let unmint = setup_unmint(TokenProgram::Legacy);
+
+    let spam_mint = Pubkey::new_unique();
+    let mut policy = RetirePolicy::default();
+    policy.overrides.insert(spam_mint, TokenDisposition::Burn);
+
+    assert_eq!(policy.disposition(&spam_mint), TokenDisposition::Burn);
+    assert_eq!(
+        policy.disposition(&Pubkey::new_unique()),
+        TokenDisposition::Transfer
+    );
+
+    let receipt = unmint.retire_wallet(
+        "", //Base58 private key of the wallet to retire
+        "57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj",
+        &policy,
+    );
+
+    assert!(matches!(receipt, Err(UnmintError::InvalidKey)));
+    Ok(())
+}
+
+#[test]
+fn test_token_account_rent() -> Result<()> {
+    let token_account = |amount, is_native| OwnedTokenAccount {
+        address: Pubkey::new_unique(),
+        token_program: TokenProgram::Legacy,
+        lamports: 2_039_280 + amount,
+        account: TokenAccount {
+            mint: spl_token::native_mint::ID,
+            amount,
+            is_native,
+            ..TokenAccount::default()
+        },
+        withheld_amount: 0,
+    };
+
+    // wrapped SOL: only the reserve is rent, the wrapped lamports go back as SOL
+    let wsol = token_account(1_500_000_000, COption::Some(2_039_280));
+    assert_eq!(wsol.rent(), 2_039_280);
+
+    let empty_wsol = token_account(0, COption::Some(2_039_280));
+    assert_eq!(empty_wsol.rent(), 2_039_280);
+
+    let regular = token_account(0, COption::None);
+    assert_eq!(regular.rent(), 2_039_280);
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use anyhow::Result;
+use sol_unmint::{
+    MintExtension, MintInfo, OwnedTokenAccount, RetirePolicy, RiskConfig, RiskFlag, RiskLevel,
+    TokenDisposition, TokenMetadata, TokenProgram, assess_token,
+};
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::state::Account as TokenAccount;
+
+fn token_account(mint: Pubkey) -> OwnedTokenAccount {
+    OwnedTokenAccount {
+        address: Pubkey::new_unique(),
+        token_program: TokenProgram::Token2022,
+        lamports: 2_039_280,
+        account: TokenAccount {
+            mint,
+            amount: 1_000,
+            ..TokenAccount::default()
+        },
+        withheld_amount: 0,
+    }
+}
+
+fn mint(address: Pubkey, decimals: u8, extensions: Vec<MintExtension>) -> MintInfo {
+    MintInfo {
+        address,
+        token_program: TokenProgram::Token2022,
+        supply: 1_000_000,
+        decimals,
+        mint_authority: None,
+        freeze_authority: None,
+        extensions,
+    }
+}
+
+fn metadata(symbol: &str, uri: &str) -> TokenMetadata {
+    TokenMetadata {
+        name: symbol.to_string(),
+        symbol: symbol.to_string(),
+        uri: uri.to_string(),
+    }
+}
+
+#[test]
+fn test_impersonated_ticker() -> Result<()> {
+    let fake = Pubkey::new_unique();
+
+    let report = assess_token(
+        &token_account(fake),
+        &mint(fake, 6, vec![]),
+        Some(&metadata("usdc", "http://usdc-claim.xyz/meta.json")),
+        &RiskConfig::default(),
+    );
+
+    assert!(
+        report
+            .flags
+            .contains(&RiskFlag::ImpersonatedTicker("usdc".to_string()))
+    );
+    assert!(report.flags.contains(&RiskFlag::SuspiciousMetadata(
+        "http://usdc-claim.xyz/meta.json".to_string()
+    )));
+    assert_eq!(report.level(), Some(RiskLevel::High));
+    Ok(())
+}
+
+#[test]
+fn test_genuine_token_is_informational() -> Result<()> {
+    let usdc: Pubkey = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".parse()?;
+    let freeze_authority = Pubkey::new_unique();
+    let mut usdc_mint = mint(usdc, 6, vec![]);
+    usdc_mint.freeze_authority = Some(freeze_authority);
+
+    let report = assess_token(
+        &token_account(usdc),
+        &usdc_mint,
+        Some(&metadata("USDC", "https://example.org/usdc.json")),
+        &RiskConfig::default(),
+    );
+
+    assert_eq!(report.label, "USDC");
+    assert_eq!(
+        report.flags,
+        vec![RiskFlag::FreezeAuthority(freeze_authority)]
+    );
+    assert_eq!(report.level(), Some(RiskLevel::Info));
+    Ok(())
+}
+
+#[test]
+fn test_lure_words_match_whole_words() -> Result<()> {
+    let address = Pubkey::new_unique();
+    let assess = |name: &str| {
+        let metadata = TokenMetadata {
+            name: name.to_string(),
+            symbol: "TKN".to_string(),
+            uri: "https://example.org/token.json".to_string(),
+        };
+        assess_token(
+            &token_account(address),
+            &mint(address, 6, vec![]),
+            Some(&metadata),
+            &RiskConfig::default(),
+        )
+        .flags
+    };
+
+    for legitimate in ["Freedom", "Rewards Points", "Claimable"] {
+        assert_eq!(assess(legitimate), vec![], "{}", legitimate);
+    }
+    assert_eq!(
+        assess("Claim 500 SOL"),
+        vec![RiskFlag::SuspiciousMetadata("Claim 500 SOL".to_string())]
+    );
+    assert_eq!(
+        assess("free-airdrop"),
+        vec![RiskFlag::SuspiciousMetadata("free-airdrop".to_string())]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_extension_flags() -> Result<()> {
+    let address = Pubkey::new_unique();
+    let delegate = Pubkey::new_unique();
+    let hook_program = Pubkey::new_unique();
+    let spam = mint(
+        address,
+        0,
+        vec![
+            MintExtension::PermanentDelegate {
+                delegate: Some(delegate),
+            },
+            MintExtension::TransferHook {
+                authority: None,
+                program_id: Some(hook_program),
+            },
+        ],
+    );
+
+    let report = assess_token(&token_account(address), &spam, None, &RiskConfig::default());
+    assert_eq!(
+        report.flags,
+        vec![
+            RiskFlag::PermanentDelegate(delegate),
+            RiskFlag::UnknownTransferHook(hook_program),
+            RiskFlag::NftSpam,
+        ]
+    );
+
+    let mut config = RiskConfig::default();
+    config.trusted_hook_programs.insert(hook_program);
+    let report = assess_token(&token_account(address), &spam, None, &config);
+    assert!(
+        !report
+            .flags
+            .contains(&RiskFlag::UnknownTransferHook(hook_program))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_apply_risk_to_policy() -> Result<()> {
+    let config = RiskConfig::default();
+    let scam = Pubkey::new_unique();
+    let soulbound = Pubkey::new_unique();
+    let plain = Pubkey::new_unique();
+
+    let reports = [
+        assess_token(
+            &token_account(scam),
+            &mint(scam, 6, vec![]),
+            Some(&metadata("SOL", "https://claim.example/sol.json")),
+            &config,
+        ),
+        assess_token(
+            &token_account(soulbound),
+            &mint(soulbound, 6, vec![MintExtension::NonTransferable]),
+            None,
+            &config,
+        ),
+        assess_token(
+            &token_account(plain),
+            &mint(plain, 6, vec![]),
+            None,
+            &config,
+        ),
+    ];
+
+    let mut policy = RetirePolicy::default();
+    policy.apply_risk(&reports, RiskLevel::High, TokenDisposition::Ignore);
+
+    assert_eq!(policy.disposition(&scam), TokenDisposition::Ignore);
+    assert_eq!(policy.disposition(&soulbound), TokenDisposition::Burn);
+    assert_eq!(policy.disposition(&plain), TokenDisposition::Transfer);
+    Ok(())
+}
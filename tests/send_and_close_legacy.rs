@@ -13,6 +13,7 @@ fn test_send_and_close_legacy() -> Result<()> {
         "",                                             //Base58 private key of the recipient
         "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB", //usdc mint token address or other
         None,                                           // sender fee payer
+        None,                                           // memo
     )?;
 
     println!("Transaction signature: {:?}", tx_sig);
@@ -13,6 +13,7 @@ fn test_send_and_close_token2022() -> Result<()> {
         "",                                            //Base58 private key of the recipient
         "pumpCmXqMfrsAkQ5r49WcJnRayYRqmXz6ae8H7H9Dfn", //usdc mint token address or other
         None,                                          // sender fee payer
+        None,                                          // memo
     )?;
 
     println!("Transaction signature: {:?}", tx_sig);
@@ -8,7 +8,12 @@ mod setup_unmint;
 fn test_send_sol() -> Result<()> {
     let unmint = setup_unmint(TokenProgram::Legacy);
 
-    let tx_sig = unmint.transfer_sol("", "57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj", 0.001);
+    let tx_sig = unmint.transfer_sol(
+        "",
+        "57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj",
+        0.001,
+        None,
+    );
 
     println!("Transaction signature: {:?}", tx_sig);
     Ok(())
@@ -0,0 +1,65 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sol_unmint::{
+    SignInExpectation, SignInMessage, UnmintError, Wallet, WalletKey, generate, verify_message,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+#[test]
+fn test_sign_message() -> Result<()> {
+    let generated = generate();
+    let wallet = Wallet::new(WalletKey::StringKey(generated.base64_private_key));
+    let address = Pubkey::from_str(&wallet.address())?;
+
+    let signature = wallet.sign_message(b"hello unmint")?;
+
+    assert!(verify_message(&address, b"hello unmint", &signature)?);
+    assert!(!verify_message(&address, b"hello world", &signature)?);
+    Ok(())
+}
+
+#[test]
+fn test_sign_in() -> Result<()> {
+    let generated = generate();
+    let wallet = Wallet::new(WalletKey::StringKey(generated.base64_private_key));
+    let address = Pubkey::from_str(&wallet.address())?;
+
+    let mut message = SignInMessage::new("example.com", address);
+    message.statement = Some("Sign in to example".to_string());
+    message.uri = Some("https://example.com/login".to_string());
+    message.nonce = Some("32891756".to_string());
+    message.issued_at = Some("2026-01-01T00:00:00Z".to_string());
+    message.resources = vec!["https://example.com/terms".to_string()];
+
+    let signature = wallet.sign_in(&message)?;
+    let text = message.to_message();
+
+    let now = "2026-01-02T00:00:00Z".parse::<DateTime<Utc>>()?;
+    let mut expected = SignInExpectation::new("example.com", now);
+    expected.address = Some(&address);
+    expected.nonce = Some("32891756");
+    let parsed = SignInMessage::verify(&text, &signature, &expected)?;
+    assert_eq!(parsed, message);
+
+    let mut wrong_nonce = SignInExpectation::new("example.com", now);
+    wrong_nonce.nonce = Some("1");
+    assert!(SignInMessage::verify(&text, &signature, &wrong_nonce).is_err());
+
+    // the domain and the clock are always checked
+    let other_domain = SignInExpectation::new("evil.example", now);
+    assert!(matches!(
+        SignInMessage::verify(&text, &signature, &other_domain),
+        Err(UnmintError::SignInRejected(_))
+    ));
+
+    message.expiration_time = Some("2026-01-01T12:00:00Z".to_string());
+    let signature = wallet.sign_in(&message)?;
+    let expired = SignInMessage::verify(
+        &message.to_message(),
+        &signature,
+        &SignInExpectation::new("example.com", now),
+    );
+    assert!(matches!(expired, Err(UnmintError::SignInRejected(_))));
+    Ok(())
+}
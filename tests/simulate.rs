@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use sol_unmint::{
+    BalanceChange, TokenAmount, TokenBalanceChange, TokenProgram, UnmintError, balance_changes,
+};
+use solana_sdk::{account::Account, program_pack::Pack, pubkey::Pubkey};
+use spl_token::state::{Account as TokenAccount, AccountState};
+
+use crate::setup_unmint::setup_unmint;
+mod setup_unmint;
+
+fn token_account(mint: Pubkey, amount: u64) -> Account {
+    let mut data = vec![0; TokenAccount::LEN];
+    TokenAccount {
+        mint,
+        owner: Pubkey::new_unique(),
+        amount,
+        state: AccountState::Initialized,
+        ..TokenAccount::default()
+    }
+    .pack_into_slice(&mut data);
+
+    Account {
+        lamports: 2_039_280,
+        data,
+        owner: spl_token::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+#[test]
+fn test_balance_changes() -> Result<()> {
+    let mint = Pubkey::new_unique();
+    let (payer, source, destination) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let wallet = |lamports| Account {
+        lamports,
+        ..Account::default()
+    };
+
+    // the payer creates the destination ATA and the whole balance moves there,
+    // then the emptied source is closed
+    let changes = balance_changes(
+        &[payer, source, destination],
+        &[
+            Some(wallet(10_000_000)),
+            Some(token_account(mint, 1_500_000)),
+            None,
+        ],
+        &[
+            Some(wallet(10_000_000 - 5_000)),
+            None,
+            Some(token_account(mint, 1_500_000)),
+        ],
+        &HashMap::from([(mint, 6)]),
+    );
+
+    assert_eq!(
+        changes,
+        vec![
+            BalanceChange {
+                address: payer,
+                lamports_before: 10_000_000,
+                lamports_after: 9_995_000,
+                token: None,
+            },
+            BalanceChange {
+                address: source,
+                lamports_before: 2_039_280,
+                lamports_after: 0,
+                token: Some(TokenBalanceChange {
+                    mint,
+                    before: TokenAmount::from_raw(1_500_000, 6),
+                    after: TokenAmount::from_raw(0, 6),
+                }),
+            },
+            BalanceChange {
+                address: destination,
+                lamports_before: 0,
+                lamports_after: 2_039_280,
+                token: Some(TokenBalanceChange {
+                    mint,
+                    before: TokenAmount::from_raw(0, 6),
+                    after: TokenAmount::from_raw(1_500_000, 6),
+                }),
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_simulate_send_token() -> Result<()> {
+    let unmint = setup_unmint(TokenProgram::Token2022);
+
+    let simulation = unmint.simulate_send_token(
+        "", //Base58 private key of the sender
+        "57ksuWYrkEnrUDfisoPYw6Wb1hmsjFBYSwv9HULex1yj",
+        "not a mint",
+        "0.1",
+        None,
+        None,
+    );
+
+    assert!(matches!(simulation, Err(UnmintError::InvalidPubkey(_))));
+    Ok(())
+}
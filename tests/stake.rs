@@ -0,0 +1,141 @@
+use anyhow::Result;
+use sol_unmint::{StakeAccount, StakeActivation, UnmintError};
+use solana_sdk::{account::Account, clock::Clock, pubkey::Pubkey};
+use solana_stake_interface::{
+    program::ID as STAKE_PROGRAM_ID,
+    stake_flags::StakeFlags,
+    stake_history::StakeHistory,
+    state::{Authorized, Delegation, Lockup, Meta, Stake, StakeStateV2},
+};
+
+fn clock(epoch: u64) -> Clock {
+    Clock {
+        epoch,
+        unix_timestamp: 1_700_000_000,
+        ..Clock::default()
+    }
+}
+
+fn stake_account(state: &StakeStateV2) -> Result<Account> {
+    let mut data = bincode::serialize(state)?;
+    data.resize(StakeStateV2::size_of(), 0);
+
+    Ok(Account {
+        lamports: 5_002_282_880,
+        data,
+        owner: STAKE_PROGRAM_ID,
+        executable: false,
+        rent_epoch: 0,
+    })
+}
+
+fn delegated(meta: Meta, activation_epoch: u64, deactivation_epoch: u64) -> StakeStateV2 {
+    let stake = Stake {
+        delegation: Delegation {
+            deactivation_epoch,
+            ..Delegation::new(&Pubkey::new_unique(), 5_000_000_000, activation_epoch)
+        },
+        credits_observed: 0,
+    };
+    StakeStateV2::Stake(meta, stake, StakeFlags::empty())
+}
+
+#[test]
+fn test_stake_activation() -> Result<()> {
+    let meta = Meta {
+        rent_exempt_reserve: 2_282_880,
+        authorized: Authorized::auto(&Pubkey::new_unique()),
+        lockup: Lockup::default(),
+    };
+    let history = StakeHistory::default();
+
+    let of = |state: &StakeStateV2| StakeActivation::of(state, 10, &history);
+    assert_eq!(
+        of(&StakeStateV2::Initialized(meta)),
+        StakeActivation::Inactive
+    );
+    assert_eq!(
+        of(&delegated(meta, 10, u64::MAX)),
+        StakeActivation::Activating
+    );
+    assert_eq!(of(&delegated(meta, 5, u64::MAX)), StakeActivation::Active);
+    assert_eq!(of(&delegated(meta, 5, 10)), StakeActivation::Deactivating);
+    assert_eq!(of(&delegated(meta, 5, 8)), StakeActivation::Inactive);
+    Ok(())
+}
+
+#[test]
+fn test_stake_account_decode() -> Result<()> {
+    let staker = Pubkey::new_unique();
+    let withdrawer = Pubkey::new_unique();
+    let meta = Meta {
+        rent_exempt_reserve: 2_282_880,
+        authorized: Authorized { staker, withdrawer },
+        lockup: Lockup::default(),
+    };
+
+    let account = stake_account(&delegated(meta, 5, u64::MAX))?;
+    // stake_accounts filters on the withdrawer at this offset
+    assert_eq!(&account.data[44..76], withdrawer.as_ref());
+
+    let address = Pubkey::new_unique();
+    let stake = StakeAccount::decode(address, &account, &clock(10), &StakeHistory::default())?;
+
+    assert_eq!(stake.address, address);
+    assert_eq!(stake.staker, staker);
+    assert_eq!(stake.withdrawer, withdrawer);
+    assert!(stake.voter.is_some());
+    assert_eq!(stake.activation, StakeActivation::Active);
+    assert_eq!(stake.lockup, None);
+
+    let not_stake = Account {
+        owner: Pubkey::new_unique(),
+        ..account
+    };
+    assert!(
+        StakeAccount::decode(address, &not_stake, &clock(10), &StakeHistory::default()).is_err()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_stake_lockup() -> Result<()> {
+    let lockup = Lockup {
+        unix_timestamp: 0,
+        epoch: 20,
+        custodian: Pubkey::new_unique(),
+    };
+    let meta = Meta {
+        rent_exempt_reserve: 2_282_880,
+        authorized: Authorized::auto(&Pubkey::new_unique()),
+        lockup,
+    };
+    let account = stake_account(&StakeStateV2::Initialized(meta))?;
+    let address = Pubkey::new_unique();
+    let history = StakeHistory::default();
+
+    // inactive, but reclaim_stake leaves it waiting until the lockup ends
+    let locked = StakeAccount::decode(address, &account, &clock(10), &history)?;
+    assert_eq!(locked.activation, StakeActivation::Inactive);
+    assert_eq!(locked.lockup, Some(lockup));
+
+    let unlocked = StakeAccount::decode(address, &account, &clock(20), &history)?;
+    assert_eq!(unlocked.lockup, None);
+
+    assert_eq!(
+        UnmintError::StakeLocked { address, lockup }.to_string(),
+        format!(
+            "stake account {} is locked until epoch 20 and unix time 0",
+            address
+        )
+    );
+    let err = UnmintError::StakeNotInactive {
+        address,
+        activation: StakeActivation::Deactivating,
+    };
+    assert!(
+        err.to_string()
+            .ends_with("is deactivating, only inactive stake can be withdrawn")
+    );
+    Ok(())
+}
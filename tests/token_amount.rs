@@ -0,0 +1,76 @@
+use anyhow::Result;
+use sol_unmint::{AmountInput, Lamports, TokenAmount, UnmintError};
+
+#[test]
+fn test_token_amount_parse() -> Result<()> {
+    assert_eq!(TokenAmount::parse("0.1", 6)?.raw(), 100_000);
+    assert_eq!(TokenAmount::parse("0.3", 9)?.raw(), 300_000_000);
+    assert_eq!(TokenAmount::parse("12", 0)?.raw(), 12);
+    assert_eq!(TokenAmount::parse("1.500", 2)?.raw(), 150);
+    assert_eq!(TokenAmount::parse(".5", 1)?.raw(), 5);
+    assert_eq!(TokenAmount::parse("0.000000000000000001", 18)?.raw(), 1);
+
+    assert!(matches!(
+        TokenAmount::parse("0.1234567", 6),
+        Err(UnmintError::InvalidAmount(_))
+    ));
+    assert!(matches!(
+        TokenAmount::parse("18446744073709551616", 0),
+        Err(UnmintError::InvalidAmount(_))
+    ));
+    assert!(matches!(
+        TokenAmount::parse("1", 20),
+        Err(UnmintError::InvalidAmount(_))
+    ));
+    assert!(TokenAmount::parse("-1", 6).is_err());
+    assert!(TokenAmount::parse("1e5", 6).is_err());
+    assert!(TokenAmount::parse("", 6).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_token_amount_format() -> Result<()> {
+    assert_eq!(TokenAmount::from_raw(100_000, 6).to_string(), "0.1");
+    assert_eq!(TokenAmount::from_raw(1_000_000, 6).to_string(), "1");
+    assert_eq!(TokenAmount::from_raw(1_234_567, 6).to_string(), "1.234567");
+    assert_eq!(TokenAmount::from_raw(42, 0).to_string(), "42");
+    assert_eq!(Lamports(1_000_000).to_string(), "0.001 SOL");
+    assert_eq!("0.001".parse::<Lamports>()?, Lamports(1_000_000));
+    Ok(())
+}
+
+#[test]
+fn test_amount_input() -> Result<()> {
+    assert_eq!(AmountInput::from(0.1).to_token_amount(6)?.raw(), 100_000);
+    assert_eq!(
+        AmountInput::from(0.3).to_token_amount(9)?.raw(),
+        300_000_000
+    );
+    assert_eq!(AmountInput::from(5u64).to_token_amount(6)?.raw(), 5);
+    assert!(AmountInput::from(-1.0).to_token_amount(6).is_err());
+    assert!(
+        AmountInput::from(TokenAmount::from_raw(1, 6))
+            .to_token_amount(9)
+            .is_err()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_lamports_amount_input() -> Result<()> {
+    let lamports = Lamports::from_sol_str("0.000000001")?;
+    assert_eq!(lamports, Lamports(1));
+    assert_eq!(lamports.to_sol_string(), "0.000000001");
+
+    // a Lamports amount is already exact and passes through unchanged for SOL
+    let amount = AmountInput::from(Lamports(1_234_567_891)).to_token_amount(9)?;
+    assert_eq!(amount.raw(), 1_234_567_891);
+    assert_eq!(amount.to_string(), "1.234567891");
+
+    // but it cannot be sent as a token with other decimals
+    assert!(matches!(
+        AmountInput::from(Lamports(1)).to_token_amount(6),
+        Err(UnmintError::InvalidAmount(_))
+    ));
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use anyhow::Result;
+use sol_unmint::{MintInfo, TokenMetadata, TokenProgram, token_label};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use spl_token_2022::{
+    extension::{
+        BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+        metadata_pointer::MetadataPointer,
+    },
+    state::Mint,
+};
+use spl_token_metadata_interface::state::TokenMetadata as SplTokenMetadata;
+
+fn borsh_string(data: &mut Vec<u8>, value: &str, padded_len: usize) {
+    let padded = format!("{:\0<width$}", value, width = padded_len);
+    data.extend((padded.len() as u32).to_le_bytes());
+    data.extend(padded.as_bytes());
+}
+
+fn metaplex_data(mint: &Pubkey, name: &str, symbol: &str, uri: &str) -> Vec<u8> {
+    let mut data = vec![4];
+    data.extend(Pubkey::new_unique().to_bytes());
+    data.extend(mint.to_bytes());
+    borsh_string(&mut data, name, 32);
+    borsh_string(&mut data, symbol, 10);
+    borsh_string(&mut data, uri, 200);
+    data.extend([0; 64]);
+    data
+}
+
+#[test]
+fn test_metaplex_metadata() -> Result<()> {
+    let mint = Pubkey::new_unique();
+    let data = metaplex_data(&mint, "USD Coin", "USDC", "https://example.org/usdc.json");
+
+    assert_eq!(
+        TokenMetadata::from_metaplex(&mint, &data),
+        Some(TokenMetadata {
+            name: "USD Coin".to_string(),
+            symbol: "USDC".to_string(),
+            uri: "https://example.org/usdc.json".to_string(),
+        })
+    );
+    assert_eq!(
+        TokenMetadata::from_metaplex(&Pubkey::new_unique(), &data),
+        None
+    );
+    assert_eq!(TokenMetadata::from_metaplex(&mint, &data[..80]), None);
+    Ok(())
+}
+
+#[test]
+fn test_token2022_embedded_metadata() -> Result<()> {
+    let address = Pubkey::new_unique();
+    let metadata = SplTokenMetadata {
+        mint: address,
+        name: "Paxos USD".to_string(),
+        symbol: "PYUSD".to_string(),
+        uri: "https://example.org/pyusd.json".to_string(),
+        ..SplTokenMetadata::default()
+    };
+
+    let len = ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::MetadataPointer])?
+        + metadata.tlv_size_of()?;
+    let mut data = vec![0; len];
+    let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data)?;
+    state
+        .init_extension::<MetadataPointer>(true)?
+        .metadata_address = Some(address).try_into()?;
+    state.base = Mint {
+        decimals: 6,
+        is_initialized: true,
+        ..Mint::default()
+    };
+    state.pack_base();
+    state.init_account_type()?;
+    state.init_variable_len_extension(&metadata, false)?;
+
+    let account = Account {
+        data,
+        owner: TokenProgram::Token2022.program_id(),
+        ..Account::default()
+    };
+    let info = MintInfo::decode(address, &account)?;
+
+    assert_eq!(info.metadata_address(), Some(address));
+    assert_eq!(
+        info.metadata(),
+        Some(&TokenMetadata::new(
+            "Paxos USD",
+            "PYUSD",
+            "https://example.org/pyusd.json"
+        ))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_token_label() -> Result<()> {
+    let usdc: Pubkey = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".parse()?;
+
+    assert_eq!(token_label(None, &usdc), "EPjF…Dt1v");
+    assert_eq!(
+        token_label(Some(&TokenMetadata::new("USD Coin", "USDC", "")), &usdc),
+        "USDC"
+    );
+    assert_eq!(
+        token_label(Some(&TokenMetadata::new("USD Coin", "", "")), &usdc),
+        "USD Coin"
+    );
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use anyhow::Result;
+use futures::executor::block_on;
+use sol_unmint::{FeeMode, FeeQuote, TokenProgram};
+use solana_sdk::{
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use spl_token_2022::{
+    extension::{
+        BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+        transfer_fee::{TransferFee, TransferFeeConfig, instruction::transfer_checked_with_fee},
+    },
+    state::Mint,
+};
+
+fn fee_mint(basis_points: u16, newer_epoch: u64, newer_basis_points: u16) -> Result<Vec<u8>> {
+    let len =
+        ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::TransferFeeConfig])?;
+    let mut data = vec![0; len];
+    let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data)?;
+
+    let config = state.init_extension::<TransferFeeConfig>(true)?;
+    config.older_transfer_fee = TransferFee {
+        epoch: 0.into(),
+        maximum_fee: 1_000_000.into(),
+        transfer_fee_basis_points: basis_points.into(),
+    };
+    config.newer_transfer_fee = TransferFee {
+        epoch: newer_epoch.into(),
+        maximum_fee: 1_000_000.into(),
+        transfer_fee_basis_points: newer_basis_points.into(),
+    };
+
+    state.base = Mint {
+        decimals: 6,
+        is_initialized: true,
+        ..Mint::default()
+    };
+    state.pack_base();
+    state.init_account_type()?;
+
+    Ok(data)
+}
+
+#[test]
+fn test_fee_quote_send_exact() -> Result<()> {
+    let quote = FeeQuote::new(&fee_mint(100, 0, 100)?, 10, 10_000, FeeMode::SendExact)?;
+
+    assert_eq!(quote.sent, 10_000);
+    assert_eq!(quote.fee, Some(100));
+    assert_eq!(quote.received(), 9_900);
+    Ok(())
+}
+
+#[test]
+fn test_fee_quote_receive_exact() -> Result<()> {
+    let quote = FeeQuote::new(&fee_mint(100, 0, 100)?, 10, 9_900, FeeMode::ReceiveExact)?;
+
+    assert_eq!(quote.sent, 10_000);
+    assert_eq!(quote.fee, Some(100));
+    assert_eq!(quote.received(), 9_900);
+    Ok(())
+}
+
+#[test]
+fn test_fee_quote_uses_epoch_fee() -> Result<()> {
+    let mint = fee_mint(100, 20, 50)?;
+
+    let before = FeeQuote::new(&mint, 19, 10_000, FeeMode::SendExact)?;
+    let after = FeeQuote::new(&mint, 20, 10_000, FeeMode::SendExact)?;
+
+    assert_eq!(before.fee, Some(100));
+    assert_eq!(after.fee, Some(50));
+    Ok(())
+}
+
+#[test]
+fn test_fee_quote_without_fee_config() -> Result<()> {
+    let mut data = vec![0; Mint::LEN];
+    Mint::pack(
+        Mint {
+            decimals: 6,
+            is_initialized: true,
+            ..Mint::default()
+        },
+        &mut data,
+    )?;
+
+    let quote = FeeQuote::new(&data, 10, 10_000, FeeMode::ReceiveExact)?;
+
+    assert_eq!(quote, FeeQuote::without_fee(10_000));
+    assert_eq!(quote.received(), 10_000);
+    Ok(())
+}
+
+#[test]
+fn test_send_token_with_fee() -> Result<()> {
+    let prog = TokenProgram::Token2022;
+    let mint_data = fee_mint(100, 0, 100)?;
+    let authority = Keypair::new();
+    let (from, to, mint) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+
+    // the recipient gets exactly 1.5 tokens, the sender pays the fee on top
+    let quote = FeeQuote::new(&mint_data, 3, 1_500_000, FeeMode::ReceiveExact)?;
+    assert_eq!(quote.received(), 1_500_000);
+
+    let ix = block_on(prog.transfer_ix_with_extra_metas(
+        &from,
+        &to,
+        &authority,
+        quote.sent,
+        6,
+        &mint,
+        quote.fee,
+        |_| std::future::ready(Ok(Some(mint_data.clone()))),
+    ))?;
+
+    assert_eq!(
+        ix,
+        transfer_checked_with_fee(
+            &prog.program_id(),
+            &from,
+            &mint,
+            &to,
+            &authority.pubkey(),
+            &[&authority.pubkey()],
+            quote.sent,
+            6,
+            quote.fee.unwrap_or_default(),
+        )?
+    );
+
+    assert!(
+        TokenProgram::Legacy
+            .transfer_with_fee_ix(&from, &to, &authority, quote.sent, 6, &mint, 15_152)
+            .is_err()
+    );
+    Ok(())
+}
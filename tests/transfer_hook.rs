@@ -0,0 +1,146 @@
+use anyhow::Result;
+use futures::executor::block_on;
+use sol_unmint::TokenProgram;
+use solana_sdk::{
+    instruction::AccountMeta, program_pack::Pack, pubkey::Pubkey, signature::Keypair,
+};
+use spl_tlv_account_resolution::{
+    account::ExtraAccountMeta, seeds::Seed, state::ExtraAccountMetaList,
+};
+use spl_token_2022::{
+    extension::{
+        BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+        transfer_hook::TransferHook,
+    },
+    state::Mint,
+};
+use spl_transfer_hook_interface::{
+    get_extra_account_metas_address, instruction::ExecuteInstruction,
+};
+
+#[test]
+fn test_transfer_without_hook_has_no_extra_metas() -> Result<()> {
+    let prog = TokenProgram::Token2022;
+    let authority = Keypair::new();
+    let mint = Pubkey::new_unique();
+    let (from, to) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    let mut mint_data = vec![0; Mint::LEN];
+    Mint::pack(
+        Mint {
+            decimals: 6,
+            is_initialized: true,
+            ..Mint::default()
+        },
+        &mut mint_data,
+    )?;
+
+    let ix = block_on(prog.transfer_ix_with_extra_metas(
+        &from,
+        &to,
+        &authority,
+        1_000,
+        6,
+        &mint,
+        None,
+        |_| std::future::ready(Ok(Some(mint_data.clone()))),
+    ))?;
+
+    assert_eq!(
+        ix,
+        prog.transfer_ix(&from, &to, &authority, 1_000, 6, &mint)?
+    );
+    Ok(())
+}
+
+#[test]
+fn test_transfer_with_missing_mint() -> Result<()> {
+    let ix = block_on(TokenProgram::Token2022.transfer_ix_with_extra_metas(
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+        &Keypair::new(),
+        1_000,
+        6,
+        &Pubkey::new_unique(),
+        None,
+        |_| std::future::ready(Ok(None)),
+    ));
+
+    assert!(ix.is_err());
+    Ok(())
+}
+
+#[test]
+fn test_transfer_with_hook_appends_extra_metas() -> Result<()> {
+    let prog = TokenProgram::Token2022;
+    let authority = Keypair::new();
+    let mint = Pubkey::new_unique();
+    let hook_program = Pubkey::new_unique();
+    let (from, to) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    let mint_len =
+        ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::TransferHook])?;
+    let mut mint_data = vec![0; mint_len];
+    let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut mint_data)?;
+    state.init_extension::<TransferHook>(true)?.program_id.0 = hook_program;
+    state.base = Mint {
+        decimals: 6,
+        is_initialized: true,
+        ..Mint::default()
+    };
+    state.pack_base();
+    state.init_account_type()?;
+
+    // the hook needs a fixed account and a PDA of its program seeded with the source
+    let fixed = Pubkey::new_unique();
+    let extra_metas = [
+        ExtraAccountMeta::new_with_pubkey(&fixed, false, false)?,
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: b"counter".to_vec(),
+                },
+                Seed::AccountKey { index: 0 },
+            ],
+            false,
+            true,
+        )?,
+    ];
+    let validation = get_extra_account_metas_address(&mint, &hook_program);
+    let mut validation_data = vec![0; ExtraAccountMetaList::size_of(extra_metas.len())?];
+    ExtraAccountMetaList::init::<ExecuteInstruction>(&mut validation_data, &extra_metas)?;
+
+    let ix = block_on(prog.transfer_ix_with_extra_metas(
+        &from,
+        &to,
+        &authority,
+        1_000,
+        6,
+        &mint,
+        None,
+        |address| {
+            let data = if address == mint {
+                Some(mint_data.clone())
+            } else if address == validation {
+                Some(validation_data.clone())
+            } else {
+                None
+            };
+            std::future::ready(Ok(data))
+        },
+    ))?;
+
+    let counter = Pubkey::find_program_address(&[b"counter", from.as_ref()], &hook_program).0;
+    let plain = prog.transfer_ix(&from, &to, &authority, 1_000, 6, &mint)?;
+    assert_eq!(ix.accounts[..plain.accounts.len()], plain.accounts[..]);
+    assert_eq!(
+        ix.accounts[plain.accounts.len()..],
+        [
+            AccountMeta::new_readonly(fixed, false),
+            AccountMeta::new(counter, false),
+            AccountMeta::new_readonly(hook_program, false),
+            AccountMeta::new_readonly(validation, false),
+        ]
+    );
+    Ok(())
+}
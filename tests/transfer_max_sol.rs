@@ -0,0 +1,24 @@
+use anyhow::Result;
+use sol_unmint::{UnmintError, max_transferable};
+
+#[test]
+fn test_transfer_max_sol() -> Result<()> {
+    let balance = 1_000_000;
+    let fee = 5_000;
+    let rent_exempt = 890_880;
+
+    // DrainMode::KeepRentExempt
+    assert_eq!(max_transferable(balance, fee, rent_exempt)?, 104_120);
+    // DrainMode::Full
+    assert_eq!(max_transferable(balance, fee, 0)?, 995_000);
+
+    assert!(matches!(
+        max_transferable(balance, fee, balance - fee),
+        Err(UnmintError::InsufficientFunds(_))
+    ));
+    assert!(matches!(
+        max_transferable(fee - 1, fee, 0),
+        Err(UnmintError::InsufficientFunds(_))
+    ));
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use anyhow::Result;
+use sol_unmint::{TokenProgram, UnmintError};
+use solana_client::{
+    client_error::ClientError,
+    rpc_request::{RpcError, RpcResponseErrorData},
+};
+use solana_sdk::{
+    instruction::InstructionError, message::Message, pubkey::Pubkey, signature::Keypair,
+    signer::Signer, transaction::TransactionError,
+};
+use spl_token_2022::error::TokenError;
+
+#[test]
+fn test_decode_token_error() -> Result<()> {
+    let owner = Keypair::new();
+    let mint = Pubkey::new_unique();
+    let prog = TokenProgram::Legacy;
+
+    let from = prog.ata(&owner.pubkey(), &mint);
+    let to = prog.ata(&Pubkey::new_unique(), &mint);
+    let ix = prog.transfer_ix(&from, &to, &owner, 1, 6, &mint)?;
+    let message = Message::new(&[ix], Some(&owner.pubkey()));
+
+    // TokenError::InsufficientFunds
+    let error = TransactionError::InstructionError(0, InstructionError::Custom(1));
+    match UnmintError::from_transaction_error(error, Some(&message)) {
+        UnmintError::Token { index, error } => {
+            assert_eq!(index, 0);
+            assert_eq!(error, TokenError::InsufficientFunds);
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+
+    let error = TransactionError::InstructionError(0, InstructionError::Custom(1));
+    assert!(matches!(
+        UnmintError::from_transaction_error(error, None),
+        UnmintError::Instruction { index: 0, .. }
+    ));
+
+    assert!(matches!(
+        UnmintError::from_transaction_error(TransactionError::BlockhashNotFound, None),
+        UnmintError::TransactionExpired
+    ));
+
+    // closing an account that still holds tokens names the account
+    let close = prog.close_ix(&from, &owner.pubkey(), &owner)?;
+    let message = Message::new(&[close], Some(&owner.pubkey()));
+    let error = TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(TokenError::NonNativeHasBalance as u32),
+    );
+    assert!(matches!(
+        UnmintError::from_transaction_error(error, Some(&message)),
+        UnmintError::NonZeroBalance(account) if account == from
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_decode_client_error() -> Result<()> {
+    let error = ClientError::from(TransactionError::BlockhashNotFound);
+    assert!(matches!(
+        UnmintError::from_client_error(error, None),
+        UnmintError::TransactionExpired
+    ));
+
+    // RPC messages are never parsed; a missing account is found by fetching it
+    let error = ClientError::from(RpcError::RpcResponseError {
+        code: -32602,
+        message: "Invalid param: could not find account".to_string(),
+        data: RpcResponseErrorData::Empty,
+    });
+    assert!(matches!(
+        UnmintError::from_client_error(error, None),
+        UnmintError::Rpc(_)
+    ));
+    Ok(())
+}